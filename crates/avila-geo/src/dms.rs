@@ -0,0 +1,345 @@
+// Notações textuais de coordenadas: graus decimais, DMS e DDM
+// Aceita letras de hemisfério antes ou depois do valor e os símbolos ° ' " (ou ′ ″)
+
+use crate::Coordinate;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseCoordinateError {
+    Empty,
+    UnexpectedCharacter(char),
+    /// Wrong number of values or hemisphere letters
+    InvalidFormat(&'static str),
+    /// Minutes/seconds outside 0..60, or fractional degrees followed by minutes
+    InvalidComponent(&'static str),
+    OutOfRange { field: &'static str, value: f64 },
+}
+
+impl fmt::Display for ParseCoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseCoordinateError::Empty => write!(f, "empty coordinate"),
+            ParseCoordinateError::UnexpectedCharacter(c) => write!(f, "unexpected character '{}'", c),
+            ParseCoordinateError::InvalidFormat(message) | ParseCoordinateError::InvalidComponent(message) => {
+                write!(f, "{}", message)
+            }
+            ParseCoordinateError::OutOfRange { field, value } => {
+                write!(f, "{}", crate::CoordinateError::OutOfRange { field, value: *value })
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseCoordinateError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Axis {
+    Latitude,
+    Longitude,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token {
+    Number(f64, bool),
+    Hemisphere(char),
+    Separator,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseCoordinateError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            '0'..='9' | '.' | '+' | '-' => {
+                let mut text = String::new();
+                while let Some(&d) = chars.peek() {
+                    if d.is_ascii_digit() || d == '.' || (text.is_empty() && (d == '+' || d == '-')) {
+                        text.push(d);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                let value: f64 = text.parse().map_err(|_| ParseCoordinateError::UnexpectedCharacter(c))?;
+                tokens.push(Token::Number(value, text.starts_with('-')));
+            }
+            'N' | 'S' | 'E' | 'W' | 'n' | 's' | 'e' | 'w' => {
+                tokens.push(Token::Hemisphere(c.to_ascii_uppercase()));
+                chars.next();
+            }
+            ',' | ';' => {
+                tokens.push(Token::Separator);
+                chars.next();
+            }
+            '°' | 'º' | '˚' | '\'' | '′' | '’' | '"' | '″' | '”' => {
+                chars.next();
+            }
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            other => return Err(ParseCoordinateError::UnexpectedCharacter(other)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Degrees, minutes and seconds (each optional after the first) plus hemisphere
+fn parse_component(tokens: &[Token], axis: Option<Axis>) -> Result<(f64, Axis), ParseCoordinateError> {
+    let mut numbers = Vec::new();
+    let mut hemisphere = None;
+    for token in tokens {
+        match token {
+            Token::Number(value, negative) => numbers.push((*value, *negative)),
+            Token::Hemisphere(h) if hemisphere.is_none() => hemisphere = Some(*h),
+            _ => return Err(ParseCoordinateError::InvalidFormat("more than one hemisphere letter")),
+        }
+    }
+    if numbers.is_empty() || numbers.len() > 3 {
+        return Err(ParseCoordinateError::InvalidFormat("expected degrees, minutes and seconds"));
+    }
+
+    let negative = numbers[0].1;
+    let mut value = numbers[0].0.abs();
+    for (i, (part, sign)) in numbers.iter().enumerate().skip(1) {
+        if *sign || !(0.0..60.0).contains(part) {
+            return Err(ParseCoordinateError::InvalidComponent("minutes and seconds must be in 0..60"));
+        }
+        if numbers[i - 1].0.fract() != 0.0 {
+            return Err(ParseCoordinateError::InvalidComponent("only the last value may have decimals"));
+        }
+        value += part / 60f64.powi(i as i32);
+    }
+
+    let axis = match (hemisphere, axis) {
+        (Some('N' | 'S'), Some(Axis::Longitude)) | (Some('E' | 'W'), Some(Axis::Latitude)) => {
+            return Err(ParseCoordinateError::InvalidFormat("hemisphere letter doesn't match the axis"));
+        }
+        (Some('N' | 'S'), _) => Axis::Latitude,
+        (Some(_), _) => Axis::Longitude,
+        (None, Some(axis)) => axis,
+        (None, None) => Axis::Latitude,
+    };
+    if negative && hemisphere.is_some() {
+        return Err(ParseCoordinateError::InvalidFormat("both a sign and a hemisphere letter"));
+    }
+    if negative || matches!(hemisphere, Some('S' | 'W')) {
+        value = -value;
+    }
+
+    let (field, limit) = match axis {
+        Axis::Latitude => ("latitude", 90.0),
+        Axis::Longitude => ("longitude", 180.0),
+    };
+    if value.abs() > limit {
+        return Err(ParseCoordinateError::OutOfRange { field, value });
+    }
+    Ok((value, axis))
+}
+
+/// Parse a single latitude such as `25°11'49.9"N`, `N25 11.832` or `-33.8568`
+pub fn parse_latitude(input: &str) -> Result<f64, ParseCoordinateError> {
+    parse_axis(input, Axis::Latitude)
+}
+
+/// Parse a single longitude such as `55°16'27.8"E`, `W0 7.5` or `151.2153`
+pub fn parse_longitude(input: &str) -> Result<f64, ParseCoordinateError> {
+    parse_axis(input, Axis::Longitude)
+}
+
+fn parse_axis(input: &str, axis: Axis) -> Result<f64, ParseCoordinateError> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err(ParseCoordinateError::Empty);
+    }
+    parse_component(&tokens, Some(axis)).map(|(value, _)| value)
+}
+
+/// Split a full coordinate into its two halves
+fn split(tokens: &[Token]) -> Result<(&[Token], &[Token]), ParseCoordinateError> {
+    if let Some(i) = tokens.iter().position(|t| *t == Token::Separator) {
+        return Ok((&tokens[..i], &tokens[i + 1..]));
+    }
+
+    let hemispheres: Vec<usize> = tokens
+        .iter()
+        .enumerate()
+        .filter(|(_, t)| matches!(t, Token::Hemisphere(_)))
+        .map(|(i, _)| i)
+        .collect();
+    match hemispheres.as_slice() {
+        // Prefixo: N25 11.832 E55 16.464
+        [0, second] => Ok((&tokens[..*second], &tokens[*second..])),
+        // Sufixo: 25°11'49.9"N 55°16'27.8"E
+        [first, _] => Ok((&tokens[..=*first], &tokens[*first + 1..])),
+        [] if tokens.len().is_multiple_of(2) => Ok(tokens.split_at(tokens.len() / 2)),
+        _ => Err(ParseCoordinateError::InvalidFormat("can't tell latitude from longitude")),
+    }
+}
+
+impl FromStr for Coordinate {
+    type Err = ParseCoordinateError;
+
+    /// Decimal degrees (`25.1972, 55.2744`), DMS (`25°11'49.9"N 55°16'27.8"E`)
+    /// or DDM (`N25 11.832 E55 16.464`). Without hemisphere letters the
+    /// latitude comes first.
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(input)?;
+        if tokens.is_empty() {
+            return Err(ParseCoordinateError::Empty);
+        }
+
+        let (first, second) = split(&tokens)?;
+        let (a, axis_a) = parse_component(first, None)?;
+        // A segunda metade é sempre o outro eixo; uma letra conflitante é erro
+        let other = match axis_a {
+            Axis::Latitude => Axis::Longitude,
+            Axis::Longitude => Axis::Latitude,
+        };
+        let (b, _) = parse_component(second, Some(other))?;
+
+        Ok(match axis_a {
+            Axis::Latitude => Coordinate::new(a, b),
+            Axis::Longitude => Coordinate::new(b, a),
+        })
+    }
+}
+
+/// Decimal degrees, `25.197200, 55.274400`; the precision flag sets the decimals
+impl fmt::Display for Coordinate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let precision = f.precision().unwrap_or(6);
+        write!(f, "{:.*}, {:.*}", precision, self.latitude, precision, self.longitude)
+    }
+}
+
+/// Degrees-minutes-seconds display, `25°11'49.9"N 55°16'27.8"E`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dms(pub Coordinate);
+
+/// Degrees-decimal-minutes display, `25°11.832'N 55°16.464'E`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ddm(pub Coordinate);
+
+impl Coordinate {
+    pub fn dms(&self) -> Dms {
+        Dms(*self)
+    }
+
+    pub fn ddm(&self) -> Ddm {
+        Ddm(*self)
+    }
+}
+
+fn hemisphere(value: f64, positive: char, negative: char) -> char {
+    if value < 0.0 {
+        negative
+    } else {
+        positive
+    }
+}
+
+/// Split |value| into whole degrees and the remainder in `unit` fractions of a
+/// degree, rounded to `decimals` so that e.g. 59.99" carries into the minutes
+fn split_degrees(value: f64, units_per_degree: u64, decimals: usize) -> (u64, u64, u64) {
+    let scale = 10u64.pow(decimals as u32);
+    let total = (value.abs() * (units_per_degree * scale) as f64).round() as u64;
+    (total / (units_per_degree * scale), total % (units_per_degree * scale), scale)
+}
+
+fn write_dms(f: &mut fmt::Formatter<'_>, value: f64, decimals: usize, hemi: char) -> fmt::Result {
+    let (degrees, rest, scale) = split_degrees(value, 3600, decimals);
+    let minutes = rest / (60 * scale);
+    let seconds = (rest % (60 * scale)) as f64 / scale as f64;
+    let width = if decimals > 0 { decimals + 3 } else { 2 };
+    write!(f, "{}°{:02}'{:0width$.decimals$}\"{}", degrees, minutes, seconds, hemi)
+}
+
+fn write_ddm(f: &mut fmt::Formatter<'_>, value: f64, decimals: usize, hemi: char) -> fmt::Result {
+    let (degrees, rest, scale) = split_degrees(value, 60, decimals);
+    let minutes = rest as f64 / scale as f64;
+    let width = if decimals > 0 { decimals + 3 } else { 2 };
+    write!(f, "{}°{:0width$.decimals$}'{}", degrees, minutes, hemi)
+}
+
+impl fmt::Display for Dms {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f.precision().unwrap_or(1);
+        write_dms(f, self.0.latitude, decimals, hemisphere(self.0.latitude, 'N', 'S'))?;
+        write!(f, " ")?;
+        write_dms(f, self.0.longitude, decimals, hemisphere(self.0.longitude, 'E', 'W'))
+    }
+}
+
+impl fmt::Display for Ddm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let decimals = f.precision().unwrap_or(3);
+        write_ddm(f, self.0.latitude, decimals, hemisphere(self.0.latitude, 'N', 'S'))?;
+        write!(f, " ")?;
+        write_ddm(f, self.0.longitude, decimals, hemisphere(self.0.longitude, 'E', 'W'))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: Coordinate, lat: f64, lon: f64) -> bool {
+        (a.latitude - lat).abs() < 1e-9 && (a.longitude - lon).abs() < 1e-9
+    }
+
+    #[test]
+    fn test_parse_notations() {
+        let dms: Coordinate = "25°11'49.9\"N 55°16'27.8\"E".parse().unwrap();
+        assert!(close(dms, 25.0 + 11.0 / 60.0 + 49.9 / 3600.0, 55.0 + 16.0 / 60.0 + 27.8 / 3600.0));
+
+        let ddm: Coordinate = "N25 11.832 E55 16.464".parse().unwrap();
+        assert!(close(ddm, 25.0 + 11.832 / 60.0, 55.0 + 16.464 / 60.0));
+
+        let decimal: Coordinate = "-33.8568, 151.2153".parse().unwrap();
+        assert!(close(decimal, -33.8568, 151.2153));
+
+        // Ordem invertida com letras, símbolos Unicode e hemisfério oeste
+        let swapped: Coordinate = "0°07′30″W 51°30′N".parse().unwrap();
+        assert!(close(swapped, 51.5, -0.125));
+        let spaced: Coordinate = "40 26 46 S 79 58 56 W".parse().unwrap();
+        assert!(close(spaced, -(40.0 + 26.0 / 60.0 + 46.0 / 3600.0), -(79.0 + 58.0 / 60.0 + 56.0 / 3600.0)));
+
+        assert!((parse_latitude("S33 51.408").unwrap() + 33.8568).abs() < 1e-9);
+        assert_eq!(parse_longitude("151.2153E").unwrap(), 151.2153);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("".parse::<Coordinate>(), Err(ParseCoordinateError::Empty));
+        assert!(matches!("25 61 N 55 E".parse::<Coordinate>(), Err(ParseCoordinateError::InvalidComponent(_))));
+        assert!(matches!("25.5 30 N 55 E".parse::<Coordinate>(), Err(ParseCoordinateError::InvalidComponent(_))));
+        assert!(matches!("95 N 55 E".parse::<Coordinate>(), Err(ParseCoordinateError::OutOfRange { field: "latitude", .. })));
+        assert!(matches!("25 N 55 S".parse::<Coordinate>(), Err(ParseCoordinateError::InvalidFormat(_))));
+        assert!(matches!("25.1 55.2 7".parse::<Coordinate>(), Err(ParseCoordinateError::InvalidFormat(_))));
+        assert_eq!("25x".parse::<Coordinate>(), Err(ParseCoordinateError::UnexpectedCharacter('x')));
+        assert!(parse_latitude("55 E").is_err());
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let burj = crate::dubai::BURJ_KHALIFA;
+        assert_eq!(burj.to_string(), "25.197200, 55.274400");
+        assert_eq!(format!("{:.2}", burj), "25.20, 55.27");
+        assert_eq!(burj.dms().to_string(), "25°11'49.9\"N 55°16'27.8\"E");
+        assert_eq!(burj.ddm().to_string(), "25°11.832'N 55°16.464'E");
+
+        // Arredondamento propaga para minutos e graus
+        let edge = Coordinate::new(-10.999999, -0.0000001);
+        assert_eq!(edge.dms().to_string(), "11°00'00.0\"S 0°00'00.0\"W");
+        assert_eq!(format!("{:.0}", edge.ddm()), "11°00'S 0°00'W");
+
+        for coordinate in [burj, Coordinate::new(-33.8568, 151.2153), Coordinate::new(51.5, -0.125)] {
+            let back: Coordinate = format!("{:.3}", coordinate.dms()).parse().unwrap();
+            assert!(back.distance_to(&coordinate) < 1e-4);
+            let back: Coordinate = format!("{:.5}", coordinate.ddm()).parse().unwrap();
+            assert!(back.distance_to(&coordinate) < 1e-4);
+        }
+    }
+}
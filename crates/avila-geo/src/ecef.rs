@@ -0,0 +1,159 @@
+// Coordenadas cartesianas: ECEF (centrado na Terra, WGS84) e ENU local
+// Altitudes são elipsoidais, em metros
+
+use crate::{Coordinate, Ellipsoid};
+
+/// Earth-centred, Earth-fixed position in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ecef {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+/// East/North/Up offsets in meters from a reference point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Enu {
+    pub east: f64,
+    pub north: f64,
+    pub up: f64,
+}
+
+impl Ecef {
+    pub fn from_geodetic(coordinate: &Coordinate, altitude: f64) -> Ecef {
+        let Ellipsoid { a, f } = Ellipsoid::WGS84;
+        let e2 = f * (2.0 - f);
+        let (sin_lat, cos_lat) = coordinate.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = coordinate.longitude.to_radians().sin_cos();
+
+        // Raio de curvatura no primeiro vertical
+        let n = a / (1.0 - e2 * sin_lat * sin_lat).sqrt();
+        Ecef {
+            x: (n + altitude) * cos_lat * cos_lon,
+            y: (n + altitude) * cos_lat * sin_lon,
+            z: (n * (1.0 - e2) + altitude) * sin_lat,
+        }
+    }
+
+    /// Geodetic position and ellipsoidal altitude (closed form, Heikkinen 1982)
+    pub fn to_geodetic(&self) -> (Coordinate, f64) {
+        let Ellipsoid { a, f } = Ellipsoid::WGS84;
+        let b = a * (1.0 - f);
+        let e2 = f * (2.0 - f);
+        let ep2 = (a * a - b * b) / (b * b);
+        let (x, y, z) = (self.x, self.y, self.z);
+
+        let p = x.hypot(y);
+        let ff = 54.0 * b * b * z * z;
+        let g = p * p + (1.0 - e2) * z * z - e2 * (a * a - b * b);
+        let c = e2 * e2 * ff * p * p / (g * g * g);
+        let s = (1.0 + c + (c * c + 2.0 * c).sqrt()).cbrt();
+        let k = s + 1.0 + 1.0 / s;
+        let pp = ff / (3.0 * k * k * g * g);
+        let q = (1.0 + 2.0 * e2 * e2 * pp).sqrt();
+        let r0 = -pp * e2 * p / (1.0 + q)
+            + (a * a / 2.0 * (1.0 + 1.0 / q) - pp * (1.0 - e2) * z * z / (q * (1.0 + q)) - pp * p * p / 2.0)
+                .max(0.0)
+                .sqrt();
+        let u = ((p - e2 * r0).powi(2) + z * z).sqrt();
+        let v = ((p - e2 * r0).powi(2) + (1.0 - e2) * z * z).sqrt();
+        let z0 = b * b * z / (a * v);
+
+        let latitude = (z + ep2 * z0).atan2(p).to_degrees();
+        let longitude = y.atan2(x).to_degrees();
+        (Coordinate::new(latitude, longitude), u * (1.0 - b * b / (a * v)))
+    }
+
+    /// Offset of this point in the local frame at `reference`
+    pub fn to_enu(&self, reference: &Coordinate, reference_altitude: f64) -> Enu {
+        let origin = Ecef::from_geodetic(reference, reference_altitude);
+        let (dx, dy, dz) = (self.x - origin.x, self.y - origin.y, self.z - origin.z);
+        let (sin_lat, cos_lat) = reference.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = reference.longitude.to_radians().sin_cos();
+
+        Enu {
+            east: -sin_lon * dx + cos_lon * dy,
+            north: -sin_lat * cos_lon * dx - sin_lat * sin_lon * dy + cos_lat * dz,
+            up: cos_lat * cos_lon * dx + cos_lat * sin_lon * dy + sin_lat * dz,
+        }
+    }
+}
+
+impl Enu {
+    /// Local offsets of a geodetic point relative to `reference`
+    pub fn from_geodetic(
+        coordinate: &Coordinate,
+        altitude: f64,
+        reference: &Coordinate,
+        reference_altitude: f64,
+    ) -> Enu {
+        Ecef::from_geodetic(coordinate, altitude).to_enu(reference, reference_altitude)
+    }
+
+    pub fn to_ecef(&self, reference: &Coordinate, reference_altitude: f64) -> Ecef {
+        let origin = Ecef::from_geodetic(reference, reference_altitude);
+        let (sin_lat, cos_lat) = reference.latitude.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = reference.longitude.to_radians().sin_cos();
+        let (e, n, u) = (self.east, self.north, self.up);
+
+        // Transposta da rotação usada em to_enu
+        Ecef {
+            x: origin.x - sin_lon * e - sin_lat * cos_lon * n + cos_lat * cos_lon * u,
+            y: origin.y + cos_lon * e - sin_lat * sin_lon * n + cos_lat * sin_lon * u,
+            z: origin.z + cos_lat * n + sin_lat * u,
+        }
+    }
+
+    pub fn to_geodetic(&self, reference: &Coordinate, reference_altitude: f64) -> (Coordinate, f64) {
+        self.to_ecef(reference, reference_altitude).to_geodetic()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ecef_reference_points() {
+        let origin = Ecef::from_geodetic(&Coordinate::new(0.0, 0.0), 0.0);
+        assert!((origin.x - 6378137.0).abs() < 1e-6 && origin.y.abs() < 1e-6 && origin.z.abs() < 1e-6);
+
+        let pole = Ecef::from_geodetic(&Coordinate::new(90.0, 0.0), 100.0);
+        assert!((pole.z - 6356852.314245).abs() < 1e-5);
+        let (coordinate, altitude) = pole.to_geodetic();
+        assert!((coordinate.latitude - 90.0).abs() < 1e-9 && (altitude - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_ecef_round_trip() {
+        for lat in (-89..=89).step_by(11) {
+            for lon in (-180..180).step_by(17) {
+                for altitude in [-400.0, 0.0, 8848.0, 400_000.0] {
+                    let coordinate = Coordinate::new(lat as f64 + 0.3, lon as f64 + 0.7);
+                    let (back, back_altitude) = Ecef::from_geodetic(&coordinate, altitude).to_geodetic();
+                    assert!(back.distance_to(&coordinate) < 1e-9, "{:?} {:?}", coordinate, back);
+                    assert!((back_altitude - altitude).abs() < 1e-6);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_enu() {
+        let reference = crate::dubai::BURJ_KHALIFA;
+
+        // 1 km ao norte e 828 m acima
+        let north = reference.destination(1.0, 0.0);
+        let enu = Enu::from_geodetic(&north, 828.0, &reference, 0.0);
+        assert!(enu.east.abs() < 1e-6);
+        assert!((enu.north - 1000.0).abs() < 5.0);
+        assert!((enu.up - 828.0).abs() < 0.5);
+
+        let offset = Enu { east: 120.5, north: -45.25, up: 12.0 };
+        let (coordinate, altitude) = offset.to_geodetic(&reference, 5.0);
+        let back = Enu::from_geodetic(&coordinate, altitude, &reference, 5.0);
+        assert!((back.east - offset.east).abs() < 1e-6);
+        assert!((back.north - offset.north).abs() < 1e-6);
+        assert!((back.up - offset.up).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,840 @@
+// Geodésicas elipsoidais (Karney, 2013): problemas inverso e direto
+// Séries de ordem 6 em n, precisão sub-milimétrica e convergência perto dos antípodas
+
+use crate::Coordinate;
+use std::f64::consts::PI;
+
+const ORDER: usize = 6;
+const MAXIT1: usize = 20;
+const MAXIT2: usize = MAXIT1 + f64::MANTISSA_DIGITS as usize + 10;
+
+const TOL0: f64 = f64::EPSILON;
+const TOL1: f64 = 200.0 * TOL0;
+const TOLB: f64 = TOL0 * 1.4901161193847656e-8; // TOL0 * sqrt(TOL0)
+const XTHRESH: f64 = 1000.0 * 1.4901161193847656e-8;
+const TINY: f64 = 1.4916681462400413e-154; // sqrt(f64::MIN_POSITIVE)
+
+/// Reference ellipsoid given by its equatorial radius (meters) and flattening
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ellipsoid {
+    pub a: f64,
+    pub f: f64,
+}
+
+impl Ellipsoid {
+    pub const WGS84: Ellipsoid = Ellipsoid { a: 6378137.0, f: 1.0 / 298.257223563 };
+    pub const GRS80: Ellipsoid = Ellipsoid { a: 6378137.0, f: 1.0 / 298.257222101 };
+
+    pub fn new(a: f64, f: f64) -> Self {
+        Ellipsoid { a, f }
+    }
+
+    /// Polar semi-axis (meters)
+    pub fn b(&self) -> f64 {
+        self.a * (1.0 - self.f)
+    }
+
+    /// Shortest path between two points on the ellipsoid
+    pub fn inverse(&self, from: &Coordinate, to: &Coordinate) -> Inverse {
+        Geodesic::new(self).inverse(from.latitude, from.longitude, to.latitude, to.longitude)
+    }
+
+    /// Point reached after `distance_m` along the geodesic leaving `from` at `azimuth` degrees
+    pub fn direct(&self, from: &Coordinate, azimuth: f64, distance_m: f64) -> Direct {
+        Geodesic::new(self).direct(from.latitude, from.longitude, azimuth, distance_m)
+    }
+
+    /// Geodesic distance in meters
+    pub fn distance(&self, from: &Coordinate, to: &Coordinate) -> f64 {
+        self.inverse(from, to).distance
+    }
+}
+
+impl Default for Ellipsoid {
+    fn default() -> Self {
+        Ellipsoid::WGS84
+    }
+}
+
+/// Solution of the inverse problem; azimuths in degrees clockwise from north, (-180, 180]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Inverse {
+    pub distance: f64,
+    pub initial_azimuth: f64,
+    pub final_azimuth: f64,
+}
+
+/// Solution of the direct problem
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Direct {
+    pub coordinate: Coordinate,
+    pub final_azimuth: f64,
+}
+
+// Auxiliares numéricos
+
+fn sq(x: f64) -> f64 {
+    x * x
+}
+
+fn norm(x: f64, y: f64) -> (f64, f64) {
+    let r = x.hypot(y);
+    (x / r, y / r)
+}
+
+/// Error-free sum: returns the rounded sum and its rounding error
+fn sum(u: f64, v: f64) -> (f64, f64) {
+    let s = u + v;
+    let up = s - v;
+    let vpp = s - up;
+    (s, -((up - u) + (vpp - v)))
+}
+
+/// `max(x, 0)` that never yields -0.0, which would flip the sign of a later atan2
+fn positive(x: f64) -> f64 {
+    if x > 0.0 {
+        x
+    } else {
+        0.0
+    }
+}
+
+fn polyval(n: usize, p: &[f64], x: f64) -> f64 {
+    p[..=n].iter().fold(0.0, |y, c| y * x + c)
+}
+
+fn remainder(x: f64, y: f64) -> f64 {
+    let z = x % y;
+    if z < -y / 2.0 {
+        z + y
+    } else if z >= y / 2.0 {
+        z - y
+    } else {
+        z
+    }
+}
+
+fn ang_normalize(x: f64) -> f64 {
+    let y = remainder(x, 360.0);
+    if y == -180.0 {
+        180.0
+    } else {
+        y
+    }
+}
+
+/// Exact `y - x` in degrees, reduced to [-180, 180], plus its rounding error
+fn ang_diff(x: f64, y: f64) -> (f64, f64) {
+    let (d, t) = sum(ang_normalize(-x), ang_normalize(y));
+    let d = ang_normalize(d);
+    sum(if d == 180.0 && t > 0.0 { -180.0 } else { d }, t)
+}
+
+/// Round tiny angles so that sums near zero stay exact
+fn ang_round(x: f64) -> f64 {
+    const Z: f64 = 1.0 / 16.0;
+    let y = x.abs();
+    let w = Z - y;
+    (if w > 0.0 { Z - w } else { y }).copysign(x)
+}
+
+fn sincosd(x: f64) -> (f64, f64) {
+    let r = x % 360.0;
+    let q = if r.is_nan() { 0.0 } else { (r / 90.0).round() };
+    let (s, c) = (r - 90.0 * q).to_radians().sin_cos();
+    match (q as i64).rem_euclid(4) {
+        0 => (s, c),
+        1 => (c, -s),
+        2 => (-s, -c),
+        _ => (-c, s),
+    }
+}
+
+fn atan2d(y: f64, x: f64) -> f64 {
+    let (mut x, mut y) = (x, y);
+    let mut q = 0;
+    if y.abs() > x.abs() {
+        std::mem::swap(&mut x, &mut y);
+        q = 2;
+    }
+    if x < 0.0 {
+        q += 1;
+        x = -x;
+    }
+    let ang = y.atan2(x).to_degrees();
+    match q {
+        1 => (if y >= 0.0 { 180.0 } else { -180.0 }) - ang,
+        2 => 90.0 - ang,
+        3 => -90.0 + ang,
+        _ => ang,
+    }
+}
+
+fn lat_fix(x: f64) -> f64 {
+    if x.abs() > 90.0 {
+        f64::NAN
+    } else {
+        x
+    }
+}
+
+/// Clenshaw summation of sum(c[l] * sin(2 l x)), l = 1..c.len()-1
+fn sin_series(sinx: f64, cosx: f64, c: &[f64]) -> f64 {
+    let mut k = c.len();
+    let mut n = k - 1;
+    let ar = 2.0 * (cosx - sinx) * (cosx + sinx);
+    let mut y0 = if n & 1 == 1 {
+        k -= 1;
+        c[k]
+    } else {
+        0.0
+    };
+    let mut y1 = 0.0;
+    n /= 2;
+    while n > 0 {
+        n -= 1;
+        k -= 1;
+        y1 = ar * y0 - y1 + c[k];
+        k -= 1;
+        y0 = ar * y1 - y0 + c[k];
+    }
+    2.0 * sinx * cosx * y0
+}
+
+// Coeficientes das séries (Karney 2013, eqs. 17, 18, 20, 21, 24, 25)
+
+fn a1m1f(eps: f64) -> f64 {
+    const COEFF: [f64; 5] = [1.0, 4.0, 64.0, 0.0, 256.0];
+    let t = polyval(3, &COEFF, sq(eps)) / COEFF[4];
+    (t + eps) / (1.0 - eps)
+}
+
+fn a2m1f(eps: f64) -> f64 {
+    const COEFF: [f64; 5] = [-11.0, -28.0, -192.0, 0.0, 256.0];
+    let t = polyval(3, &COEFF, sq(eps)) / COEFF[4];
+    (t - eps) / (1.0 + eps)
+}
+
+/// Shared layout of the C1, C1' and C2 coefficient tables
+fn series(coeff: &[f64; 18], eps: f64) -> [f64; ORDER + 1] {
+    let mut c = [0.0; ORDER + 1];
+    let eps2 = sq(eps);
+    let mut d = eps;
+    let mut o = 0;
+    for (l, value) in c.iter_mut().enumerate().skip(1) {
+        let m = (ORDER - l) / 2;
+        *value = d * polyval(m, &coeff[o..], eps2) / coeff[o + m + 1];
+        o += m + 2;
+        d *= eps;
+    }
+    c
+}
+
+fn c1f(eps: f64) -> [f64; ORDER + 1] {
+    const COEFF: [f64; 18] = [
+        -1.0, 6.0, -16.0, 32.0, -9.0, 64.0, -128.0, 2048.0, 9.0, -16.0, 768.0, 3.0, -5.0, 512.0,
+        -7.0, 1280.0, -7.0, 2048.0,
+    ];
+    series(&COEFF, eps)
+}
+
+fn c1pf(eps: f64) -> [f64; ORDER + 1] {
+    const COEFF: [f64; 18] = [
+        205.0, -432.0, 768.0, 1536.0, 4005.0, -4736.0, 3840.0, 12288.0, -225.0, 116.0, 384.0,
+        -7173.0, 2695.0, 7680.0, 3467.0, 7680.0, 38081.0, 61440.0,
+    ];
+    series(&COEFF, eps)
+}
+
+fn c2f(eps: f64) -> [f64; ORDER + 1] {
+    const COEFF: [f64; 18] = [
+        1.0, 2.0, 16.0, 32.0, 35.0, 64.0, 384.0, 2048.0, 15.0, 80.0, 768.0, 7.0, 35.0, 512.0, 63.0,
+        1280.0, 77.0, 2048.0,
+    ];
+    series(&COEFF, eps)
+}
+
+/// Quantities derived from the ellipsoid that the algorithms need repeatedly
+struct Geodesic {
+    a: f64,
+    f: f64,
+    f1: f64,
+    ep2: f64,
+    n: f64,
+    b: f64,
+    etol2: f64,
+    a3x: [f64; ORDER],
+    c3x: [f64; 15],
+}
+
+struct Lambda {
+    lam12: f64,
+    salp2: f64,
+    calp2: f64,
+    sig12: f64,
+    ssig1: f64,
+    csig1: f64,
+    ssig2: f64,
+    csig2: f64,
+    eps: f64,
+    dlam12: f64,
+}
+
+impl Geodesic {
+    fn new(ellipsoid: &Ellipsoid) -> Self {
+        let (a, f) = (ellipsoid.a, ellipsoid.f);
+        let f1 = 1.0 - f;
+        let e2 = f * (2.0 - f);
+        let n = f / (2.0 - f);
+
+        const A3: [f64; 18] = [
+            -3.0, 128.0, -2.0, -3.0, 64.0, -1.0, -3.0, -1.0, 16.0, 3.0, -1.0, -2.0, 8.0, 1.0, -1.0,
+            2.0, 1.0, 1.0,
+        ];
+        let mut a3x = [0.0; ORDER];
+        let mut o = 0;
+        for (k, j) in (0..ORDER).rev().enumerate() {
+            let m = (ORDER - j - 1).min(j);
+            a3x[k] = polyval(m, &A3[o..], n) / A3[o + m + 1];
+            o += m + 2;
+        }
+
+        const C3: [f64; 45] = [
+            3.0, 128.0, 2.0, 5.0, 128.0, -1.0, 3.0, 3.0, 64.0, -1.0, 0.0, 1.0, 8.0, -1.0, 1.0, 4.0,
+            5.0, 256.0, 1.0, 3.0, 128.0, -3.0, -2.0, 3.0, 64.0, 1.0, -3.0, 2.0, 32.0, 7.0, 512.0,
+            -10.0, 9.0, 384.0, 5.0, -9.0, 5.0, 192.0, 7.0, 512.0, -14.0, 7.0, 512.0, 21.0, 2560.0,
+        ];
+        let mut c3x = [0.0; 15];
+        let (mut o, mut k) = (0, 0);
+        for l in 1..ORDER {
+            for j in (l..ORDER).rev() {
+                let m = (ORDER - j - 1).min(j);
+                c3x[k] = polyval(m, &C3[o..], n) / C3[o + m + 1];
+                k += 1;
+                o += m + 2;
+            }
+        }
+
+        let tol2 = TOL0.sqrt();
+        Geodesic {
+            a,
+            f,
+            f1,
+            ep2: e2 / sq(f1),
+            n,
+            b: a * f1,
+            etol2: 0.1 * tol2 / ((f.abs().max(0.001) * (1.0 - f / 2.0).min(1.0) / 2.0).sqrt()),
+            a3x,
+            c3x,
+        }
+    }
+
+    fn a3f(&self, eps: f64) -> f64 {
+        polyval(ORDER - 1, &self.a3x, eps)
+    }
+
+    fn c3f(&self, eps: f64) -> [f64; ORDER] {
+        let mut c = [0.0; ORDER];
+        let mut mult = 1.0;
+        let mut o = 0;
+        for (l, value) in c.iter_mut().enumerate().skip(1) {
+            let m = ORDER - l - 1;
+            mult *= eps;
+            *value = mult * polyval(m, &self.c3x[o..], eps);
+            o += m + 1;
+        }
+        c
+    }
+
+    /// Reduced distance `s12 / b` and reduced length `m12 / b`, plus `m0`
+    fn lengths(
+        &self,
+        eps: f64,
+        sig12: f64,
+        (ssig1, csig1, dn1): (f64, f64, f64),
+        (ssig2, csig2, dn2): (f64, f64, f64),
+    ) -> (f64, f64, f64) {
+        let c1a = c1f(eps);
+        let c2a = c2f(eps);
+        let m0x = a1m1f(eps) - a2m1f(eps);
+        let a1 = 1.0 + a1m1f(eps);
+        let a2 = 1.0 + a2m1f(eps);
+
+        let b1 = sin_series(ssig2, csig2, &c1a) - sin_series(ssig1, csig1, &c1a);
+        let b2 = sin_series(ssig2, csig2, &c2a) - sin_series(ssig1, csig1, &c2a);
+        let s12b = a1 * (sig12 + b1);
+        let j12 = m0x * sig12 + (a1 * b1 - a2 * b2);
+        let m12b = dn2 * (csig1 * ssig2) - dn1 * (ssig1 * csig2) - csig1 * csig2 * j12;
+        (s12b, m12b, m0x)
+    }
+
+    /// Solve the astroid equation k^4 + 2k^3 - (x^2 + y^2 - 1)k^2 - 2y^2 k - y^2 = 0
+    fn astroid(x: f64, y: f64) -> f64 {
+        let p = sq(x);
+        let q = sq(y);
+        let r = (p + q - 1.0) / 6.0;
+        if q == 0.0 && r <= 0.0 {
+            return 0.0;
+        }
+
+        let s = p * q / 4.0;
+        let r2 = sq(r);
+        let r3 = r * r2;
+        let disc = s * (s + 2.0 * r3);
+        let mut u = r;
+        if disc >= 0.0 {
+            let mut t3 = s + r3;
+            t3 += if t3 < 0.0 { -disc.sqrt() } else { disc.sqrt() };
+            let t = t3.cbrt();
+            u += t + if t != 0.0 { r2 / t } else { 0.0 };
+        } else {
+            let ang = (-disc).sqrt().atan2(-(s + r3));
+            u += 2.0 * r * (ang / 3.0).cos();
+        }
+        let v = (sq(u) + q).sqrt();
+        let uv = if u < 0.0 { q / (v - u) } else { u + v };
+        let w = (uv - q) / (2.0 * v);
+        uv / ((uv + sq(w)).sqrt() + w)
+    }
+
+    /// Starting azimuth for Newton's method; returns `sig12 >= 0` when the
+    /// short-line approximation is already exact enough
+    fn inverse_start(
+        &self,
+        (sbet1, cbet1, dn1): (f64, f64, f64),
+        (sbet2, cbet2, dn2): (f64, f64, f64),
+        lam12: f64,
+        slam12: f64,
+        clam12: f64,
+    ) -> (f64, f64, f64, f64, f64, f64) {
+        let mut sig12 = -1.0;
+        let (mut salp2, mut calp2, mut dnm) = (f64::NAN, f64::NAN, f64::NAN);
+
+        let sbet12 = sbet2 * cbet1 - cbet2 * sbet1;
+        let cbet12 = cbet2 * cbet1 + sbet2 * sbet1;
+        let sbet12a = sbet2 * cbet1 + cbet2 * sbet1;
+        let shortline = cbet12 >= 0.0 && sbet12 < 0.5 && cbet2 * lam12 < 0.5;
+
+        let (mut somg12, mut comg12) = if shortline {
+            let mut sbetm2 = sq(sbet1 + sbet2);
+            sbetm2 /= sbetm2 + sq(cbet1 + cbet2);
+            dnm = (1.0 + self.ep2 * sbetm2).sqrt();
+            (lam12 / (self.f1 * dnm)).sin_cos()
+        } else {
+            (slam12, clam12)
+        };
+
+        let mut salp1 = cbet2 * somg12;
+        let mut calp1 = if comg12 >= 0.0 {
+            sbet12 + cbet2 * sbet1 * sq(somg12) / (1.0 + comg12)
+        } else {
+            sbet12a - cbet2 * sbet1 * sq(somg12) / (1.0 - comg12)
+        };
+
+        let ssig12 = salp1.hypot(calp1);
+        let csig12 = sbet1 * sbet2 + cbet1 * cbet2 * comg12;
+
+        if shortline && ssig12 < self.etol2 {
+            // Distâncias curtas: aproximação esférica com raio médio já é exata
+            let s = cbet1 * somg12;
+            let c = sbet12
+                - cbet1 * sbet2 * if comg12 >= 0.0 { sq(somg12) / (1.0 + comg12) } else { 1.0 - comg12 };
+            (salp2, calp2) = norm(s, c);
+            sig12 = ssig12.atan2(csig12);
+        } else if self.n.abs() > 0.1 || csig12 >= 0.0 || ssig12 >= 6.0 * self.n.abs() * PI * sq(cbet1) {
+            // Chute inicial esférico é bom o bastante
+        } else {
+            // Quase antípodas: resolver a astroide
+            let lam12x = (-slam12).atan2(-clam12);
+            let (x, y, lamscale) = if self.f >= 0.0 {
+                let k2 = sq(sbet1) * self.ep2;
+                let eps = k2 / (2.0 * (1.0 + (1.0 + k2).sqrt()) + k2);
+                let lamscale = self.f * cbet1 * self.a3f(eps) * PI;
+                let betscale = lamscale * cbet1;
+                (lam12x / lamscale, sbet12a / betscale, lamscale)
+            } else {
+                let cbet12a = cbet2 * cbet1 - sbet2 * sbet1;
+                let bet12a = sbet12a.atan2(cbet12a);
+                let (_, m12b, m0) =
+                    self.lengths(self.n, PI + bet12a, (sbet1, -cbet1, dn1), (sbet2, cbet2, dn2));
+                let x = -1.0 + m12b / (cbet1 * cbet2 * m0 * PI);
+                let betscale = if x < -0.01 { sbet12a / x } else { -self.f * sq(cbet1) * PI };
+                let lamscale = betscale / cbet1;
+                (x, lam12x / lamscale, lamscale)
+            };
+
+            if y > -TOL1 && x > -1.0 - XTHRESH {
+                if self.f >= 0.0 {
+                    salp1 = (-x).min(1.0);
+                    calp1 = -(1.0 - sq(salp1)).sqrt();
+                } else {
+                    calp1 = x.max(if x > -TOL1 { 0.0 } else { -1.0 });
+                    salp1 = (1.0 - sq(calp1)).sqrt();
+                }
+            } else {
+                let k = Self::astroid(x, y);
+                let omg12a = lamscale * if self.f >= 0.0 { -x * k / (1.0 + k) } else { -y * (1.0 + k) / k };
+                (somg12, comg12) = omg12a.sin_cos();
+                comg12 = -comg12;
+                salp1 = cbet2 * somg12;
+                calp1 = sbet12a - cbet2 * sbet1 * sq(somg12) / (1.0 - comg12);
+            }
+        }
+
+        if salp1 > 0.0 {
+            (salp1, calp1) = norm(salp1, calp1);
+        } else {
+            salp1 = 1.0;
+            calp1 = 0.0;
+        }
+        (sig12, salp1, calp1, salp2, calp2, dnm)
+    }
+
+    /// Longitude difference reached by the geodesic with starting azimuth alp1,
+    /// and its derivative with respect to alp1 when `diffp`
+    #[allow(clippy::too_many_arguments)]
+    fn lambda12(
+        &self,
+        (sbet1, cbet1, dn1): (f64, f64, f64),
+        (sbet2, cbet2, dn2): (f64, f64, f64),
+        salp1: f64,
+        mut calp1: f64,
+        slam120: f64,
+        clam120: f64,
+        diffp: bool,
+    ) -> Lambda {
+        if sbet1 == 0.0 && calp1 == 0.0 {
+            calp1 = -TINY;
+        }
+
+        let salp0 = salp1 * cbet1;
+        let calp0 = calp1.hypot(salp1 * sbet1);
+
+        let somg1 = salp0 * sbet1;
+        let comg1 = calp1 * cbet1;
+        let (ssig1, csig1) = norm(sbet1, comg1);
+
+        let salp2 = if cbet2 != cbet1 { salp0 / cbet2 } else { salp1 };
+        let calp2 = if cbet2 != cbet1 || sbet2.abs() != -sbet1 {
+            (sq(calp1 * cbet1)
+                + if cbet1 < -sbet1 {
+                    (cbet2 - cbet1) * (cbet1 + cbet2)
+                } else {
+                    (sbet1 - sbet2) * (sbet1 + sbet2)
+                })
+            .sqrt()
+                / cbet2
+        } else {
+            calp1.abs()
+        };
+
+        let somg2 = salp0 * sbet2;
+        let comg2 = calp2 * cbet2;
+        let (ssig2, csig2) = norm(sbet2, comg2);
+
+        let sig12 = positive(csig1 * ssig2 - ssig1 * csig2).atan2(csig1 * csig2 + ssig1 * ssig2);
+        let somg12 = positive(comg1 * somg2 - somg1 * comg2);
+        let comg12 = comg1 * comg2 + somg1 * somg2;
+        let eta = (somg12 * clam120 - comg12 * slam120).atan2(comg12 * clam120 + somg12 * slam120);
+
+        let k2 = sq(calp0) * self.ep2;
+        let eps = k2 / (2.0 * (1.0 + (1.0 + k2).sqrt()) + k2);
+        let c3a = self.c3f(eps);
+        let b312 = sin_series(ssig2, csig2, &c3a) - sin_series(ssig1, csig1, &c3a);
+        let lam12 = eta - self.f * self.a3f(eps) * salp0 * (sig12 + b312);
+
+        let dlam12 = if !diffp {
+            f64::NAN
+        } else if calp2 == 0.0 {
+            -2.0 * self.f1 * dn1 / sbet1
+        } else {
+            let (_, m12b, _) = self.lengths(eps, sig12, (ssig1, csig1, dn1), (ssig2, csig2, dn2));
+            m12b * self.f1 / (calp2 * cbet2)
+        };
+
+        Lambda { lam12, salp2, calp2, sig12, ssig1, csig1, ssig2, csig2, eps, dlam12 }
+    }
+
+    fn inverse(&self, lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> Inverse {
+        // Reduzir ao caso canônico: lon12 >= 0, |lat1| >= |lat2|, lat1 <= 0
+        let (lon12, lon12s) = ang_diff(lon1, lon2);
+        let mut lonsign = if lon12 >= 0.0 { 1.0 } else { -1.0 };
+        let lon12 = lonsign * ang_round(lon12);
+        let lon12s = ang_round((180.0 - lon12) - lonsign * lon12s);
+        let lam12 = lon12.to_radians();
+        let (slam12, clam12) = if lon12 > 90.0 {
+            let (s, c) = sincosd(lon12s);
+            (s, -c)
+        } else {
+            sincosd(lon12)
+        };
+
+        let mut lat1 = ang_round(lat_fix(lat1));
+        let mut lat2 = ang_round(lat_fix(lat2));
+        let swapp = if lat1.abs() < lat2.abs() { -1.0 } else { 1.0 };
+        if swapp < 0.0 {
+            lonsign = -lonsign;
+            std::mem::swap(&mut lat1, &mut lat2);
+        }
+        let latsign = if lat1 < 0.0 { 1.0 } else { -1.0 };
+        lat1 *= latsign;
+        lat2 *= latsign;
+
+        let reduced = |lat: f64| {
+            let (s, c) = sincosd(lat);
+            let (s, c) = norm(self.f1 * s, c);
+            (s, c.max(TINY))
+        };
+        let (sbet1, cbet1) = reduced(lat1);
+        let (mut sbet2, mut cbet2) = reduced(lat2);
+        if cbet1 < -sbet1 {
+            if cbet2 == cbet1 {
+                sbet2 = sbet1.copysign(sbet2);
+            }
+        } else if sbet2.abs() == -sbet1 {
+            cbet2 = cbet1;
+        }
+        let dn1 = (1.0 + self.ep2 * sq(sbet1)).sqrt();
+        let dn2 = (1.0 + self.ep2 * sq(sbet2)).sqrt();
+        let point1 = (sbet1, cbet1, dn1);
+        let point2 = (sbet2, cbet2, dn2);
+
+        let (mut salp1, mut calp1, mut salp2, mut calp2);
+        let mut s12x = 0.0;
+        let mut meridian = lat1 == -90.0 || slam12 == 0.0;
+
+        if meridian {
+            // Ao longo de um meridiano (ou partindo de um polo)
+            calp1 = clam12;
+            salp1 = slam12;
+            calp2 = 1.0;
+            salp2 = 0.0;
+            let (ssig1, csig1) = (sbet1, calp1 * cbet1);
+            let (ssig2, csig2) = (sbet2, calp2 * cbet2);
+            let sig12 = positive(csig1 * ssig2 - ssig1 * csig2).atan2(csig1 * csig2 + ssig1 * ssig2);
+            let (s, m12x, _) = self.lengths(self.n, sig12, (ssig1, csig1, dn1), (ssig2, csig2, dn2));
+            if sig12 < 1.0 || m12x >= 0.0 {
+                s12x = if sig12 < 3.0 * TINY || (sig12 < TOL0 && (s < 0.0 || m12x < 0.0)) {
+                    0.0
+                } else {
+                    s * self.b
+                };
+            } else {
+                // O meridiano não é a geodésica mais curta (antípodas num elipsoide oblato)
+                meridian = false;
+            }
+        } else {
+            (salp1, calp1, salp2, calp2) = (0.0, 0.0, 0.0, 0.0);
+        }
+
+        if !meridian {
+            if sbet1 == 0.0 && (self.f <= 0.0 || lon12s >= self.f * 180.0) {
+                // Ao longo do equador
+                calp1 = 0.0;
+                calp2 = 0.0;
+                salp1 = 1.0;
+                salp2 = 1.0;
+                s12x = self.a * lam12;
+            } else {
+                let (sig12, s1, c1, s2, c2, dnm) = self.inverse_start(point1, point2, lam12, slam12, clam12);
+                (salp1, calp1, salp2, calp2) = (s1, c1, s2, c2);
+
+                if sig12 >= 0.0 {
+                    s12x = sig12 * self.b * dnm;
+                } else {
+                    // Newton com bisseção de segurança sobre o azimute inicial
+                    let (mut salp1a, mut calp1a, mut salp1b, mut calp1b) = (TINY, 1.0, TINY, -1.0);
+                    let (mut tripn, mut tripb) = (false, false);
+                    let mut numit = 0;
+                    let solution = loop {
+                        let l = self.lambda12(point1, point2, salp1, calp1, slam12, clam12, numit < MAXIT1);
+                        let v = l.lam12;
+                        if tripb || v.abs() < (if tripn { 8.0 } else { 1.0 }) * TOL0 || numit == MAXIT2 {
+                            break l;
+                        }
+                        numit += 1;
+
+                        if v > 0.0 && (numit > MAXIT1 || calp1 / salp1 > calp1b / salp1b) {
+                            salp1b = salp1;
+                            calp1b = calp1;
+                        } else if v < 0.0 && (numit > MAXIT1 || calp1 / salp1 < calp1a / salp1a) {
+                            salp1a = salp1;
+                            calp1a = calp1;
+                        }
+
+                        if numit < MAXIT1 && l.dlam12 > 0.0 {
+                            let dalp1 = -v / l.dlam12;
+                            if dalp1.abs() < PI {
+                                let (sdalp1, cdalp1) = dalp1.sin_cos();
+                                let nsalp1 = salp1 * cdalp1 + calp1 * sdalp1;
+                                if nsalp1 > 0.0 {
+                                    (salp1, calp1) = norm(nsalp1, calp1 * cdalp1 - salp1 * sdalp1);
+                                    tripn = v.abs() <= 16.0 * TOL0;
+                                    continue;
+                                }
+                            }
+                        }
+
+                        (salp1, calp1) = norm((salp1a + salp1b) / 2.0, (calp1a + calp1b) / 2.0);
+                        tripn = false;
+                        tripb = (salp1a - salp1).abs() + (calp1a - calp1) < TOLB
+                            || (salp1 - salp1b).abs() + (calp1 - calp1b) < TOLB;
+                    };
+
+                    salp2 = solution.salp2;
+                    calp2 = solution.calp2;
+                    let (s, _, _) = self.lengths(
+                        solution.eps,
+                        solution.sig12,
+                        (solution.ssig1, solution.csig1, dn1),
+                        (solution.ssig2, solution.csig2, dn2),
+                    );
+                    s12x = s * self.b;
+                }
+            }
+        }
+
+        // Desfazer as trocas de sinal e de pontos
+        if swapp < 0.0 {
+            std::mem::swap(&mut salp1, &mut salp2);
+            std::mem::swap(&mut calp1, &mut calp2);
+        }
+        salp1 *= swapp * lonsign;
+        calp1 *= swapp * latsign;
+        salp2 *= swapp * lonsign;
+        calp2 *= swapp * latsign;
+
+        Inverse {
+            distance: s12x + 0.0,
+            initial_azimuth: atan2d(salp1, calp1),
+            final_azimuth: atan2d(salp2, calp2),
+        }
+    }
+
+    fn direct(&self, lat1: f64, lon1: f64, azi1: f64, s12: f64) -> Direct {
+        let (salp1, calp1) = sincosd(ang_round(ang_normalize(azi1)));
+        let (sbet1, cbet1) = sincosd(ang_round(lat_fix(lat1)));
+        let (sbet1, cbet1) = norm(self.f1 * sbet1, cbet1);
+        let cbet1 = cbet1.max(TINY);
+
+        let salp0 = salp1 * cbet1;
+        let calp0 = calp1.hypot(salp1 * sbet1);
+        let somg1 = salp0 * sbet1;
+        let comg1 = if sbet1 != 0.0 || calp1 != 0.0 { calp1 * cbet1 } else { 1.0 };
+        let (ssig1, csig1) = norm(sbet1, comg1);
+
+        let k2 = sq(calp0) * self.ep2;
+        let eps = k2 / (2.0 * (1.0 + (1.0 + k2).sqrt()) + k2);
+        let a1m1 = a1m1f(eps);
+        let c1a = c1f(eps);
+        let c1pa = c1pf(eps);
+        let c3a = self.c3f(eps);
+        let a3c = -self.f * salp0 * self.a3f(eps);
+
+        let b11 = sin_series(ssig1, csig1, &c1a);
+        let (s, c) = b11.sin_cos();
+        let stau1 = ssig1 * c + csig1 * s;
+        let ctau1 = csig1 * c - ssig1 * s;
+
+        // Distância -> arco na esfera auxiliar, invertendo a série com C1'
+        let tau12 = s12 / (self.b * (1.0 + a1m1));
+        let (s, c) = tau12.sin_cos();
+        let b12 = -sin_series(stau1 * c + ctau1 * s, ctau1 * c - stau1 * s, &c1pa);
+        let mut sig12 = tau12 - (b12 - b11);
+        let (mut ssig12, mut csig12) = sig12.sin_cos();
+        if self.f.abs() > 0.01 {
+            // Achatamento grande: um passo de Newton corrige a inversão da série
+            let ssig2 = ssig1 * csig12 + csig1 * ssig12;
+            let csig2 = csig1 * csig12 - ssig1 * ssig12;
+            let b12 = sin_series(ssig2, csig2, &c1a);
+            let serr = (1.0 + a1m1) * (sig12 + (b12 - b11)) - s12 / self.b;
+            sig12 -= serr / (1.0 + k2 * sq(ssig2)).sqrt();
+            (ssig12, csig12) = sig12.sin_cos();
+        }
+
+        let ssig2 = ssig1 * csig12 + csig1 * ssig12;
+        let csig2 = csig1 * csig12 - ssig1 * ssig12;
+        let sbet2 = calp0 * ssig2;
+        let mut cbet2 = salp0.hypot(calp0 * csig2);
+        let mut csig2 = csig2;
+        if cbet2 == 0.0 {
+            cbet2 = TINY;
+            csig2 = TINY;
+        }
+        let salp2 = salp0;
+        let calp2 = calp0 * csig2;
+
+        let somg2 = salp0 * ssig2;
+        let comg2 = csig2;
+        let omg12 = (somg2 * comg1 - comg2 * somg1).atan2(comg2 * comg1 + somg2 * somg1);
+        let lam12 = omg12 + a3c * (sig12 + (sin_series(ssig2, csig2, &c3a) - sin_series(ssig1, csig1, &c3a)));
+        let lon2 = ang_normalize(ang_normalize(lon1) + ang_normalize(lam12.to_degrees()));
+
+        Direct {
+            coordinate: Coordinate::new(atan2d(sbet2, self.f1 * cbet2), lon2),
+            final_azimuth: atan2d(salp2, calp2),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dms(deg: f64, min: f64, sec: f64) -> f64 {
+        deg.signum() * (deg.abs() + min / 60.0 + sec / 3600.0)
+    }
+
+    #[test]
+    fn test_inverse_reference_values() {
+        let wgs84 = Ellipsoid::WGS84;
+
+        // Quarto de meridiano e quarto de equador
+        let quarter = wgs84.inverse(&Coordinate::new(0.0, 0.0), &Coordinate::new(90.0, 0.0));
+        assert!((quarter.distance - 10001965.729).abs() < 1e-3);
+        let equator = wgs84.inverse(&Coordinate::new(0.0, 0.0), &Coordinate::new(0.0, 90.0));
+        assert!((equator.distance - 6378137.0 * PI / 2.0).abs() < 1e-6);
+        assert!((equator.initial_azimuth - 90.0).abs() < 1e-12);
+
+        // Flinders Peak -> Buninyong (exemplo clássico de Vincenty, GRS80)
+        let flinders = Coordinate::new(dms(-37.0, 57.0, 3.72030), dms(144.0, 25.0, 29.52440));
+        let buninyong = Coordinate::new(dms(-37.0, 39.0, 10.15610), dms(143.0, 55.0, 35.38390));
+        let result = Ellipsoid::GRS80.inverse(&flinders, &buninyong);
+        assert!((result.distance - 54972.271).abs() < 1e-3);
+        assert!((result.initial_azimuth + 360.0 - dms(306.0, 52.0, 5.37)).abs() < 1e-4);
+        assert!((result.final_azimuth + 180.0 - dms(127.0, 10.0, 25.07)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_near_antipodal_converges() {
+        // Exemplo de Karney (2013): Vincenty não converge aqui
+        let wgs84 = Ellipsoid::WGS84;
+        let result = wgs84.inverse(&Coordinate::new(-30.0, 0.0), &Coordinate::new(29.9, 179.8));
+        assert!((result.distance - 19989832.827610).abs() < 1e-5);
+        assert!((result.initial_azimuth - 161.890524736).abs() < 1e-8);
+        assert!((result.final_azimuth - 18.090737246).abs() < 1e-8);
+
+        // Antípodas exatos no equador: a geodésica passa pelo polo
+        let antipodal = wgs84.inverse(&Coordinate::new(0.0, 0.0), &Coordinate::new(0.0, 180.0));
+        assert!((antipodal.distance - 2.0 * 10001965.729).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_direct_round_trip() {
+        let wgs84 = Ellipsoid::WGS84;
+        let start = Coordinate::new(40.0, 0.0);
+        let result = wgs84.direct(&start, 30.0, 10_000_000.0);
+        assert!((result.coordinate.latitude - 41.79331020506).abs() < 1e-9);
+        assert!((result.coordinate.longitude - 137.84490004377).abs() < 1e-9);
+        assert!((result.final_azimuth - 149.09016931807).abs() < 1e-9);
+
+        let back = wgs84.inverse(&start, &result.coordinate);
+        assert!((back.distance - 10_000_000.0).abs() < 1e-6);
+        assert!((back.initial_azimuth - 30.0).abs() < 1e-9);
+
+        let dubai = Coordinate::new(25.1972, 55.2744);
+        let marina = Coordinate::new(25.0805, 55.1403);
+        let spherical = dubai.distance_to(&marina) * 1000.0;
+        assert!((wgs84.distance(&dubai, &marina) - spherical).abs() / spherical < 0.005);
+    }
+}
@@ -0,0 +1,209 @@
+// Geohash: codificação base32 intercalando bits de longitude e latitude
+// Prefixos comuns = células vizinhas; útil como chave de índice espacial
+
+use crate::{normalize_longitude, BoundingBox, Coordinate};
+
+const BASE32: &[u8; 32] = b"0123456789bcdefghjkmnpqrstuvwxyz";
+
+/// Longest hash produced by [`encode`]; 12 characters is a few centimeters
+pub const MAX_PRECISION: usize = 12;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GeohashError {
+    Empty,
+    InvalidCharacter(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    /// Steps in (latitude, longitude) cells
+    fn offset(&self) -> (f64, f64) {
+        match self {
+            Direction::North => (1.0, 0.0),
+            Direction::NorthEast => (1.0, 1.0),
+            Direction::East => (0.0, 1.0),
+            Direction::SouthEast => (-1.0, 1.0),
+            Direction::South => (-1.0, 0.0),
+            Direction::SouthWest => (-1.0, -1.0),
+            Direction::West => (0.0, -1.0),
+            Direction::NorthWest => (1.0, -1.0),
+        }
+    }
+}
+
+/// Hash of `coordinate` with `precision` characters (clamped to 1..=12).
+/// Longitudes are wrapped, so 180° encodes like -180°.
+pub fn encode(coordinate: &Coordinate, precision: usize) -> String {
+    let precision = precision.clamp(1, MAX_PRECISION);
+    let latitude = coordinate.latitude.clamp(-90.0, 90.0);
+    let longitude = normalize_longitude(coordinate.longitude);
+
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut hash = String::with_capacity(precision);
+    let mut even = true;
+    let (mut bits, mut value) = (0, 0usize);
+
+    while hash.len() < precision {
+        // Bits pares refinam a longitude, ímpares a latitude
+        let (range, target): (&mut (f64, f64), f64) = if even {
+            (&mut lon_range, longitude)
+        } else {
+            (&mut lat_range, latitude)
+        };
+        let mid = (range.0 + range.1) / 2.0;
+        value <<= 1;
+        if target >= mid {
+            value |= 1;
+            range.0 = mid;
+        } else {
+            range.1 = mid;
+        }
+        even = !even;
+
+        bits += 1;
+        if bits == 5 {
+            hash.push(BASE32[value] as char);
+            bits = 0;
+            value = 0;
+        }
+    }
+    hash
+}
+
+/// Cell covered by `hash`
+pub fn decode(hash: &str) -> Result<BoundingBox, GeohashError> {
+    if hash.is_empty() {
+        return Err(GeohashError::Empty);
+    }
+
+    let (mut lat_range, mut lon_range) = ((-90.0, 90.0), (-180.0, 180.0));
+    let mut even = true;
+    for c in hash.chars() {
+        let value = BASE32
+            .iter()
+            .position(|&b| b as char == c.to_ascii_lowercase())
+            .ok_or(GeohashError::InvalidCharacter(c))?;
+        for shift in (0..5).rev() {
+            let range: &mut (f64, f64) = if even { &mut lon_range } else { &mut lat_range };
+            let mid = (range.0 + range.1) / 2.0;
+            if value >> shift & 1 == 1 {
+                range.0 = mid;
+            } else {
+                range.1 = mid;
+            }
+            even = !even;
+        }
+    }
+    Ok(BoundingBox::new(lat_range.0, lon_range.0, lat_range.1, lon_range.1))
+}
+
+/// Center of the cell covered by `hash`
+pub fn decode_center(hash: &str) -> Result<Coordinate, GeohashError> {
+    decode(hash).map(|cell| cell.center())
+}
+
+/// Adjacent cell of the same precision. Crosses the antimeridian; `None`
+/// beyond the poles.
+pub fn neighbor(hash: &str, direction: Direction) -> Result<Option<String>, GeohashError> {
+    let cell = decode(hash)?;
+    let (d_lat, d_lon) = direction.offset();
+    let height = cell.max_lat - cell.min_lat;
+    let width = cell.max_lon - cell.min_lon;
+
+    let latitude = (cell.min_lat + cell.max_lat) / 2.0 + d_lat * height;
+    if !(-90.0..=90.0).contains(&latitude) {
+        return Ok(None);
+    }
+    let longitude = (cell.min_lon + cell.max_lon) / 2.0 + d_lon * width;
+    Ok(Some(encode(&Coordinate::new(latitude, longitude), hash.len())))
+}
+
+/// The (up to) 8 cells around `hash`, clockwise from north; fewer at the poles
+pub fn neighbors(hash: &str) -> Result<Vec<String>, GeohashError> {
+    let mut cells = Vec::with_capacity(8);
+    for direction in Direction::ALL {
+        if let Some(cell) = neighbor(hash, direction)? {
+            cells.push(cell);
+        }
+    }
+    Ok(cells)
+}
+
+/// Cell size in degrees (latitude, longitude) at `precision` characters
+pub fn cell_size(precision: usize) -> (f64, f64) {
+    let bits = 5 * precision as i32;
+    let lat_bits = bits / 2;
+    let lon_bits = bits - lat_bits;
+    (180.0 / 2f64.powi(lat_bits), 360.0 / 2f64.powi(lon_bits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        // Valores de referência de geohash.org
+        assert_eq!(encode(&Coordinate::new(57.64911, 10.40744), 11), "u4pruydqqvj");
+        assert_eq!(encode(&Coordinate::new(42.605, -5.603), 5), "ezs42");
+        let burj = encode(&crate::dubai::BURJ_KHALIFA, 7);
+        assert!(decode(&burj).unwrap().contains(&crate::dubai::BURJ_KHALIFA));
+        assert!(burj.starts_with(&encode(&crate::dubai::BURJ_KHALIFA, 4)));
+
+        let cell = decode("ezs42").unwrap();
+        assert!((cell.min_lat - 42.583).abs() < 1e-3 && (cell.max_lat - 42.627).abs() < 1e-3);
+        assert!((cell.min_lon + 5.625).abs() < 1e-3 && (cell.max_lon + 5.581).abs() < 1e-3);
+        assert!(cell.contains(&Coordinate::new(42.605, -5.603)));
+
+        let center = decode_center("u4pruydqqvj").unwrap();
+        assert!(center.distance_to(&Coordinate::new(57.64911, 10.40744)) < 1e-3);
+
+        assert_eq!(decode(""), Err(GeohashError::Empty));
+        assert_eq!(decode("ezsa"), Err(GeohashError::InvalidCharacter('a')));
+        assert_eq!(encode(&Coordinate::new(0.0, 180.0), 1), encode(&Coordinate::new(0.0, -180.0), 1));
+    }
+
+    #[test]
+    fn test_neighbors() {
+        assert_eq!(neighbor("ezs42", Direction::North).unwrap().unwrap(), "ezs48");
+        assert_eq!(neighbor("ezs42", Direction::East).unwrap().unwrap(), "ezs43");
+        assert_eq!(neighbor("ezs42", Direction::SouthWest).unwrap().unwrap(), "ezefp");
+
+        let around = neighbors("ezs42").unwrap();
+        assert_eq!(around, vec!["ezs48", "ezs49", "ezs43", "ezs41", "ezs40", "ezefp", "ezefr", "ezefx"]);
+
+        // Antimeridiano e polo
+        assert_eq!(neighbor("8", Direction::West).unwrap().unwrap(), "x");
+        assert_eq!(neighbors("b").unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_cell_size() {
+        let (height, width) = cell_size(5);
+        assert!((height - 0.0439453125).abs() < 1e-12);
+        assert!((width - 0.0439453125).abs() < 1e-12);
+        assert_eq!(cell_size(1), (45.0, 45.0));
+    }
+}
@@ -0,0 +1,398 @@
+// Geometrias: LineString, Polygon (com buracos) e BoundingBox
+// Longitudes são "desenroladas" para funcionar através do antimeridiano
+
+use crate::{normalize_longitude, Coordinate, EARTH_RADIUS_KM};
+
+/// Planar (lon, lat) ring used internally
+type Ring = Vec<(f64, f64)>;
+
+/// Wrap a longitude delta into [-180, 180)
+fn wrap_delta(delta: f64) -> f64 {
+    normalize_longitude(delta)
+}
+
+/// Ring as planar (lon, lat) points with longitudes made continuous, so an
+/// edge never jumps by 360° when it crosses the antimeridian
+fn unwrap_ring(ring: &[Coordinate]) -> Ring {
+    let mut out: Vec<(f64, f64)> = Vec::with_capacity(ring.len());
+    for c in ring {
+        let lon = match out.last() {
+            Some(&(prev, _)) => prev + wrap_delta(c.longitude - prev),
+            None => c.longitude,
+        };
+        out.push((lon, c.latitude));
+    }
+    out
+}
+
+/// Shift `lon` by a multiple of 360° to be as close as possible to `reference`
+fn near(lon: f64, reference: f64) -> f64 {
+    reference + wrap_delta(lon - reference)
+}
+
+/// Even-odd rule on a planar ring
+fn ring_contains(ring: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    let mut inside = false;
+    let mut j = ring.len() - 1;
+    for i in 0..ring.len() {
+        let (xi, yi) = ring[i];
+        let (xj, yj) = ring[j];
+        if (yi > y) != (yj > y) && x < xi + (y - yi) / (yj - yi) * (xj - xi) {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn segments_intersect(a: (f64, f64), b: (f64, f64), c: (f64, f64), d: (f64, f64)) -> bool {
+    let orient = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| (q.0 - p.0) * (r.1 - p.1) - (q.1 - p.1) * (r.0 - p.0);
+    let on_segment = |p: (f64, f64), q: (f64, f64), r: (f64, f64)| {
+        r.0 >= p.0.min(q.0) && r.0 <= p.0.max(q.0) && r.1 >= p.1.min(q.1) && r.1 <= p.1.max(q.1)
+    };
+
+    let (d1, d2) = (orient(c, d, a), orient(c, d, b));
+    let (d3, d4) = (orient(a, b, c), orient(a, b, d));
+    if ((d1 > 0.0) != (d2 > 0.0)) && d1 != 0.0 && d2 != 0.0 && ((d3 > 0.0) != (d4 > 0.0)) && d3 != 0.0 && d4 != 0.0 {
+        return true;
+    }
+    (d1 == 0.0 && on_segment(c, d, a))
+        || (d2 == 0.0 && on_segment(c, d, b))
+        || (d3 == 0.0 && on_segment(a, b, c))
+        || (d4 == 0.0 && on_segment(a, b, d))
+}
+
+fn edges(ring: &[(f64, f64)]) -> impl Iterator<Item = ((f64, f64), (f64, f64))> + '_ {
+    ring.iter().zip(ring.iter().cycle().skip(1)).map(|(a, b)| (*a, *b))
+}
+
+/// Spherical excess of a ring (in km², unsigned)
+fn ring_area(ring: &[(f64, f64)]) -> f64 {
+    let sum: f64 = edges(ring)
+        .map(|((lon1, lat1), (lon2, lat2))| {
+            (lon2 - lon1).to_radians() * (2.0 + lat1.to_radians().sin() + lat2.to_radians().sin())
+        })
+        .sum();
+    (sum * EARTH_RADIUS_KM * EARTH_RADIUS_KM / 2.0).abs()
+}
+
+fn ring_perimeter(ring: &[Coordinate]) -> f64 {
+    ring.iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a.distance_to(b))
+        .sum()
+}
+
+/// Drop the closing point if the ring repeats its first vertex
+fn open_ring(mut ring: Vec<Coordinate>) -> Vec<Coordinate> {
+    if ring.len() > 1 && ring.first() == ring.last() {
+        ring.pop();
+    }
+    ring
+}
+
+/// Latitude/longitude box. `min_lon > max_lon` means it crosses the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lat: f64,
+    pub min_lon: f64,
+    pub max_lat: f64,
+    pub max_lon: f64,
+}
+
+impl BoundingBox {
+    pub fn new(min_lat: f64, min_lon: f64, max_lat: f64, max_lon: f64) -> Self {
+        Self { min_lat, min_lon, max_lat, max_lon }
+    }
+
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.min_lon > self.max_lon
+    }
+
+    pub fn contains(&self, point: &Coordinate) -> bool {
+        point.latitude >= self.min_lat
+            && point.latitude <= self.max_lat
+            && (point.longitude - self.min_lon).rem_euclid(360.0) <= self.lon_span()
+    }
+
+    /// Midpoint of the box, on the correct side of the antimeridian
+    pub fn center(&self) -> Coordinate {
+        Coordinate::new(
+            (self.min_lat + self.max_lat) / 2.0,
+            normalize_longitude(self.min_lon + self.lon_span() / 2.0),
+        )
+    }
+
+    /// Longitude span in degrees, accounting for antimeridian crossing
+    fn lon_span(&self) -> f64 {
+        if self.crosses_antimeridian() {
+            self.max_lon - self.min_lon + 360.0
+        } else {
+            self.max_lon - self.min_lon
+        }
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        if self.max_lat < other.min_lat || other.max_lat < self.min_lat {
+            return false;
+        }
+        // Um começa dentro do intervalo de longitude do outro
+        let starts_within = |a: &BoundingBox, b: &BoundingBox| (a.min_lon - b.min_lon).rem_euclid(360.0) <= b.lon_span();
+        starts_within(self, other) || starts_within(other, self)
+    }
+
+    /// Corners as a planar ring, unwrapped to lie near `reference`
+    fn ring_near(&self, reference: f64) -> Ring {
+        let min_lon = near(self.min_lon, reference - self.lon_span() / 2.0);
+        let max_lon = min_lon + self.lon_span();
+        vec![
+            (min_lon, self.min_lat),
+            (max_lon, self.min_lat),
+            (max_lon, self.max_lat),
+            (min_lon, self.max_lat),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineString {
+    pub points: Vec<Coordinate>,
+}
+
+impl LineString {
+    pub fn new(points: Vec<Coordinate>) -> Self {
+        Self { points }
+    }
+
+    /// Length along the great circles between consecutive points (in km)
+    pub fn length(&self) -> f64 {
+        self.points.windows(2).map(|w| w[0].distance_to(&w[1])).sum()
+    }
+
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        bbox_of(&self.points)
+    }
+}
+
+fn bbox_of(points: &[Coordinate]) -> Option<BoundingBox> {
+    let unwrapped = unwrap_ring(points);
+    let first = unwrapped.first()?;
+    let (mut min_lon, mut max_lon, mut min_lat, mut max_lat) = (first.0, first.0, first.1, first.1);
+    for &(lon, lat) in &unwrapped {
+        min_lon = min_lon.min(lon);
+        max_lon = max_lon.max(lon);
+        min_lat = min_lat.min(lat);
+        max_lat = max_lat.max(lat);
+    }
+    if max_lon - min_lon >= 360.0 {
+        return Some(BoundingBox::new(min_lat, -180.0, max_lat, 180.0));
+    }
+    Some(BoundingBox::new(min_lat, normalize_longitude(min_lon), max_lat, normalize_longitude(max_lon)))
+}
+
+/// Polygon with an exterior ring and optional holes. Rings are closed
+/// implicitly; a repeated closing vertex is accepted and dropped.
+/// Rings must span less than 180° of longitude and not enclose a pole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    pub exterior: Vec<Coordinate>,
+    pub holes: Vec<Vec<Coordinate>>,
+}
+
+impl Polygon {
+    pub fn new(exterior: Vec<Coordinate>, holes: Vec<Vec<Coordinate>>) -> Self {
+        Self {
+            exterior: open_ring(exterior),
+            holes: holes.into_iter().map(open_ring).collect(),
+        }
+    }
+
+    /// At least three distinct vertices in every ring
+    pub fn is_valid(&self) -> bool {
+        self.exterior.len() >= 3 && self.holes.iter().all(|h| h.len() >= 3)
+    }
+
+    /// Exterior and holes unwrapped onto one continuous longitude range
+    fn planar(&self) -> (Ring, Vec<Ring>) {
+        let exterior = unwrap_ring(&self.exterior);
+        let reference = exterior.first().map_or(0.0, |p| p.0);
+        let holes = self
+            .holes
+            .iter()
+            .map(|hole| {
+                let mut ring = unwrap_ring(hole);
+                let shift = near(ring[0].0, reference) - ring[0].0;
+                ring.iter_mut().for_each(|p| p.0 += shift);
+                ring
+            })
+            .collect();
+        (exterior, holes)
+    }
+
+    fn center_lon(ring: &[(f64, f64)]) -> f64 {
+        let min = ring.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max = ring.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        (min + max) / 2.0
+    }
+
+    pub fn contains(&self, point: &Coordinate) -> bool {
+        if !self.is_valid() {
+            return false;
+        }
+        let (exterior, holes) = self.planar();
+        let p = (near(point.longitude, Self::center_lon(&exterior)), point.latitude);
+        ring_contains(&exterior, p) && !holes.iter().any(|h| ring_contains(h, p))
+    }
+
+    /// Area on the sphere, holes excluded (in km²)
+    pub fn area(&self) -> f64 {
+        if !self.is_valid() {
+            return 0.0;
+        }
+        let (exterior, holes) = self.planar();
+        ring_area(&exterior) - holes.iter().map(|h| ring_area(h)).sum::<f64>()
+    }
+
+    /// Length of the exterior ring plus all holes (in km)
+    pub fn perimeter(&self) -> f64 {
+        ring_perimeter(&self.exterior) + self.holes.iter().map(|h| ring_perimeter(h)).sum::<f64>()
+    }
+
+    /// Area-weighted centroid in lat/lon space, holes excluded. Accurate for
+    /// polygons up to a few hundred km across.
+    pub fn centroid(&self) -> Option<Coordinate> {
+        if !self.is_valid() {
+            return None;
+        }
+        let (exterior, holes) = self.planar();
+
+        // (área com sinal, soma x, soma y) de um anel
+        let moments = |ring: &[(f64, f64)]| {
+            edges(ring).fold((0.0, 0.0, 0.0), |(a, cx, cy), ((x0, y0), (x1, y1))| {
+                let cross = x0 * y1 - x1 * y0;
+                (a + cross, cx + (x0 + x1) * cross, cy + (y0 + y1) * cross)
+            })
+        };
+        let signed = |ring: &[(f64, f64)]| {
+            let (a, cx, cy) = moments(ring);
+            // Orientação normalizada: exterior soma, buracos subtraem
+            if a < 0.0 { (-a, -cx, -cy) } else { (a, cx, cy) }
+        };
+
+        let (mut area, mut cx, mut cy) = signed(&exterior);
+        for hole in &holes {
+            let (a, x, y) = signed(hole);
+            area -= a;
+            cx -= x;
+            cy -= y;
+        }
+        if area == 0.0 {
+            return None;
+        }
+        Some(Coordinate::new(cy / (3.0 * area), normalize_longitude(cx / (3.0 * area))))
+    }
+
+    pub fn bbox(&self) -> Option<BoundingBox> {
+        bbox_of(&self.exterior)
+    }
+
+    /// True if the polygon and the box share any area
+    pub fn intersects_bbox(&self, bbox: &BoundingBox) -> bool {
+        if !self.is_valid() {
+            return false;
+        }
+        let (exterior, holes) = self.planar();
+        let rect = bbox.ring_near(Self::center_lon(&exterior));
+
+        let crosses = |ring: &[(f64, f64)]| {
+            edges(ring).any(|(a, b)| edges(&rect).any(|(c, d)| segments_intersect(a, b, c, d)))
+        };
+        let in_rect = |p: &(f64, f64)| p.0 >= rect[0].0 && p.0 <= rect[1].0 && p.1 >= rect[0].1 && p.1 <= rect[2].1;
+
+        let touches_exterior =
+            crosses(&exterior) || exterior.iter().any(in_rect) || rect.iter().all(|c| ring_contains(&exterior, *c));
+        if !touches_exterior {
+            return false;
+        }
+
+        // Caixa inteira dentro de um buraco não intersecta
+        !holes
+            .iter()
+            .any(|h| !crosses(h) && !h.iter().any(in_rect) && rect.iter().all(|c| ring_contains(h, *c)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ring(points: &[(f64, f64)]) -> Vec<Coordinate> {
+        points.iter().map(|&(lat, lon)| Coordinate::new(lat, lon)).collect()
+    }
+
+    fn square_with_hole() -> Polygon {
+        Polygon::new(
+            ring(&[(0.0, 0.0), (0.0, 4.0), (4.0, 4.0), (4.0, 0.0), (0.0, 0.0)]),
+            vec![ring(&[(1.0, 1.0), (1.0, 3.0), (3.0, 3.0), (3.0, 1.0)])],
+        )
+    }
+
+    #[test]
+    fn test_contains_with_hole() {
+        let polygon = square_with_hole();
+        assert_eq!(polygon.exterior.len(), 4);
+        assert!(polygon.contains(&Coordinate::new(0.5, 0.5)));
+        assert!(!polygon.contains(&Coordinate::new(2.0, 2.0)));
+        assert!(!polygon.contains(&Coordinate::new(5.0, 2.0)));
+    }
+
+    #[test]
+    fn test_antimeridian() {
+        // Fiji: de 179°E a 179°W
+        let polygon = Polygon::new(ring(&[(-16.0, 179.0), (-16.0, -179.0), (-18.0, -179.0), (-18.0, 179.0)]), vec![]);
+        assert!(polygon.contains(&Coordinate::new(-17.0, 179.9)));
+        assert!(polygon.contains(&Coordinate::new(-17.0, -179.9)));
+        assert!(polygon.contains(&Coordinate::new(-17.0, 180.0)));
+        assert!(!polygon.contains(&Coordinate::new(-17.0, 0.0)));
+        assert!(!polygon.contains(&Coordinate::new(-17.0, 178.0)));
+
+        let bbox = polygon.bbox().unwrap();
+        assert!(bbox.crosses_antimeridian());
+        assert_eq!((bbox.min_lon, bbox.max_lon), (179.0, -179.0));
+        assert!((polygon.centroid().unwrap().longitude.abs() - 180.0).abs() < 1e-9);
+        assert!(polygon.intersects_bbox(&BoundingBox::new(-17.5, -179.5, -16.5, -170.0)));
+        assert!(!polygon.intersects_bbox(&BoundingBox::new(-17.5, 170.0, -16.5, 178.0)));
+    }
+
+    #[test]
+    fn test_area_and_perimeter() {
+        // 1° x 1° no equador: R² · Δλ · (sin φ2 − sin φ1)
+        let cell = Polygon::new(ring(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0), (1.0, 0.0)]), vec![]);
+        let expected = EARTH_RADIUS_KM.powi(2) * 1f64.to_radians() * 1f64.to_radians().sin();
+        assert!((cell.area() - expected).abs() < 1e-6 * expected);
+        assert!((cell.perimeter() - 4.0 * 111.19).abs() < 1.0);
+
+        let holed = square_with_hole();
+        let outer = Polygon::new(holed.exterior.clone(), vec![]);
+        let hole = Polygon::new(holed.holes[0].clone(), vec![]);
+        assert!((holed.area() - (outer.area() - hole.area())).abs() < 1e-6);
+
+        let line = LineString::new(ring(&[(0.0, 0.0), (0.0, 1.0), (1.0, 1.0)]));
+        assert!((line.length() - 2.0 * 111.19).abs() < 0.5);
+    }
+
+    #[test]
+    fn test_centroid_and_bbox_intersection() {
+        let polygon = square_with_hole();
+        let centroid = polygon.centroid().unwrap();
+        assert!((centroid.latitude - 2.0).abs() < 1e-9 && (centroid.longitude - 2.0).abs() < 1e-9);
+
+        assert!(polygon.intersects_bbox(&BoundingBox::new(3.5, 3.5, 10.0, 10.0)));
+        assert!(polygon.intersects_bbox(&BoundingBox::new(-1.0, -1.0, 5.0, 5.0)));
+        assert!(!polygon.intersects_bbox(&BoundingBox::new(1.5, 1.5, 2.5, 2.5)));
+        assert!(!polygon.intersects_bbox(&BoundingBox::new(5.0, 5.0, 6.0, 6.0)));
+
+        assert!(BoundingBox::new(0.0, 170.0, 10.0, -170.0).intersects(&BoundingBox::new(5.0, -175.0, 6.0, -160.0)));
+        assert!(!BoundingBox::new(0.0, 170.0, 10.0, -170.0).intersects(&BoundingBox::new(5.0, 0.0, 6.0, 10.0)));
+    }
+}
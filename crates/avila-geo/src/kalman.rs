@@ -0,0 +1,228 @@
+// Filtro de Kalman de velocidade constante para suavizar fixes de GPS
+// Estado por eixo (leste/norte): posição e velocidade em metros, plano tangente à última estimativa
+
+use crate::{normalize_longitude, Coordinate, EARTH_RADIUS_KM};
+
+/// Acceleration noise (m²/s³) suited to people and urban traffic
+pub const DEFAULT_PROCESS_NOISE: f64 = 1.0;
+
+/// Accuracies below this are treated as 1 m, so no fix is trusted blindly
+const MIN_ACCURACY: f64 = 1.0;
+/// Standard deviation of the velocity before anything is known (m/s)
+const INITIAL_SPEED_SIGMA: f64 = 10.0;
+
+/// A measured position
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fix {
+    /// Seconds; only differences matter
+    pub timestamp: f64,
+    pub coordinate: Coordinate,
+    /// One standard deviation of the horizontal error, in meters
+    pub accuracy: f64,
+}
+
+/// Filtered position and velocity after a fix
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub coordinate: Coordinate,
+    /// Meters per second
+    pub velocity_east: f64,
+    pub velocity_north: f64,
+    /// One standard deviation of the position, in meters
+    pub accuracy: f64,
+}
+
+impl Estimate {
+    /// Meters per second
+    pub fn speed(&self) -> f64 {
+        self.velocity_east.hypot(self.velocity_north)
+    }
+
+    /// Degrees clockwise from true north, in [0, 360)
+    pub fn course(&self) -> f64 {
+        self.velocity_east.atan2(self.velocity_north).to_degrees().rem_euclid(360.0)
+    }
+}
+
+/// Covariance of (position, velocity) along one axis. Accuracy is a radius
+/// and the noise is the same in every direction, so both axes share it.
+#[derive(Debug, Clone, Copy)]
+struct Covariance {
+    position: f64,
+    cross: f64,
+    velocity: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct KalmanFilter {
+    process_noise: f64,
+    state: Option<(f64, Estimate, Covariance)>,
+}
+
+impl Default for KalmanFilter {
+    fn default() -> Self {
+        KalmanFilter::new(DEFAULT_PROCESS_NOISE)
+    }
+}
+
+impl KalmanFilter {
+    /// `process_noise` is the acceleration spectral density in m²/s³: higher
+    /// values follow turns faster, lower values smooth more
+    pub fn new(process_noise: f64) -> Self {
+        KalmanFilter { process_noise, state: None }
+    }
+
+    /// Latest estimate, if any fix was accepted
+    pub fn estimate(&self) -> Option<Estimate> {
+        self.state.map(|(_, estimate, _)| estimate)
+    }
+
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    /// Feed a fix and return the new estimate. Fixes older than the previous
+    /// one are ignored and return `None`.
+    pub fn update(&mut self, fix: &Fix) -> Option<Estimate> {
+        let r = fix.accuracy.max(MIN_ACCURACY).powi(2);
+        let Some((last_time, estimate, p)) = self.state else {
+            let estimate = Estimate {
+                coordinate: fix.coordinate,
+                velocity_east: 0.0,
+                velocity_north: 0.0,
+                accuracy: r.sqrt(),
+            };
+            let p = Covariance { position: r, cross: 0.0, velocity: INITIAL_SPEED_SIGMA.powi(2) };
+            self.state = Some((fix.timestamp, estimate, p));
+            return Some(estimate);
+        };
+        let dt = fix.timestamp - last_time;
+        if dt < 0.0 || dt.is_nan() {
+            return None;
+        }
+
+        // Predição: anda com a velocidade atual e a incerteza cresce
+        let q = self.process_noise;
+        let predicted = estimate.coordinate.destination(estimate.speed() * dt / 1000.0, estimate.course());
+        let p = Covariance {
+            position: p.position + 2.0 * dt * p.cross + dt * dt * p.velocity + q * dt.powi(3) / 3.0,
+            cross: p.cross + dt * p.velocity + q * dt * dt / 2.0,
+            velocity: p.velocity + q * dt,
+        };
+
+        // Correção: a inovação é o fix visto a partir da posição prevista
+        let (east, north) = offset_m(&predicted, &fix.coordinate);
+        let s = p.position + r;
+        let (k_position, k_velocity) = (p.position / s, p.cross / s);
+        let estimate = Estimate {
+            coordinate: displace(&predicted, k_position * east, k_position * north),
+            velocity_east: estimate.velocity_east + k_velocity * east,
+            velocity_north: estimate.velocity_north + k_velocity * north,
+            accuracy: ((1.0 - k_position) * p.position).sqrt(),
+        };
+        let p = Covariance {
+            position: (1.0 - k_position) * p.position,
+            cross: (1.0 - k_position) * p.cross,
+            velocity: p.velocity - k_velocity * p.cross,
+        };
+
+        self.state = Some((fix.timestamp, estimate, p));
+        Some(estimate)
+    }
+}
+
+fn meters_per_degree() -> f64 {
+    EARTH_RADIUS_KM * 1000.0 * std::f64::consts::PI / 180.0
+}
+
+/// (east, north) meters from `origin` to `point` on the local tangent plane
+fn offset_m(origin: &Coordinate, point: &Coordinate) -> (f64, f64) {
+    let cos_lat = origin.latitude.to_radians().cos();
+    (
+        normalize_longitude(point.longitude - origin.longitude) * cos_lat * meters_per_degree(),
+        (point.latitude - origin.latitude) * meters_per_degree(),
+    )
+}
+
+fn displace(origin: &Coordinate, east: f64, north: f64) -> Coordinate {
+    // Perto dos polos o cosseno zera; limitar evita divisão por zero
+    let cos_lat = origin.latitude.to_radians().cos().max(1e-9);
+    Coordinate::new(
+        (origin.latitude + north / meters_per_degree()).clamp(-90.0, 90.0),
+        normalize_longitude(origin.longitude + east / (cos_lat * meters_per_degree())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Ruído determinístico em [-1, 1]
+    fn noise(i: usize) -> f64 {
+        ((i as f64 * 12.9898).sin() * 43758.5453).fract()
+    }
+
+    #[test]
+    fn test_stationary_fixes_are_smoothed() {
+        let truth = crate::dubai::BURJ_KHALIFA;
+        let mut filter = KalmanFilter::new(0.05);
+        let (mut raw_error, mut filtered_error) = (0.0, 0.0);
+        for i in 0..120 {
+            // Até 25 m de erro em direções aleatórias
+            let measured = truth.destination(0.025 * noise(i).abs(), 360.0 * noise(i + 1000).abs());
+            let estimate = filter.update(&Fix { timestamp: i as f64, coordinate: measured, accuracy: 15.0 }).unwrap();
+            if i >= 20 {
+                raw_error += measured.distance_to(&truth);
+                filtered_error += estimate.coordinate.distance_to(&truth);
+            }
+        }
+
+        let estimate = filter.estimate().unwrap();
+        assert!(filtered_error < raw_error / 2.0, "{} {}", filtered_error, raw_error);
+        assert!(estimate.speed() < 0.5, "{}", estimate.speed());
+        assert!(estimate.accuracy < 15.0);
+    }
+
+    #[test]
+    fn test_constant_velocity_is_tracked() {
+        let start = Coordinate::new(-23.5505, -46.6333);
+        let mut filter = KalmanFilter::default();
+        let mut last = None;
+        for i in 0..90 {
+            // 12 m/s para o nordeste, com até 8 m de erro
+            let truth = start.destination(0.012 * i as f64, 45.0);
+            let measured = truth.destination(0.008 * noise(i).abs(), 360.0 * noise(i + 500).abs());
+            last = filter.update(&Fix { timestamp: i as f64, coordinate: measured, accuracy: 8.0 });
+        }
+
+        let estimate = last.unwrap();
+        assert!((estimate.speed() - 12.0).abs() < 1.0, "{}", estimate.speed());
+        assert!((estimate.course() - 45.0).abs() < 5.0, "{}", estimate.course());
+        let truth = start.destination(0.012 * 89.0, 45.0);
+        assert!(estimate.coordinate.distance_to(&truth) < 0.008);
+    }
+
+    #[test]
+    fn test_first_and_out_of_order_fixes() {
+        let mut filter = KalmanFilter::new(2.0);
+        assert_eq!(filter.estimate(), None);
+
+        let first = Fix { timestamp: 100.0, coordinate: Coordinate::new(10.0, 20.0), accuracy: 0.0 };
+        let estimate = filter.update(&first).unwrap();
+        assert_eq!(estimate.coordinate, first.coordinate);
+        assert_eq!((estimate.speed(), estimate.accuracy), (0.0, 1.0));
+
+        let late = Fix { timestamp: 99.0, ..first };
+        assert_eq!(filter.update(&late), None);
+        assert_eq!(filter.estimate(), Some(estimate));
+
+        // Cruzando o antimeridiano
+        filter.reset();
+        filter.update(&Fix { timestamp: 0.0, coordinate: Coordinate::new(0.0, 179.9999), accuracy: 5.0 });
+        let next = filter
+            .update(&Fix { timestamp: 1.0, coordinate: Coordinate::new(0.0, -179.9999), accuracy: 5.0 })
+            .unwrap();
+        assert!(next.coordinate.longitude.abs() > 179.999);
+        assert!(next.velocity_east > 0.0);
+    }
+}
@@ -1,21 +1,74 @@
 // AvilaGeo - Native Geolocation Implementation
 // Zero External Dependencies 🦀
 
-#[derive(Debug, Clone, Copy)]
+use std::fmt;
+
+/// Mean Earth radius used by the spherical calculations
+pub const EARTH_RADIUS_KM: f64 = 6371.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Coordinate {
     pub latitude: f64,
     pub longitude: f64,
 }
 
+/// Why a latitude/longitude pair was rejected by [`Coordinate::try_new`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateError {
+    NotFinite { field: &'static str },
+    OutOfRange { field: &'static str, value: f64 },
+}
+
+impl CoordinateError {
+    /// `"latitude"` or `"longitude"`
+    pub fn field(&self) -> &'static str {
+        match self {
+            CoordinateError::NotFinite { field } | CoordinateError::OutOfRange { field, .. } => field,
+        }
+    }
+}
+
+impl fmt::Display for CoordinateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoordinateError::NotFinite { field } => write!(f, "{} must be a finite number", field),
+            CoordinateError::OutOfRange { field, value } => {
+                let limit = if *field == "latitude" { 90 } else { 180 };
+                write!(f, "{} {} is outside [-{}, {}]", field, value, limit, limit)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CoordinateError {}
+
 impl Coordinate {
     pub fn new(latitude: f64, longitude: f64) -> Self {
         Self { latitude, longitude }
     }
 
+    /// Validated constructor: both values finite, latitude in [-90, 90] and
+    /// longitude in [-180, 180]. Use [`Coordinate::normalized`] first to accept
+    /// longitudes that went around the globe.
+    pub fn try_new(latitude: f64, longitude: f64) -> Result<Self, CoordinateError> {
+        for (field, value, limit) in [("latitude", latitude, 90.0), ("longitude", longitude, 180.0)] {
+            if !value.is_finite() {
+                return Err(CoordinateError::NotFinite { field });
+            }
+            if value.abs() > limit {
+                return Err(CoordinateError::OutOfRange { field, value });
+            }
+        }
+        Ok(Self { latitude, longitude })
+    }
+
+    /// Same point with the longitude wrapped into [-180, 180)
+    pub fn normalized(&self) -> Self {
+        Self::new(self.latitude, normalize_longitude(self.longitude))
+    }
+
     /// Calculate distance between two coordinates using Haversine formula (in km)
     pub fn distance_to(&self, other: &Coordinate) -> f64 {
-        const EARTH_RADIUS_KM: f64 = 6371.0;
-
         let lat1_rad = self.latitude.to_radians();
         let lat2_rad = other.latitude.to_radians();
         let delta_lat = (other.latitude - self.latitude).to_radians();
@@ -28,6 +81,90 @@ impl Coordinate {
 
         EARTH_RADIUS_KM * c
     }
+
+    /// Initial great-circle bearing towards `other` (degrees from true north, 0..360)
+    pub fn initial_bearing_to(&self, other: &Coordinate) -> f64 {
+        let lat1 = self.latitude.to_radians();
+        let lat2 = other.latitude.to_radians();
+        let delta_lon = (other.longitude - self.longitude).to_radians();
+
+        let y = delta_lon.sin() * lat2.cos();
+        let x = lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * delta_lon.cos();
+        y.atan2(x).to_degrees().rem_euclid(360.0)
+    }
+
+    /// Bearing on arrival at `other` when following the great circle (degrees, 0..360)
+    pub fn final_bearing_to(&self, other: &Coordinate) -> f64 {
+        (other.initial_bearing_to(self) + 180.0).rem_euclid(360.0)
+    }
+
+    /// Point reached after `distance_km` along the great circle starting at `bearing` degrees
+    pub fn destination(&self, distance_km: f64, bearing: f64) -> Coordinate {
+        let delta = distance_km / EARTH_RADIUS_KM;
+        let theta = bearing.to_radians();
+        let lat1 = self.latitude.to_radians();
+        let lon1 = self.longitude.to_radians();
+
+        let lat2 = (lat1.sin() * delta.cos() + lat1.cos() * delta.sin() * theta.cos()).asin();
+        let lon2 = lon1
+            + (theta.sin() * delta.sin() * lat1.cos()).atan2(delta.cos() - lat1.sin() * lat2.sin());
+
+        Coordinate::new(lat2.to_degrees(), normalize_longitude(lon2.to_degrees()))
+    }
+
+    /// Halfway point along the great circle to `other`
+    pub fn midpoint(&self, other: &Coordinate) -> Coordinate {
+        self.interpolate(other, 0.5)
+    }
+
+    /// Point at `fraction` (0 = self, 1 = other) of the great-circle path to `other`
+    pub fn interpolate(&self, other: &Coordinate, fraction: f64) -> Coordinate {
+        let delta = self.distance_to(other) / EARTH_RADIUS_KM;
+        if delta == 0.0 {
+            return *self;
+        }
+
+        let (lat1, lon1) = (self.latitude.to_radians(), self.longitude.to_radians());
+        let (lat2, lon2) = (other.latitude.to_radians(), other.longitude.to_radians());
+        let a = ((1.0 - fraction) * delta).sin() / delta.sin();
+        let b = (fraction * delta).sin() / delta.sin();
+
+        let x = a * lat1.cos() * lon1.cos() + b * lat2.cos() * lon2.cos();
+        let y = a * lat1.cos() * lon1.sin() + b * lat2.cos() * lon2.sin();
+        let z = a * lat1.sin() + b * lat2.sin();
+
+        Coordinate::new(
+            z.atan2((x * x + y * y).sqrt()).to_degrees(),
+            normalize_longitude(y.atan2(x).to_degrees()),
+        )
+    }
+
+    /// Distance from the great circle through `start` and `end` (in km).
+    /// Positive when this point lies to the right of the path, negative to the left.
+    pub fn cross_track_distance(&self, start: &Coordinate, end: &Coordinate) -> f64 {
+        let delta13 = start.distance_to(self) / EARTH_RADIUS_KM;
+        let theta13 = start.initial_bearing_to(self).to_radians();
+        let theta12 = start.initial_bearing_to(end).to_radians();
+
+        (delta13.sin() * (theta13 - theta12).sin()).asin() * EARTH_RADIUS_KM
+    }
+
+    /// Distance from `start` to the closest point on the path towards `end` (in km).
+    /// Negative when that point lies behind `start`.
+    pub fn along_track_distance(&self, start: &Coordinate, end: &Coordinate) -> f64 {
+        let delta13 = start.distance_to(self) / EARTH_RADIUS_KM;
+        let delta_xt = self.cross_track_distance(start, end) / EARTH_RADIUS_KM;
+        let theta13 = start.initial_bearing_to(self).to_radians();
+        let theta12 = start.initial_bearing_to(end).to_radians();
+
+        let along = (delta13.cos() / delta_xt.cos()).clamp(-1.0, 1.0).acos();
+        along.copysign((theta12 - theta13).cos()) * EARTH_RADIUS_KM
+    }
+}
+
+/// Wrap a longitude into [-180, 180)
+pub fn normalize_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
 }
 
 #[derive(Debug)]
@@ -55,6 +192,23 @@ impl Location {
     }
 }
 
+pub mod dms;
+pub mod ecef;
+pub mod geodesic;
+pub mod geohash;
+pub mod geometry;
+pub mod kalman;
+pub mod nmea;
+pub mod polyline;
+pub mod simplify;
+pub mod utm;
+
+pub use dms::{Ddm, Dms, ParseCoordinateError};
+pub use ecef::{Ecef, Enu};
+pub use geodesic::Ellipsoid;
+pub use geometry::{BoundingBox, LineString, Polygon};
+pub use utm::{Hemisphere, Utm, UtmError};
+
 // Dubai landmarks and areas
 pub mod dubai {
     use super::*;
@@ -108,6 +262,70 @@ mod tests {
         assert!(distance > 10.0 && distance < 20.0); // Approximately 15km
     }
 
+    #[test]
+    fn test_bearings_and_destination() {
+        let a = Coordinate::new(0.0, 0.0);
+        assert!((a.initial_bearing_to(&Coordinate::new(0.0, 10.0)) - 90.0).abs() < 1e-9);
+        assert!((a.initial_bearing_to(&Coordinate::new(-10.0, 0.0)) - 180.0).abs() < 1e-9);
+
+        // Kansas City -> St Louis
+        let kc = Coordinate::new(39.099912, -94.581213);
+        let stl = Coordinate::new(38.627089, -90.200203);
+        assert!((kc.initial_bearing_to(&stl) - 96.51).abs() < 0.01);
+        assert!(kc.final_bearing_to(&stl) > kc.initial_bearing_to(&stl));
+
+        let there = kc.destination(kc.distance_to(&stl), kc.initial_bearing_to(&stl));
+        assert!(there.distance_to(&stl) < 1e-6);
+        let wrapped = Coordinate::new(0.0, 179.5).destination(111.195, 90.0);
+        assert!((wrapped.longitude + 179.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_midpoint_and_interpolation() {
+        let burj = dubai::BURJ_KHALIFA;
+        let marina = dubai::DUBAI_MARINA;
+        let mid = burj.midpoint(&marina);
+        assert!((mid.distance_to(&burj) - mid.distance_to(&marina)).abs() < 1e-9);
+
+        let quarter = burj.interpolate(&marina, 0.25);
+        assert!((quarter.distance_to(&burj) - burj.distance_to(&marina) / 4.0).abs() < 1e-9);
+        assert_eq!(burj.interpolate(&burj, 0.5), burj);
+
+        let across = Coordinate::new(0.0, 179.0).midpoint(&Coordinate::new(0.0, -179.0));
+        assert!((across.longitude.abs() - 180.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cross_and_along_track() {
+        // Trajeto ao longo do equador, ponto 1° ao norte (à esquerda)
+        let start = Coordinate::new(0.0, 0.0);
+        let end = Coordinate::new(0.0, 10.0);
+        let point = Coordinate::new(1.0, 5.0);
+        let degree_km = EARTH_RADIUS_KM * 1f64.to_radians();
+
+        assert!((point.cross_track_distance(&start, &end) + degree_km).abs() < 1e-6);
+        assert!((point.along_track_distance(&start, &end) - 5.0 * degree_km).abs() < 0.01);
+        assert!(Coordinate::new(-1.0, -2.0).along_track_distance(&start, &end) < 0.0);
+    }
+
+    #[test]
+    fn test_try_new_and_normalize() {
+        assert!(Coordinate::try_new(-90.0, 180.0).is_ok());
+        assert_eq!(
+            Coordinate::try_new(500.0, 0.0),
+            Err(CoordinateError::OutOfRange { field: "latitude", value: 500.0 })
+        );
+        assert_eq!(Coordinate::try_new(0.0, f64::NAN).unwrap_err().field(), "longitude");
+        assert!(Coordinate::try_new(f64::INFINITY, 0.0).is_err());
+        assert_eq!(Coordinate::try_new(91.0, 0.0).unwrap_err().to_string(), "latitude 91 is outside [-90, 90]");
+
+        let wrapped = Coordinate::new(10.0, 190.0).normalized();
+        assert_eq!(wrapped, Coordinate::new(10.0, -170.0));
+        assert_eq!(Coordinate::new(0.0, 180.0).normalized().longitude, -180.0);
+        assert_eq!(Coordinate::new(0.0, -540.0).normalized().longitude, -180.0);
+        assert!(Coordinate::try_new(wrapped.latitude, wrapped.longitude).is_ok());
+    }
+
     #[test]
     fn test_coordinate_creation() {
         let coord = Coordinate::new(25.1972, 55.2744);
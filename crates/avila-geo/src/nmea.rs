@@ -0,0 +1,560 @@
+// NMEA 0183 sentence parser
+// Supports RMC, GGA, GSA and VTG from any talker (GP, GN, GL, GA, BD...)
+
+use crate::Coordinate;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum NmeaError {
+    MissingStart,
+    MissingChecksum,
+    InvalidChecksum { expected: u8, found: u8 },
+    UnsupportedSentence(String),
+    InvalidField(&'static str),
+}
+
+/// GGA fix quality indicator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixQuality {
+    Invalid,
+    Gps,
+    Dgps,
+    Pps,
+    Rtk,
+    FloatRtk,
+    Estimated,
+    Manual,
+    Simulation,
+}
+
+impl FixQuality {
+    fn from_digit(value: u8) -> Self {
+        match value {
+            1 => FixQuality::Gps,
+            2 => FixQuality::Dgps,
+            3 => FixQuality::Pps,
+            4 => FixQuality::Rtk,
+            5 => FixQuality::FloatRtk,
+            6 => FixQuality::Estimated,
+            7 => FixQuality::Manual,
+            8 => FixQuality::Simulation,
+            _ => FixQuality::Invalid,
+        }
+    }
+}
+
+/// GSA fix type
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FixType {
+    NoFix,
+    Fix2D,
+    Fix3D,
+}
+
+/// UTC time of day
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NmeaTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: f64,
+}
+
+impl NmeaTime {
+    fn seconds_of_day(&self) -> u64 {
+        self.hour as u64 * 3600 + self.minute as u64 * 60 + self.second as u64
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NmeaDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// Recommended minimum data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rmc {
+    pub time: Option<NmeaTime>,
+    pub date: Option<NmeaDate>,
+    /// Status `A` (active) vs `V` (void)
+    pub valid: bool,
+    pub coordinate: Option<Coordinate>,
+    pub speed_knots: Option<f64>,
+    pub course: Option<f64>,
+}
+
+impl Rmc {
+    /// Seconds since the Unix epoch, when both date and time are present
+    pub fn timestamp(&self) -> Option<u64> {
+        let date = self.date?;
+        let time = self.time?;
+        let days = days_from_civil(date.year as i64, date.month as u32, date.day as u32);
+        u64::try_from(days * 86_400).ok().map(|secs| secs + time.seconds_of_day())
+    }
+}
+
+/// Fix data
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gga {
+    pub time: Option<NmeaTime>,
+    pub coordinate: Option<Coordinate>,
+    pub fix_quality: FixQuality,
+    pub satellites: Option<u8>,
+    pub hdop: Option<f64>,
+    /// Meters above mean sea level
+    pub altitude: Option<f64>,
+}
+
+/// DOP and active satellites
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gsa {
+    pub fix_type: FixType,
+    pub satellites: Vec<u8>,
+    pub pdop: Option<f64>,
+    pub hdop: Option<f64>,
+    pub vdop: Option<f64>,
+}
+
+/// Track made good and ground speed
+#[derive(Debug, Clone, PartialEq)]
+pub struct Vtg {
+    pub course: Option<f64>,
+    pub speed_knots: Option<f64>,
+    pub speed_kmh: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Sentence {
+    Rmc(Rmc),
+    Gga(Gga),
+    Gsa(Gsa),
+    Vtg(Vtg),
+}
+
+/// Check the `*hh` checksum and return the sentence body between `$` and `*`
+pub fn validate_checksum(sentence: &str) -> Result<&str, NmeaError> {
+    let sentence = sentence.trim();
+    let body_start = sentence
+        .strip_prefix('$')
+        .or_else(|| sentence.strip_prefix('!'))
+        .ok_or(NmeaError::MissingStart)?;
+    let (body, checksum) = body_start.rsplit_once('*').ok_or(NmeaError::MissingChecksum)?;
+
+    let found = u8::from_str_radix(checksum.trim(), 16)
+        .map_err(|_| NmeaError::InvalidField("checksum"))?;
+    let expected = body.bytes().fold(0u8, |acc, b| acc ^ b);
+
+    if expected != found {
+        return Err(NmeaError::InvalidChecksum { expected, found });
+    }
+    Ok(body)
+}
+
+/// Parse one sentence, e.g. `$GPRMC,...*hh`
+pub fn parse(sentence: &str) -> Result<Sentence, NmeaError> {
+    let body = validate_checksum(sentence)?;
+    let fields: Vec<&str> = body.split(',').collect();
+    let address = fields[0];
+    if address.len() < 5 || !address.is_ascii() {
+        return Err(NmeaError::UnsupportedSentence(address.to_string()));
+    }
+
+    match &address[address.len() - 3..] {
+        "RMC" => parse_rmc(&fields),
+        "GGA" => parse_gga(&fields),
+        "GSA" => parse_gsa(&fields),
+        "VTG" => parse_vtg(&fields),
+        _ => Err(NmeaError::UnsupportedSentence(address.to_string())),
+    }
+}
+
+fn field<'a>(fields: &[&'a str], index: usize) -> Option<&'a str> {
+    fields.get(index).map(|f| f.trim()).filter(|f| !f.is_empty())
+}
+
+fn number(fields: &[&str], index: usize, name: &'static str) -> Result<Option<f64>, NmeaError> {
+    match field(fields, index) {
+        Some(v) => v.parse().map(Some).map_err(|_| NmeaError::InvalidField(name)),
+        None => Ok(None),
+    }
+}
+
+fn parse_time(value: Option<&str>) -> Result<Option<NmeaTime>, NmeaError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let err = NmeaError::InvalidField("time");
+    if value.len() < 6 || !value.is_ascii() {
+        return Err(err);
+    }
+
+    let hour: u8 = value[0..2].parse().map_err(|_| err.clone())?;
+    let minute: u8 = value[2..4].parse().map_err(|_| err.clone())?;
+    let second: f64 = value[4..].parse().map_err(|_| err.clone())?;
+    if hour > 23 || minute > 59 || second >= 61.0 {
+        return Err(err);
+    }
+    Ok(Some(NmeaTime { hour, minute, second }))
+}
+
+fn parse_date(value: Option<&str>) -> Result<Option<NmeaDate>, NmeaError> {
+    let value = match value {
+        Some(v) => v,
+        None => return Ok(None),
+    };
+    let err = NmeaError::InvalidField("date");
+    if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(err);
+    }
+
+    let day: u8 = value[0..2].parse().map_err(|_| err.clone())?;
+    let month: u8 = value[2..4].parse().map_err(|_| err.clone())?;
+    let yy: u16 = value[4..6].parse().map_err(|_| err.clone())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(err);
+    }
+    // Ano com dois dígitos: 80-99 => 19xx
+    let year = if yy >= 80 { 1900 + yy } else { 2000 + yy };
+    Ok(Some(NmeaDate { year, month, day }))
+}
+
+/// `ddmm.mmmm` + hemisphere (latitude) or `dddmm.mmmm` + hemisphere (longitude)
+fn parse_angle(value: Option<&str>, hemisphere: Option<&str>, degree_digits: usize) -> Result<Option<f64>, NmeaError> {
+    let (value, hemisphere) = match (value, hemisphere) {
+        (Some(v), Some(h)) => (v, h),
+        (None, _) => return Ok(None),
+        (Some(_), None) => return Err(NmeaError::InvalidField("hemisphere")),
+    };
+    let err = NmeaError::InvalidField(if degree_digits == 2 { "latitude" } else { "longitude" });
+    if value.len() < degree_digits + 2 || !value.is_char_boundary(degree_digits) {
+        return Err(err);
+    }
+
+    let degrees: f64 = value[..degree_digits].parse().map_err(|_| err.clone())?;
+    let minutes: f64 = value[degree_digits..].parse().map_err(|_| err.clone())?;
+    if minutes >= 60.0 {
+        return Err(err);
+    }
+    let angle = degrees + minutes / 60.0;
+
+    match hemisphere {
+        "N" | "E" => Ok(Some(angle)),
+        "S" | "W" => Ok(Some(-angle)),
+        _ => Err(NmeaError::InvalidField("hemisphere")),
+    }
+}
+
+fn parse_coordinate(fields: &[&str], index: usize) -> Result<Option<Coordinate>, NmeaError> {
+    let latitude = parse_angle(field(fields, index), field(fields, index + 1), 2)?;
+    let longitude = parse_angle(field(fields, index + 2), field(fields, index + 3), 3)?;
+    match (latitude, longitude) {
+        (Some(lat), Some(lon)) if lat.abs() <= 90.0 && lon.abs() <= 180.0 => Ok(Some(Coordinate::new(lat, lon))),
+        (Some(_), Some(_)) => Err(NmeaError::InvalidField("coordinate")),
+        _ => Ok(None),
+    }
+}
+
+// $--RMC,hhmmss.ss,A,llll.ll,a,yyyyy.yy,a,x.x,x.x,ddmmyy,x.x,a*hh
+fn parse_rmc(fields: &[&str]) -> Result<Sentence, NmeaError> {
+    Ok(Sentence::Rmc(Rmc {
+        time: parse_time(field(fields, 1))?,
+        valid: field(fields, 2) == Some("A"),
+        coordinate: parse_coordinate(fields, 3)?,
+        speed_knots: number(fields, 7, "speed")?,
+        course: number(fields, 8, "course")?,
+        date: parse_date(field(fields, 9))?,
+    }))
+}
+
+// $--GGA,hhmmss.ss,llll.ll,a,yyyyy.yy,a,x,xx,x.x,x.x,M,x.x,M,x.x,xxxx*hh
+fn parse_gga(fields: &[&str]) -> Result<Sentence, NmeaError> {
+    let quality = match field(fields, 6) {
+        Some(v) => v.parse::<u8>().map_err(|_| NmeaError::InvalidField("fix quality"))?,
+        None => 0,
+    };
+    let satellites = match field(fields, 7) {
+        Some(v) => Some(v.parse::<u8>().map_err(|_| NmeaError::InvalidField("satellites"))?),
+        None => None,
+    };
+
+    Ok(Sentence::Gga(Gga {
+        time: parse_time(field(fields, 1))?,
+        coordinate: parse_coordinate(fields, 2)?,
+        fix_quality: FixQuality::from_digit(quality),
+        satellites,
+        hdop: number(fields, 8, "hdop")?,
+        altitude: number(fields, 9, "altitude")?,
+    }))
+}
+
+// $--GSA,a,x,xx,xx,xx,xx,xx,xx,xx,xx,xx,xx,xx,xx,x.x,x.x,x.x*hh
+fn parse_gsa(fields: &[&str]) -> Result<Sentence, NmeaError> {
+    let fix_type = match field(fields, 2) {
+        Some("2") => FixType::Fix2D,
+        Some("3") => FixType::Fix3D,
+        _ => FixType::NoFix,
+    };
+    let mut satellites = Vec::new();
+    for index in 3..15 {
+        if let Some(prn) = field(fields, index) {
+            satellites.push(prn.parse().map_err(|_| NmeaError::InvalidField("satellite"))?);
+        }
+    }
+
+    Ok(Sentence::Gsa(Gsa {
+        fix_type,
+        satellites,
+        pdop: number(fields, 15, "pdop")?,
+        hdop: number(fields, 16, "hdop")?,
+        vdop: number(fields, 17, "vdop")?,
+    }))
+}
+
+// $--VTG,x.x,T,x.x,M,x.x,N,x.x,K,m*hh
+fn parse_vtg(fields: &[&str]) -> Result<Sentence, NmeaError> {
+    Ok(Sentence::Vtg(Vtg {
+        course: number(fields, 1, "course")?,
+        speed_knots: number(fields, 5, "speed")?,
+        speed_kmh: number(fields, 7, "speed")?,
+    }))
+}
+
+/// A position fix assembled from the sentences of one reporting cycle
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fix {
+    pub coordinate: Coordinate,
+    /// Seconds since the Unix epoch; `None` when the receiver sent no date (GGA only)
+    pub timestamp: Option<u64>,
+    pub fix_quality: Option<FixQuality>,
+    pub satellites: Option<u8>,
+    pub hdop: Option<f64>,
+    pub altitude: Option<f64>,
+    pub speed_knots: Option<f64>,
+    pub course: Option<f64>,
+}
+
+/// Merges the sentences of a stream into fixes.
+///
+/// A fix is emitted for every valid RMC, enriched with the GGA/GSA/VTG data
+/// seen since the previous one. Receivers that never send RMC get a fix per
+/// GGA instead, without a date, once the next GGA (or `finish`) closes the cycle.
+#[derive(Debug, Default)]
+pub struct FixAccumulator {
+    gga: Option<Gga>,
+    gsa: Option<Gsa>,
+    vtg: Option<Vtg>,
+    seen_rmc: bool,
+}
+
+impl FixAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, sentence: Sentence) -> Option<Fix> {
+        match sentence {
+            Sentence::Rmc(rmc) => {
+                self.seen_rmc = true;
+                let gga = self.gga.take();
+                let gsa = self.gsa.take();
+                let vtg = self.vtg.take();
+                if !rmc.valid {
+                    return None;
+                }
+
+                // GGA só vale se for do mesmo instante
+                let gga = gga.filter(|g| g.time.is_none() || g.time == rmc.time);
+                Some(Fix {
+                    coordinate: rmc.coordinate?,
+                    timestamp: rmc.timestamp(),
+                    fix_quality: gga.as_ref().map(|g| g.fix_quality),
+                    satellites: gga.as_ref().and_then(|g| g.satellites),
+                    hdop: gga.as_ref().and_then(|g| g.hdop).or(gsa.and_then(|g| g.hdop)),
+                    altitude: gga.as_ref().and_then(|g| g.altitude),
+                    speed_knots: rmc.speed_knots.or(vtg.as_ref().and_then(|v| v.speed_knots)),
+                    course: rmc.course.or(vtg.and_then(|v| v.course)),
+                })
+            }
+            Sentence::Gga(gga) => {
+                // Um GGA que nenhum RMC consumiu fecha o ciclo anterior
+                if self.seen_rmc {
+                    self.gga = Some(gga);
+                    None
+                } else {
+                    let previous = self.gga.replace(gga);
+                    self.gga_fix(previous)
+                }
+            }
+            Sentence::Gsa(gsa) => {
+                self.gsa = Some(gsa);
+                None
+            }
+            Sentence::Vtg(vtg) => {
+                self.vtg = Some(vtg);
+                None
+            }
+        }
+    }
+
+    /// Emit the pending GGA-only fix at the end of a stream
+    pub fn finish(&mut self) -> Option<Fix> {
+        if self.seen_rmc {
+            return None;
+        }
+        let pending = self.gga.take();
+        self.gga_fix(pending)
+    }
+
+    fn gga_fix(&mut self, gga: Option<Gga>) -> Option<Fix> {
+        let gsa = self.gsa.take();
+        let vtg = self.vtg.take();
+        let gga = gga.filter(|g| g.fix_quality != FixQuality::Invalid)?;
+        Some(Fix {
+            coordinate: gga.coordinate?,
+            timestamp: None,
+            fix_quality: Some(gga.fix_quality),
+            satellites: gga.satellites,
+            hdop: gga.hdop.or(gsa.and_then(|g| g.hdop)),
+            altitude: gga.altitude,
+            speed_knots: vtg.as_ref().and_then(|v| v.speed_knots),
+            course: vtg.and_then(|v| v.course),
+        })
+    }
+}
+
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMC: &str = "$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A";
+    const GGA: &str = "$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47";
+    const GSA: &str = "$GPGSA,A,3,04,05,,09,12,,,24,,,,,2.5,1.3,2.1*39";
+    const VTG: &str = "$GPVTG,054.7,T,034.4,M,005.5,N,010.2,K*48";
+
+    #[test]
+    fn test_checksum() {
+        assert!(validate_checksum(RMC).is_ok());
+        assert_eq!(
+            validate_checksum("$GPRMC,123519,A*00"),
+            Err(NmeaError::InvalidChecksum { expected: 0x07, found: 0 })
+        );
+        assert_eq!(validate_checksum("$GPRMC,123519,A"), Err(NmeaError::MissingChecksum));
+        assert_eq!(validate_checksum("GPRMC*00"), Err(NmeaError::MissingStart));
+    }
+
+    #[test]
+    fn test_parse_rmc() {
+        let rmc = match parse(RMC).unwrap() {
+            Sentence::Rmc(rmc) => rmc,
+            other => panic!("unexpected {:?}", other),
+        };
+        assert!(rmc.valid);
+        let coord = rmc.coordinate.unwrap();
+        assert!((coord.latitude - 48.1173).abs() < 1e-4);
+        assert!((coord.longitude - 11.516_666).abs() < 1e-5);
+        assert_eq!(rmc.speed_knots, Some(22.4));
+        assert_eq!(rmc.course, Some(84.4));
+        // 1994-03-23T12:35:19Z
+        assert_eq!(rmc.timestamp(), Some(764_426_119));
+    }
+
+    #[test]
+    fn test_parse_gga_gsa_vtg() {
+        match parse(GGA).unwrap() {
+            Sentence::Gga(gga) => {
+                assert_eq!(gga.fix_quality, FixQuality::Gps);
+                assert_eq!(gga.satellites, Some(8));
+                assert_eq!(gga.hdop, Some(0.9));
+                assert_eq!(gga.altitude, Some(545.4));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse(GSA).unwrap() {
+            Sentence::Gsa(gsa) => {
+                assert_eq!(gsa.fix_type, FixType::Fix3D);
+                assert_eq!(gsa.satellites, vec![4, 5, 9, 12, 24]);
+                assert_eq!(gsa.hdop, Some(1.3));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        match parse(VTG).unwrap() {
+            Sentence::Vtg(vtg) => {
+                assert_eq!(vtg.course, Some(54.7));
+                assert_eq!(vtg.speed_kmh, Some(10.2));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_southern_western_hemispheres() {
+        let body = "GNGGA,000000,2333.000,S,04638.000,W,1,5,1.0,760,M,,,,";
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        match parse(&format!("${}*{:02X}", body, checksum)).unwrap() {
+            Sentence::Gga(gga) => {
+                let coord = gga.coordinate.unwrap();
+                assert!((coord.latitude + 23.55).abs() < 1e-9);
+                assert!((coord.longitude + 46.633_333).abs() < 1e-5);
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_accumulator_merges_cycle() {
+        let mut acc = FixAccumulator::new();
+        assert!(acc.push(parse(GGA).unwrap()).is_none());
+        assert!(acc.push(parse(GSA).unwrap()).is_none());
+        let fix = acc.push(parse(RMC).unwrap()).unwrap();
+        assert!(acc.finish().is_none());
+        assert_eq!(fix.satellites, Some(8));
+        assert_eq!(fix.altitude, Some(545.4));
+        assert_eq!(fix.timestamp, Some(764_426_119));
+    }
+
+    #[test]
+    fn test_accumulator_gga_only_receiver() {
+        let mut acc = FixAccumulator::new();
+        assert!(acc.push(parse(GGA).unwrap()).is_none());
+        assert!(acc.push(parse(VTG).unwrap()).is_none());
+
+        // O próximo GGA fecha o ciclo anterior, com o VTG recebido nele
+        let fix = acc.push(parse(GGA).unwrap()).unwrap();
+        assert_eq!(fix.timestamp, None);
+        assert_eq!(fix.course, Some(54.7));
+        assert!(acc.finish().is_some());
+        assert!(acc.finish().is_none());
+    }
+
+    #[test]
+    fn test_unsupported_sentence() {
+        let body = "GPGSV,1,1,00";
+        let checksum = body.bytes().fold(0u8, |acc, b| acc ^ b);
+        assert_eq!(
+            parse(&format!("${}*{:02X}", body, checksum)),
+            Err(NmeaError::UnsupportedSentence("GPGSV".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_non_ascii_fields_are_rejected() {
+        let sign = |body: &str| format!("${}*{:02X}", body, body.bytes().fold(0u8, |acc, b| acc ^ b));
+        // Checksum válido, mas cortar por bytes cairia no meio de um caractere
+        assert_eq!(sign("GPéé,1"), "$GPéé,1*0A");
+        assert_eq!(parse("$GPéé,1*0A"), Err(NmeaError::UnsupportedSentence("GPéé".to_string())));
+
+        let rmc = sign("GPRMC,1é3456,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W");
+        assert_eq!(parse(&rmc), Err(NmeaError::InvalidField("time")));
+    }
+}
@@ -0,0 +1,122 @@
+// Google Encoded Polyline Algorithm
+// Diferenças de lat/lon em inteiros com sinal, 5 bits por caractere ASCII (63..=126)
+
+use crate::Coordinate;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolylineError {
+    InvalidCharacter(char),
+    /// The string ended in the middle of a value or after a lone latitude
+    Truncated,
+}
+
+/// Encode `coordinates` with `precision` decimal digits (5 for Google Maps, 6 for OSRM/Valhalla)
+pub fn encode(coordinates: &[Coordinate], precision: u32) -> String {
+    let factor = 10f64.powi(precision as i32);
+    let mut out = String::new();
+    let (mut last_lat, mut last_lon) = (0i64, 0i64);
+
+    for coordinate in coordinates {
+        let lat = (coordinate.latitude * factor).round() as i64;
+        let lon = (coordinate.longitude * factor).round() as i64;
+        encode_value(lat - last_lat, &mut out);
+        encode_value(lon - last_lon, &mut out);
+        (last_lat, last_lon) = (lat, lon);
+    }
+    out
+}
+
+fn encode_value(value: i64, out: &mut String) {
+    // Zigzag: o bit de sinal vai para o bit menos significativo
+    let mut value = if value < 0 { !(value << 1) } else { value << 1 } as u64;
+    while value >= 0x20 {
+        out.push((((value & 0x1f) | 0x20) as u8 + 63) as char);
+        value >>= 5;
+    }
+    out.push((value as u8 + 63) as char);
+}
+
+/// Decode a polyline produced with the same `precision`
+pub fn decode(polyline: &str, precision: u32) -> Result<Vec<Coordinate>, PolylineError> {
+    let factor = 10f64.powi(precision as i32);
+    let mut chars = polyline.chars();
+    let mut coordinates = Vec::new();
+    let (mut lat, mut lon) = (0i64, 0i64);
+
+    while let Some(d_lat) = decode_value(&mut chars)? {
+        let d_lon = decode_value(&mut chars)?.ok_or(PolylineError::Truncated)?;
+        lat += d_lat;
+        lon += d_lon;
+        coordinates.push(Coordinate::new(lat as f64 / factor, lon as f64 / factor));
+    }
+    Ok(coordinates)
+}
+
+/// Next value, or `None` at the end of the input
+fn decode_value(chars: &mut std::str::Chars) -> Result<Option<i64>, PolylineError> {
+    let (mut result, mut shift) = (0u64, 0u32);
+    loop {
+        let c = match chars.next() {
+            Some(c) => c,
+            None if shift == 0 => return Ok(None),
+            None => return Err(PolylineError::Truncated),
+        };
+        if !('?'..='~').contains(&c) || shift > 60 {
+            return Err(PolylineError::InvalidCharacter(c));
+        }
+        let chunk = c as u64 - 63;
+        result |= (chunk & 0x1f) << shift;
+        shift += 5;
+        if chunk < 0x20 {
+            break;
+        }
+    }
+    let value = (result >> 1) as i64;
+    Ok(Some(if result & 1 == 1 { !value } else { value }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn points() -> Vec<Coordinate> {
+        vec![
+            Coordinate::new(38.5, -120.2),
+            Coordinate::new(40.7, -120.95),
+            Coordinate::new(43.252, -126.453),
+        ]
+    }
+
+    #[test]
+    fn test_reference_polyline() {
+        // Exemplo da documentação do Google
+        assert_eq!(encode(&points(), 5), "_p~iF~ps|U_ulLnnqC_mqNvxq`@");
+        let decoded = decode("_p~iF~ps|U_ulLnnqC_mqNvxq`@", 5).unwrap();
+        assert_eq!(decoded.len(), 3);
+        for (a, b) in decoded.iter().zip(points()) {
+            assert!((a.latitude - b.latitude).abs() < 1e-9 && (a.longitude - b.longitude).abs() < 1e-9);
+        }
+        assert_eq!(encode(&[], 5), "");
+        assert_eq!(decode("", 5).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_precision_six_round_trip() {
+        let track = vec![
+            crate::dubai::BURJ_KHALIFA,
+            Coordinate::new(-33.856784, 151.215297),
+            Coordinate::new(0.000001, -0.000001),
+        ];
+        let decoded = decode(&encode(&track, 6), 6).unwrap();
+        for (a, b) in decoded.iter().zip(&track) {
+            assert!((a.latitude - b.latitude).abs() < 1e-6 && (a.longitude - b.longitude).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        assert_eq!(decode("_p~iF", 5), Err(PolylineError::Truncated));
+        assert_eq!(decode("_p~", 5), Err(PolylineError::Truncated));
+        assert_eq!(decode("_p iF~ps|U", 5), Err(PolylineError::InvalidCharacter(' ')));
+    }
+}
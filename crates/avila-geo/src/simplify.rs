@@ -0,0 +1,195 @@
+// Simplificação de trilhas: Douglas-Peucker e Visvalingam-Whyatt
+// Tolerâncias em metros sobre a esfera, não em graus; o primeiro e o último ponto sempre ficam
+
+use crate::{normalize_longitude, Coordinate, EARTH_RADIUS_KM};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Points of `points` kept by [`douglas_peucker_indices`]
+pub fn douglas_peucker(points: &[Coordinate], tolerance_m: f64) -> Vec<Coordinate> {
+    douglas_peucker_indices(points, tolerance_m).into_iter().map(|i| points[i]).collect()
+}
+
+/// Indices (ascending) of the points kept by Douglas-Peucker: every removed
+/// point lies within `tolerance_m` of the simplified line
+pub fn douglas_peucker_indices(points: &[Coordinate], tolerance_m: f64) -> Vec<usize> {
+    if points.len() < 3 {
+        return (0..points.len()).collect();
+    }
+
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Pilha explícita: trilhas longas estourariam a recursão
+    let mut stack = vec![(0, points.len() - 1)];
+    while let Some((first, last)) = stack.pop() {
+        let mut farthest = (0, 0.0);
+        for i in first + 1..last {
+            let distance = segment_distance_m(&points[i], &points[first], &points[last]);
+            if distance > farthest.1 {
+                farthest = (i, distance);
+            }
+        }
+        if farthest.1 > tolerance_m {
+            keep[farthest.0] = true;
+            stack.push((first, farthest.0));
+            stack.push((farthest.0, last));
+        }
+    }
+
+    (0..points.len()).filter(|&i| keep[i]).collect()
+}
+
+/// Points of `points` kept by [`visvalingam_whyatt_indices`]
+pub fn visvalingam_whyatt(points: &[Coordinate], tolerance_m: f64) -> Vec<Coordinate> {
+    visvalingam_whyatt_indices(points, tolerance_m).into_iter().map(|i| points[i]).collect()
+}
+
+/// Indices (ascending) of the points kept by Visvalingam-Whyatt: points are
+/// removed while the smallest triangle they form with their neighbors is
+/// under `tolerance_m²` square meters
+pub fn visvalingam_whyatt_indices(points: &[Coordinate], tolerance_m: f64) -> Vec<usize> {
+    let n = points.len();
+    if n < 3 {
+        return (0..n).collect();
+    }
+
+    let threshold = tolerance_m * tolerance_m;
+    let mut previous: Vec<usize> = (0..n).map(|i| i.wrapping_sub(1)).collect();
+    let mut next: Vec<usize> = (1..=n).collect();
+    let mut areas = vec![f64::INFINITY; n];
+    let mut heap = BinaryHeap::new();
+    for i in 1..n - 1 {
+        areas[i] = triangle_area_m2(&points[i - 1], &points[i], &points[i + 1]);
+        heap.push(Candidate { area: areas[i], index: i });
+    }
+
+    let mut removed = vec![false; n];
+    while let Some(Candidate { area, index }) = heap.pop() {
+        // Entradas antigas ficam no heap; só vale a área atual do ponto
+        if removed[index] || area != areas[index] {
+            continue;
+        }
+        if area >= threshold {
+            break;
+        }
+        removed[index] = true;
+        let (before, after) = (previous[index], next[index]);
+        next[before] = after;
+        previous[after] = before;
+
+        // A área de um vizinho nunca fica menor que a do ponto removido,
+        // senão ele sairia antes de pontos mais significativos
+        for neighbor in [before, after] {
+            if neighbor == 0 || neighbor == n - 1 {
+                continue;
+            }
+            let recomputed = triangle_area_m2(&points[previous[neighbor]], &points[neighbor], &points[next[neighbor]]);
+            areas[neighbor] = recomputed.max(area);
+            heap.push(Candidate { area: areas[neighbor], index: neighbor });
+        }
+    }
+
+    (0..n).filter(|&i| !removed[i]).collect()
+}
+
+/// Distance from `point` to the great-circle segment `start`-`end`
+fn segment_distance_m(point: &Coordinate, start: &Coordinate, end: &Coordinate) -> f64 {
+    let length = start.distance_to(end);
+    if length == 0.0 {
+        return point.distance_to(start) * 1000.0;
+    }
+    let along = point.along_track_distance(start, end);
+    let km = if along <= 0.0 {
+        point.distance_to(start)
+    } else if along >= length {
+        point.distance_to(end)
+    } else {
+        point.cross_track_distance(start, end).abs()
+    };
+    km * 1000.0
+}
+
+/// Area of the triangle `a`-`b`-`c`, projected onto the plane tangent at `b`
+fn triangle_area_m2(a: &Coordinate, b: &Coordinate, c: &Coordinate) -> f64 {
+    let meters_per_degree = EARTH_RADIUS_KM * 1000.0 * std::f64::consts::PI / 180.0;
+    let cos_lat = b.latitude.to_radians().cos();
+    let project = |p: &Coordinate| {
+        (
+            normalize_longitude(p.longitude - b.longitude) * cos_lat * meters_per_degree,
+            (p.latitude - b.latitude) * meters_per_degree,
+        )
+    };
+    let ((xa, ya), (xc, yc)) = (project(a), project(c));
+    (xa * yc - xc * ya).abs() / 2.0
+}
+
+/// Min-heap entry ordered by area
+#[derive(Debug, PartialEq)]
+struct Candidate {
+    area: f64,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.area.total_cmp(&self.area).then(other.index.cmp(&self.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Linha reta para o leste com ruído lateral de até 3 m e um desvio de 200 m no meio
+    fn noisy_track() -> Vec<Coordinate> {
+        let start = crate::dubai::BURJ_KHALIFA;
+        (0..=100)
+            .map(|i| {
+                let along = start.destination(i as f64 * 0.01, 90.0);
+                let offset = if i == 50 { 0.2 } else { [0.0, 0.003, -0.002][i % 3] };
+                along.destination(offset, 0.0)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_douglas_peucker() {
+        let track = noisy_track();
+        assert_eq!(douglas_peucker_indices(&track, 10.0), vec![0, 49, 50, 51, 100]);
+        assert_eq!(douglas_peucker_indices(&track, 1000.0), vec![0, 100]);
+        assert_eq!(douglas_peucker(&track, 0.0).len(), 101);
+        assert_eq!(douglas_peucker(&track[..2], 10.0), track[..2].to_vec());
+
+        // Ponto além do fim do segmento: conta a distância ao extremo, não à reta
+        let back = vec![track[0], track[100], track[0].destination(0.5, 90.0)];
+        assert_eq!(douglas_peucker_indices(&back, 100.0), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_visvalingam_whyatt() {
+        let track = noisy_track();
+        let kept = visvalingam_whyatt_indices(&track, 30.0);
+        assert_eq!((kept[0], *kept.last().unwrap()), (0, 100));
+        assert!(kept.contains(&50));
+        assert!(kept.len() <= 5, "{:?}", kept);
+        assert_eq!(visvalingam_whyatt(&track, 0.0).len(), 101);
+
+        // Triângulo retângulo de 100 m x 100 m: 5000 m²
+        let a = crate::dubai::BURJ_KHALIFA;
+        let b = a.destination(0.1, 90.0);
+        let c = b.destination(0.1, 0.0);
+        assert!((triangle_area_m2(&a, &b, &c) - 5000.0).abs() < 1.0);
+        assert_eq!(visvalingam_whyatt_indices(&[a, b, c], 70.0), vec![0, 1, 2]);
+        assert_eq!(visvalingam_whyatt_indices(&[a, b, c], 71.0), vec![0, 2]);
+    }
+}
@@ -0,0 +1,366 @@
+// UTM (Transversa de Mercator, séries de Krüger até n^4) e MGRS sobre o WGS84
+// Inclui as exceções de zona da Noruega e de Svalbard; polos (UPS) não são suportados
+
+use crate::{Coordinate, Ellipsoid};
+use std::fmt;
+
+const K0: f64 = 0.9996;
+const FALSE_EASTING: f64 = 500_000.0;
+const FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+
+/// Latitude bands C..X, 8° each from 80°S; X spans 72°N..84°N
+const BANDS: &[u8; 20] = b"CDEFGHJKLMNPQRSTUVWX";
+/// 100 km square column letters, repeating every three zones
+const COLUMN_SETS: [&[u8; 8]; 3] = [b"ABCDEFGH", b"JKLMNPQR", b"STUVWXYZ"];
+const ROW_LETTERS: &[u8; 20] = b"ABCDEFGHJKLMNPQRSTUV";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum UtmError {
+    /// UTM only covers 80°S..84°N
+    LatitudeOutOfRange(f64),
+    InvalidZone(u8),
+    InvalidMgrs(&'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hemisphere {
+    North,
+    South,
+}
+
+/// UTM position in meters
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utm {
+    pub zone: u8,
+    pub hemisphere: Hemisphere,
+    pub easting: f64,
+    pub northing: f64,
+}
+
+/// Krüger series coefficients for the WGS84 ellipsoid
+struct Kruger {
+    a: f64,
+    alpha: [f64; 4],
+    beta: [f64; 4],
+    delta: [f64; 4],
+    e: f64,
+}
+
+impl Kruger {
+    fn wgs84() -> Self {
+        let Ellipsoid { a, f } = Ellipsoid::WGS84;
+        let n = f / (2.0 - f);
+        let (n2, n3, n4) = (n * n, n * n * n, n * n * n * n);
+
+        Kruger {
+            a: a / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0),
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0,
+                49561.0 * n4 / 161280.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0,
+                4397.0 * n4 / 161280.0,
+            ],
+            delta: [
+                2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0,
+                7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0,
+                56.0 * n3 / 15.0 - 136.0 * n4 / 35.0,
+                4279.0 * n4 / 630.0,
+            ],
+            e: (f * (2.0 - f)).sqrt(),
+        }
+    }
+}
+
+fn central_meridian(zone: u8) -> f64 {
+    zone as f64 * 6.0 - 183.0
+}
+
+/// Standard zone for a position, applying the Norway and Svalbard exceptions
+pub fn zone_for(coordinate: &Coordinate) -> u8 {
+    let lat = coordinate.latitude;
+    let lon = crate::normalize_longitude(coordinate.longitude);
+    let zone = (((lon + 180.0) / 6.0).floor() as u8 + 1).min(60);
+
+    if (56.0..64.0).contains(&lat) && (3.0..12.0).contains(&lon) {
+        return 32;
+    }
+    if (72.0..=84.0).contains(&lat) && (0.0..42.0).contains(&lon) {
+        return match lon {
+            l if l < 9.0 => 31,
+            l if l < 21.0 => 33,
+            l if l < 33.0 => 35,
+            _ => 37,
+        };
+    }
+    zone
+}
+
+/// MGRS latitude band letter
+pub fn band_for(latitude: f64) -> Option<char> {
+    if !(-80.0..=84.0).contains(&latitude) {
+        return None;
+    }
+    let index = (((latitude + 80.0) / 8.0).floor() as usize).min(BANDS.len() - 1);
+    Some(BANDS[index] as char)
+}
+
+impl Utm {
+    pub fn from_coordinate(coordinate: &Coordinate) -> Result<Utm, UtmError> {
+        Utm::from_coordinate_in_zone(coordinate, zone_for(coordinate))
+    }
+
+    /// Project into a given zone, e.g. to keep a survey in one grid across a zone edge
+    pub fn from_coordinate_in_zone(coordinate: &Coordinate, zone: u8) -> Result<Utm, UtmError> {
+        if !(1..=60).contains(&zone) {
+            return Err(UtmError::InvalidZone(zone));
+        }
+        if !(-80.0..=84.0).contains(&coordinate.latitude) {
+            return Err(UtmError::LatitudeOutOfRange(coordinate.latitude));
+        }
+
+        let k = Kruger::wgs84();
+        let phi = coordinate.latitude.to_radians();
+        let lambda = crate::normalize_longitude(coordinate.longitude - central_meridian(zone)).to_radians();
+
+        // Latitude conforme
+        let t = (phi.sin().atanh() - k.e * (k.e * phi.sin()).atanh()).sinh();
+        let xi_prime = t.atan2(lambda.cos());
+        let eta_prime = (lambda.sin() / (1.0 + t * t).sqrt()).atanh();
+
+        let mut xi = xi_prime;
+        let mut eta = eta_prime;
+        for (j, alpha) in k.alpha.iter().enumerate() {
+            let m = 2.0 * (j + 1) as f64;
+            xi += alpha * (m * xi_prime).sin() * (m * eta_prime).cosh();
+            eta += alpha * (m * xi_prime).cos() * (m * eta_prime).sinh();
+        }
+
+        let hemisphere = if coordinate.latitude < 0.0 { Hemisphere::South } else { Hemisphere::North };
+        let false_northing = match hemisphere {
+            Hemisphere::North => 0.0,
+            Hemisphere::South => FALSE_NORTHING_SOUTH,
+        };
+
+        Ok(Utm {
+            zone,
+            hemisphere,
+            easting: FALSE_EASTING + K0 * k.a * eta,
+            northing: false_northing + K0 * k.a * xi,
+        })
+    }
+
+    pub fn to_coordinate(&self) -> Coordinate {
+        let k = Kruger::wgs84();
+        let false_northing = match self.hemisphere {
+            Hemisphere::North => 0.0,
+            Hemisphere::South => FALSE_NORTHING_SOUTH,
+        };
+        let xi = (self.northing - false_northing) / (K0 * k.a);
+        let eta = (self.easting - FALSE_EASTING) / (K0 * k.a);
+
+        let mut xi_prime = xi;
+        let mut eta_prime = eta;
+        for (j, beta) in k.beta.iter().enumerate() {
+            let m = 2.0 * (j + 1) as f64;
+            xi_prime -= beta * (m * xi).sin() * (m * eta).cosh();
+            eta_prime -= beta * (m * xi).cos() * (m * eta).sinh();
+        }
+
+        let chi = (xi_prime.sin() / eta_prime.cosh()).asin();
+        let mut phi = chi;
+        for (j, delta) in k.delta.iter().enumerate() {
+            phi += delta * (2.0 * (j + 1) as f64 * chi).sin();
+        }
+        let lambda = eta_prime.sinh().atan2(xi_prime.cos());
+
+        Coordinate::new(
+            phi.to_degrees(),
+            crate::normalize_longitude(central_meridian(self.zone) + lambda.to_degrees()),
+        )
+    }
+
+    /// MGRS reference truncated to `precision` digits per axis (0 = 100 km square, 5 = 1 m)
+    pub fn to_mgrs(&self, precision: u8) -> Result<String, UtmError> {
+        if precision > 5 {
+            return Err(UtmError::InvalidMgrs("precision must be 0..=5"));
+        }
+        let latitude = self.to_coordinate().latitude;
+        let band = band_for(latitude).ok_or(UtmError::LatitudeOutOfRange(latitude))?;
+
+        let column = (self.easting / 100_000.0).floor() as usize;
+        if !(1..=8).contains(&column) {
+            return Err(UtmError::InvalidMgrs("easting outside the zone"));
+        }
+        let column_letter = COLUMN_SETS[(self.zone as usize - 1) % 3][column - 1] as char;
+        let row = (self.northing / 100_000.0).floor() as usize;
+        let row_letter = ROW_LETTERS[(row + row_offset(self.zone)) % 20] as char;
+
+        let scale = 10f64.powi(5 - precision as i32);
+        let easting = ((self.easting % 100_000.0) / scale).floor() as u32;
+        let northing = ((self.northing % 100_000.0) / scale).floor() as u32;
+        let width = precision as usize;
+
+        Ok(if width == 0 {
+            format!("{:02}{}{}{}", self.zone, band, column_letter, row_letter)
+        } else {
+            format!(
+                "{:02}{}{}{}{:0width$}{:0width$}",
+                self.zone, band, column_letter, row_letter, easting, northing
+            )
+        })
+    }
+
+    /// Parse an MGRS reference (spaces allowed); the result is the south-west
+    /// corner of the referenced square
+    pub fn from_mgrs(reference: &str) -> Result<Utm, UtmError> {
+        let compact: String = reference
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        let digits_end = compact.find(|c: char| !c.is_ascii_digit()).ok_or(UtmError::InvalidMgrs("missing band"))?;
+        let zone: u8 = compact[..digits_end].parse().map_err(|_| UtmError::InvalidMgrs("zone"))?;
+        if !(1..=60).contains(&zone) {
+            return Err(UtmError::InvalidZone(zone));
+        }
+
+        let rest = &compact.as_bytes()[digits_end..];
+        if rest.len() < 3 {
+            return Err(UtmError::InvalidMgrs("missing 100 km square"));
+        }
+        let band = BANDS.iter().position(|&b| b == rest[0]).ok_or(UtmError::InvalidMgrs("band"))?;
+        let column = COLUMN_SETS[(zone as usize - 1) % 3]
+            .iter()
+            .position(|&b| b == rest[1])
+            .ok_or(UtmError::InvalidMgrs("100 km column letter"))?;
+        let row = ROW_LETTERS
+            .iter()
+            .position(|&b| b == rest[2])
+            .ok_or(UtmError::InvalidMgrs("100 km row letter"))?;
+
+        let numbers = std::str::from_utf8(&rest[3..]).map_err(|_| UtmError::InvalidMgrs("digits"))?;
+        if numbers.len() % 2 != 0 || numbers.len() > 10 || !numbers.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(UtmError::InvalidMgrs("digits"));
+        }
+        let precision = numbers.len() / 2;
+        let scale = 10f64.powi(5 - precision as i32);
+        let parse = |s: &str| if s.is_empty() { Ok(0.0) } else { s.parse::<f64>().map(|v| v * scale) };
+        let easting = parse(&numbers[..precision]).map_err(|_| UtmError::InvalidMgrs("digits"))?;
+        let northing = parse(&numbers[precision..]).map_err(|_| UtmError::InvalidMgrs("digits"))?;
+
+        let band_bottom = -80.0 + band as f64 * 8.0;
+        let band_middle = band_bottom + if BANDS[band] == b'X' { 6.0 } else { 4.0 };
+        let hemisphere = if band_bottom < 0.0 { Hemisphere::South } else { Hemisphere::North };
+        let row_northing = ((row + 20 - row_offset(zone)) % 20) as f64 * 100_000.0 + northing;
+
+        // As letras de linha repetem a cada 2000 km: escolher o ciclo que cai na faixa
+        let utm = (0..5)
+            .map(|cycle| Utm {
+                zone,
+                hemisphere,
+                easting: (column + 1) as f64 * 100_000.0 + easting,
+                northing: row_northing + cycle as f64 * 2_000_000.0,
+            })
+            .min_by(|a, b| {
+                let da = (a.to_coordinate().latitude - band_middle).abs();
+                let db = (b.to_coordinate().latitude - band_middle).abs();
+                da.total_cmp(&db)
+            })
+            .expect("five candidates");
+        Ok(utm)
+    }
+}
+
+/// Row letters start five letters later in even zones
+fn row_offset(zone: u8) -> usize {
+    if zone.is_multiple_of(2) {
+        5
+    } else {
+        0
+    }
+}
+
+impl fmt::Display for Utm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hemisphere = match self.hemisphere {
+            Hemisphere::North => 'N',
+            Hemisphere::South => 'S',
+        };
+        write!(f, "{}{} {:.0} {:.0}", self.zone, hemisphere, self.easting, self.northing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utm_reference_points() {
+        let burj = Utm::from_coordinate(&crate::dubai::BURJ_KHALIFA).unwrap();
+        assert_eq!(burj.zone, 40);
+        assert_eq!(burj.hemisphere, Hemisphere::North);
+
+        // Torre Eiffel; MGRS trunca (31U DQ 48251 11932), a exibição arredonda
+        let eiffel = Utm::from_coordinate(&Coordinate::new(48.8582, 2.2945)).unwrap();
+        assert_eq!(eiffel.to_string(), "31N 448252 5411933");
+
+        let utm = Utm::from_coordinate(&Coordinate::new(51.2, 7.5)).unwrap();
+        assert_eq!(utm.zone, 32);
+        assert!((utm.easting - 395201.3104).abs() < 1e-3);
+        assert!((utm.northing - 5673135.2412).abs() < 1e-3);
+
+        let sydney = Utm::from_coordinate(&Coordinate::new(-33.8568, 151.2153)).unwrap();
+        assert_eq!((sydney.zone, sydney.hemisphere), (56, Hemisphere::South));
+
+        assert!(Utm::from_coordinate(&Coordinate::new(85.0, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_zone_exceptions() {
+        assert_eq!(zone_for(&Coordinate::new(60.0, 5.0)), 32); // Bergen
+        assert_eq!(zone_for(&Coordinate::new(60.0, 2.0)), 31);
+        assert_eq!(zone_for(&Coordinate::new(78.2, 15.6)), 33); // Longyearbyen
+        assert_eq!(zone_for(&Coordinate::new(78.0, 8.0)), 31);
+        assert_eq!(zone_for(&Coordinate::new(0.0, 180.0)), 1);
+        assert_eq!(band_for(84.0), Some('X'));
+    }
+
+    #[test]
+    fn test_utm_round_trip() {
+        for lat in (-80..=84).step_by(7) {
+            for lon in (-180..180).step_by(13) {
+                let coordinate = Coordinate::new(lat as f64 + 0.25, lon as f64 + 0.5);
+                let utm = Utm::from_coordinate(&coordinate).unwrap();
+                let back = utm.to_coordinate();
+                assert!(back.distance_to(&coordinate) < 1e-6, "{:?} -> {:?}", coordinate, back);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mgrs() {
+        let eiffel = Utm::from_coordinate(&Coordinate::new(48.8582, 2.2945)).unwrap();
+        assert_eq!(eiffel.to_mgrs(5).unwrap(), "31UDQ4825111932");
+        assert_eq!(eiffel.to_mgrs(2).unwrap(), "31UDQ4811");
+        assert_eq!(eiffel.to_mgrs(0).unwrap(), "31UDQ");
+
+        let parsed = Utm::from_mgrs("31U DQ 48251 11932").unwrap();
+        assert_eq!((parsed.zone, parsed.easting, parsed.northing), (31, 448251.0, 5411932.0));
+
+        // Ida e volta em ambos os hemisférios, com 1 m de precisão
+        for coordinate in [Coordinate::new(-33.8568, 151.2153), Coordinate::new(78.2, 15.6), Coordinate::new(-79.5, -60.0)] {
+            let mgrs = Utm::from_coordinate(&coordinate).unwrap().to_mgrs(5).unwrap();
+            let back = Utm::from_mgrs(&mgrs).unwrap().to_coordinate();
+            assert!(back.distance_to(&coordinate) < 0.0015, "{} {:?}", mgrs, back);
+        }
+
+        assert!(Utm::from_mgrs("31UDQ123").is_err());
+        assert!(Utm::from_mgrs("31IDQ").is_err());
+    }
+}
@@ -0,0 +1,197 @@
+// Análise de trajetos: viagens separadas por intervalos sem fixes e por pontos de parada
+// Pontos de parada seguem Li et al. (2008): fixes a até R metros do primeiro por pelo menos T segundos
+
+use crate::stats::{track_distance, DistanceModel};
+use crate::Location;
+use avila_geo::Coordinate;
+use avila_json::JsonValue;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Split a chronological track into trips: a new one starts wherever
+/// consecutive fixes are more than `max_gap` seconds apart, and stay points
+/// (see [`stay_points`]) are cut out. A trip runs from the last fix of one
+/// stay to the first fix of the next. Returns index ranges into `track`.
+pub fn trips(track: &[&Location], max_gap: u64, radius_m: f64, min_duration: u64) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    for part in gap_split(track, max_gap) {
+        let stays = stay_points(&track[part.clone()], radius_m, min_duration);
+        if stays.is_empty() {
+            segments.push(part);
+            continue;
+        }
+
+        let mut start = part.start;
+        for stay in stays {
+            let (arrival, departure) = (part.start + stay.start, part.start + stay.end - 1);
+            // Parado do início do trecho até aqui: não há viagem antes
+            if arrival > start {
+                segments.push(start..arrival + 1);
+            }
+            start = departure;
+        }
+        if part.end - start > 1 {
+            segments.push(start..part.end);
+        }
+    }
+    segments
+}
+
+/// Ranges of `track` with no two consecutive fixes more than `max_gap` seconds apart
+fn gap_split(track: &[&Location], max_gap: u64) -> Vec<Range<usize>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for i in 1..track.len() {
+        if gap(track[i - 1], track[i]) > max_gap {
+            parts.push(start..i);
+            start = i;
+        }
+    }
+    if start < track.len() {
+        parts.push(start..track.len());
+    }
+    parts
+}
+
+/// Stay points of a chronological track: runs of fixes within `radius_m` of
+/// the run's first fix spanning at least `min_duration` seconds. A run still
+/// open at the end of the track counts as well.
+pub fn stay_points(track: &[&Location], radius_m: f64, min_duration: u64) -> Vec<Range<usize>> {
+    let mut stays = Vec::new();
+    let mut i = 0;
+    while i < track.len() {
+        let anchor = coordinate(track[i]);
+        // Primeiro fix que sai do raio (ou o fim da trilha)
+        let j = (i + 1..track.len())
+            .find(|&j| anchor.distance_to(&coordinate(track[j])) * 1000.0 > radius_m)
+            .unwrap_or(track.len());
+
+        if gap(track[i], track[j - 1]) >= min_duration && j - i > 1 {
+            stays.push(i..j);
+            i = j;
+        } else {
+            i += 1;
+        }
+    }
+    stays
+}
+
+/// Mean position, averaged as unit vectors so it works across the antimeridian
+pub fn centroid(points: &[&Location]) -> Coordinate {
+    let (mut x, mut y, mut z) = (0.0, 0.0, 0.0);
+    for location in points {
+        let (lat, lon) = (location.latitude.to_radians(), location.longitude.to_radians());
+        x += lat.cos() * lon.cos();
+        y += lat.cos() * lon.sin();
+        z += lat.sin();
+    }
+    Coordinate::new(z.atan2(x.hypot(y)).to_degrees(), y.atan2(x).to_degrees())
+}
+
+/// Start, end, duration, distance, point count and centroid of a segment
+pub fn segment_json(points: &[&Location], model: DistanceModel) -> JsonValue {
+    let (first, last) = (points[0], points[points.len() - 1]);
+    let center = centroid(points);
+
+    let mut map = HashMap::new();
+    map.insert("start".to_string(), JsonValue::String(first.timestamp.clone()));
+    map.insert("end".to_string(), JsonValue::String(last.timestamp.clone()));
+    map.insert("duration_s".to_string(), JsonValue::Number(gap(first, last) as f64));
+    map.insert("distance_m".to_string(), JsonValue::Number(track_distance(points, model)));
+    map.insert("points".to_string(), JsonValue::Number(points.len() as f64));
+    map.insert("latitude".to_string(), JsonValue::Number(center.latitude));
+    map.insert("longitude".to_string(), JsonValue::Number(center.longitude));
+    JsonValue::Object(map)
+}
+
+fn coordinate(location: &Location) -> Coordinate {
+    Coordinate::new(location.latitude, location.longitude)
+}
+
+/// Seconds between two fixes; 0 when a timestamp is unreadable
+fn gap(from: &Location, to: &Location) -> u64 {
+    match (from.epoch_secs(), to.epoch_secs()) {
+        (Some(a), Some(b)) => b.saturating_sub(a),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (segundos, metros para o leste de um ponto fixo)
+    fn track(points: &[(u64, f64)]) -> Vec<Location> {
+        let origin = Coordinate::new(-23.5614, -46.6559);
+        points
+            .iter()
+            .map(|&(seconds, east)| {
+                let position = origin.destination(east / 1000.0, 90.0);
+                Location {
+                    latitude: position.latitude,
+                    longitude: position.longitude,
+                    timestamp: seconds.to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_trips_split_on_gaps() {
+        let fixes = track(&[(0, 0.0), (60, 500.0), (120, 1000.0), (4000, 1000.0), (4060, 1500.0), (9000, 0.0)]);
+        let refs: Vec<&Location> = fixes.iter().collect();
+        assert_eq!(trips(&refs, 600, 200.0, 1800), vec![0..3, 3..5, 5..6]);
+        assert_eq!(trips(&refs, 10_000, 200.0, 10_000), vec![0..6]);
+        // Sem o corte por intervalo, a hora parada em 1000 m vira um ponto de parada
+        assert_eq!(trips(&refs, 10_000, 200.0, 1800), vec![0..3, 3..6]);
+        assert!(trips(&[], 600, 200.0, 1800).is_empty());
+
+        let trip = segment_json(&refs[0..3], DistanceModel::Spherical);
+        let trip = trip.as_object().unwrap();
+        assert_eq!(trip["duration_s"].as_f64(), Some(120.0));
+        assert!((trip["distance_m"].as_f64().unwrap() - 1000.0).abs() < 0.5);
+        assert_eq!(trip["end"].as_str(), Some("120"));
+    }
+
+    #[test]
+    fn test_trips_split_on_reporting_stop() {
+        // Dirige 1,5 km, fica 40 min estacionado enviando fixes a cada minuto, e segue
+        let mut points: Vec<(u64, f64)> = (0..4).map(|k| (k * 60, k as f64 * 500.0)).collect();
+        points.extend((0..41).map(|k| (240 + k * 60, 2000.0 + (k % 2) as f64 * 10.0)));
+        points.extend((0..3).map(|k| (2700 + k * 60, 2500.0 + k as f64 * 500.0)));
+        let fixes = track(&points);
+        let refs: Vec<&Location> = fixes.iter().collect();
+
+        assert_eq!(stay_points(&refs, 200.0, 1800), vec![4..45]);
+        assert_eq!(trips(&refs, 600, 200.0, 1800), vec![0..5, 44..48]);
+        // Parada curta demais não corta a viagem
+        assert_eq!(trips(&refs, 600, 200.0, 3600), vec![0..48]);
+    }
+
+    #[test]
+    fn test_stay_points() {
+        // Caminha, fica 40 min a menos de 50 m, caminha de novo e termina parado 30 min
+        let mut points = vec![(0, 0.0), (300, 400.0)];
+        points.extend((0..9).map(|k| (600 + k * 300, 1000.0 + (k % 3) as f64 * 20.0)));
+        points.extend([(3300, 1600.0), (3600, 2200.0), (3900, 2210.0), (5400, 2205.0)]);
+        let fixes = track(&points);
+        let refs: Vec<&Location> = fixes.iter().collect();
+
+        let stays = stay_points(&refs, 100.0, 1800);
+        assert_eq!(stays, vec![2..11, 12..15]);
+        let center = centroid(&refs[stays[0].clone()]);
+        let anchor = coordinate(refs[2]);
+        assert!(anchor.distance_to(&center) * 1000.0 < 30.0);
+
+        assert_eq!(stay_points(&refs, 100.0, 3600), vec![]);
+    }
+
+    #[test]
+    fn test_centroid_across_antimeridian() {
+        let fixes = [179.9, -179.9].map(|longitude| Location { latitude: 10.0, longitude, ..Default::default() });
+        let center = centroid(&fixes.iter().collect::<Vec<_>>());
+        assert!((center.latitude - 10.0).abs() < 1e-3);
+        assert!((center.longitude.abs() - 180.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,110 @@
+// Configuração do servidor via variáveis de ambiente
+// Todos os valores têm padrão; nada é obrigatório
+
+use crate::quality::{Mode, QualityPolicy};
+use crate::retention::{Limits, RetentionPolicy};
+use crate::stats::DistanceModel;
+use crate::storage::FsyncPolicy;
+use crate::webhook::RetryPolicy;
+use avila_geo::kalman::DEFAULT_PROCESS_NOISE;
+use std::env;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Snapshot file; the WAL lives next to it with a `.wal` extension
+    pub history_file: String,
+    pub fsync: FsyncPolicy,
+    /// Compact once this many records are in the WAL
+    pub compact_every: u64,
+    /// ...or at least this often when the WAL is non-empty
+    pub compact_interval: Duration,
+    pub retention: RetentionPolicy,
+    /// How often the background pruning task runs
+    pub retention_interval: Duration,
+    /// Geofence definitions and their event log
+    pub geofence_file: String,
+    /// Webhook subscriptions, delivery queue and dead letters
+    pub webhook_file: String,
+    pub webhook_retry: RetryPolicy,
+    /// Battery percentage below which a `battery.low` event fires
+    pub low_battery: f64,
+    /// Address of the raw NMEA 0183 TCP listener; `None` disables it
+    pub nmea_addr: Option<String>,
+    /// Default Earth model for distance statistics
+    pub distance_model: DistanceModel,
+    /// Kalman process noise (m²/s³); `None` disables smoothing
+    pub kalman_noise: Option<f64>,
+    /// Ingestion quality filter; off unless `GPS_QUALITY` asks for it
+    pub quality: QualityPolicy,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        let fsync_interval = Duration::from_millis(env_parse("GPS_FSYNC_INTERVAL_MS", 1000));
+        let fsync = env::var("GPS_FSYNC")
+            .ok()
+            .and_then(|v| FsyncPolicy::parse(&v, fsync_interval))
+            .unwrap_or(FsyncPolicy::Interval(fsync_interval));
+
+        Config {
+            history_file: env::var("GPS_HISTORY_FILE")
+                .unwrap_or_else(|_| "location_history.json".to_string()),
+            fsync,
+            compact_every: env_parse("GPS_COMPACT_EVERY", 1000),
+            compact_interval: Duration::from_secs(env_parse("GPS_COMPACT_INTERVAL_SECS", 300)),
+            retention: RetentionPolicy {
+                global: env_limits("GPS_RETENTION", Limits { max_entries: Some(1000), max_age: None }),
+                per_device: env_limits("GPS_RETENTION_PER_DEVICE", Limits::default()),
+                overrides: env::var("GPS_RETENTION_OVERRIDES")
+                    .ok()
+                    .and_then(|v| RetentionPolicy::parse_overrides(&v))
+                    .unwrap_or_default(),
+            },
+            retention_interval: Duration::from_secs(env_parse("GPS_RETENTION_INTERVAL_SECS", 60)),
+            geofence_file: env::var("GPS_GEOFENCE_FILE").unwrap_or_else(|_| "geofences.json".to_string()),
+            webhook_file: env::var("GPS_WEBHOOK_FILE").unwrap_or_else(|_| "webhooks.json".to_string()),
+            webhook_retry: RetryPolicy {
+                max_attempts: env_parse("GPS_WEBHOOK_MAX_ATTEMPTS", 8u32).max(1),
+                base_backoff_secs: env_parse("GPS_WEBHOOK_BACKOFF_SECS", 5),
+            },
+            low_battery: env_parse("GPS_LOW_BATTERY", 15.0),
+            // 10110 é a porta registrada para NMEA-0183 sobre TCP
+            nmea_addr: match env::var("GPS_NMEA_ADDR") {
+                Ok(v) if v.trim().is_empty() || v.trim() == "off" => None,
+                Ok(v) => Some(v.trim().to_string()),
+                Err(_) => Some("0.0.0.0:10110".to_string()),
+            },
+            distance_model: env::var("GPS_DISTANCE_MODEL")
+                .ok()
+                .and_then(|v| DistanceModel::parse(&v))
+                .unwrap_or(DistanceModel::Ellipsoidal),
+            kalman_noise: match env::var("GPS_KALMAN").map(|v| v.trim().to_lowercase()) {
+                Ok(v) if v == "on" || v == "true" || v == "1" => Some(
+                    env_parse("GPS_KALMAN_PROCESS_NOISE", DEFAULT_PROCESS_NOISE).max(f64::MIN_POSITIVE),
+                ),
+                _ => None,
+            },
+            quality: QualityPolicy {
+                mode: env::var("GPS_QUALITY").ok().and_then(|v| Mode::parse(&v)).unwrap_or(Mode::Off),
+                // 0 desativa a verificação
+                max_speed: Some(env_parse("GPS_MAX_SPEED", 100.0)).filter(|v| *v > 0.0),
+                max_accuracy: Some(env_parse("GPS_MAX_ACCURACY", 500.0)).filter(|v| *v > 0.0),
+            },
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name)
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn env_limits(name: &str, default: Limits) -> Limits {
+    env::var(name)
+        .ok()
+        .and_then(|v| Limits::parse(&v))
+        .unwrap_or(default)
+}
@@ -0,0 +1,136 @@
+// Registro de dispositivos - última localização conhecida por device_name
+// Cada device_name é uma chave única na frota
+
+use crate::Location;
+use avila_json::JsonValue;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub id: String,
+    pub location: Location,
+    pub first_seen: String,
+    pub last_seen: String,
+    pub fix_count: u64,
+}
+
+impl Device {
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::String(self.id.clone()));
+        map.insert("first_seen".to_string(), JsonValue::String(self.first_seen.clone()));
+        map.insert("last_seen".to_string(), JsonValue::String(self.last_seen.clone()));
+        map.insert("fix_count".to_string(), JsonValue::Number(self.fix_count as f64));
+        map.insert("location".to_string(), self.location.to_json());
+        JsonValue::Object(map)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DeviceRegistry {
+    devices: HashMap<String, Device>,
+    latest: Option<String>,
+}
+
+impl DeviceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a fix for its device. Older fixes (e.g. imported tracks) count
+    /// towards the device but don't replace a newer latest position.
+    pub fn record(&mut self, location: &Location) {
+        let id = location.device_name.clone();
+
+        match self.devices.get_mut(&id) {
+            Some(device) => {
+                device.fix_count += 1;
+                if location.epoch_secs() < device.location.epoch_secs() {
+                    if location.epoch_secs() < epoch(&device.first_seen) {
+                        device.first_seen = location.timestamp.clone();
+                    }
+                    return;
+                }
+                device.location = location.clone();
+                device.last_seen = location.timestamp.clone();
+            }
+            None => {
+                self.devices.insert(id.clone(), Device {
+                    id: id.clone(),
+                    location: location.clone(),
+                    first_seen: location.timestamp.clone(),
+                    last_seen: location.timestamp.clone(),
+                    fix_count: 1,
+                });
+            }
+        }
+
+        self.latest = Some(id);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Device> {
+        self.devices.get(id)
+    }
+
+    /// Most recently updated device, regardless of which one it is
+    pub fn latest(&self) -> Option<&Device> {
+        self.latest.as_ref().and_then(|id| self.devices.get(id))
+    }
+
+    /// All known devices, sorted by id
+    pub fn list(&self) -> Vec<&Device> {
+        let mut devices: Vec<&Device> = self.devices.values().collect();
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+        devices
+    }
+}
+
+fn epoch(timestamp: &str) -> Option<u64> {
+    timestamp.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(device: &str, timestamp: &str) -> Location {
+        Location {
+            id: 0,
+            latitude: 25.1972,
+            longitude: 55.2744,
+            accuracy: None,
+            timestamp: timestamp.to_string(),
+            device_name: device.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_devices_are_keyed_by_name() {
+        let mut registry = DeviceRegistry::new();
+        registry.record(&fix("phone-a", "100"));
+        registry.record(&fix("phone-b", "110"));
+        registry.record(&fix("phone-a", "120"));
+
+        assert_eq!(registry.list().len(), 2);
+
+        let a = registry.get("phone-a").unwrap();
+        assert_eq!(a.fix_count, 2);
+        assert_eq!(a.first_seen, "100");
+        assert_eq!(a.last_seen, "120");
+
+        assert_eq!(registry.latest().unwrap().id, "phone-a");
+    }
+
+    #[test]
+    fn test_older_fix_does_not_replace_latest() {
+        let mut registry = DeviceRegistry::new();
+        registry.record(&fix("phone-a", "200"));
+        registry.record(&fix("phone-a", "50"));
+
+        let a = registry.get("phone-a").unwrap();
+        assert_eq!(a.fix_count, 2);
+        assert_eq!(a.location.timestamp, "200");
+        assert_eq!(a.first_seen, "50");
+    }
+}
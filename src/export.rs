@@ -0,0 +1,318 @@
+// Exportação do histórico: GPX 1.1, KML, GeoJSON e CSV
+// Gerado nativamente, sem crates externas
+
+use crate::time::format_rfc3339;
+use crate::Location;
+use avila_geo::{polyline, Coordinate};
+use avila_json::JsonValue;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Gpx,
+    Kml,
+    GeoJson,
+    Csv,
+}
+
+impl Format {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "gpx" => Some(Format::Gpx),
+            "kml" => Some(Format::Kml),
+            "geojson" => Some(Format::GeoJson),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Gpx => "application/gpx+xml",
+            Format::Kml => "application/vnd.google-earth.kml+xml",
+            Format::GeoJson => "application/geo+json",
+            Format::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Gpx => "gpx",
+            Format::Kml => "kml",
+            Format::GeoJson => "geojson",
+            Format::Csv => "csv",
+        }
+    }
+}
+
+pub fn export(format: Format, locations: &[&Location]) -> String {
+    match format {
+        Format::Gpx => to_gpx(locations),
+        Format::Kml => to_kml(locations),
+        Format::GeoJson => to_geojson(locations),
+        Format::Csv => to_csv(locations),
+    }
+}
+
+/// Group by device, keeping first-seen order of devices and the order of fixes
+fn by_device<'a>(locations: &[&'a Location]) -> Vec<(&'a str, Vec<&'a Location>)> {
+    let mut groups: Vec<(&str, Vec<&Location>)> = Vec::new();
+    let mut index: HashMap<&str, usize> = HashMap::new();
+
+    for &location in locations {
+        let device = location.device_name.as_str();
+        let i = *index.entry(device).or_insert_with(|| {
+            groups.push((device, Vec::new()));
+            groups.len() - 1
+        });
+        groups[i].1.push(location);
+    }
+    groups
+}
+
+/// One encoded polyline per device track, with a parallel array of timestamps
+pub fn polyline_tracks(locations: &[&Location], precision: u32) -> Vec<JsonValue> {
+    by_device(locations)
+        .into_iter()
+        .map(|(device, fixes)| {
+            let coordinates: Vec<Coordinate> = fixes
+                .iter()
+                .map(|l| Coordinate::new(l.latitude, l.longitude))
+                .collect();
+            let timestamps = fixes.iter().map(|l| JsonValue::String(l.timestamp.clone())).collect();
+
+            let mut map = HashMap::new();
+            map.insert("device".to_string(), JsonValue::String(device.to_string()));
+            map.insert("polyline".to_string(), JsonValue::String(polyline::encode(&coordinates, precision)));
+            map.insert("timestamps".to_string(), JsonValue::Array(timestamps));
+            JsonValue::Object(map)
+        })
+        .collect()
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// GPX 1.1: one `<trk>` per device, each with a single `<trkseg>`
+fn to_gpx(locations: &[&Location]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <gpx version=\"1.1\" creator=\"Device Location Tracker\" \
+         xmlns=\"http://www.topografix.com/GPX/1/1\">\n",
+    );
+
+    for (device, fixes) in by_device(locations) {
+        out.push_str(&format!("  <trk>\n    <name>{}</name>\n    <trkseg>\n", xml_escape(device)));
+        for location in fixes {
+            out.push_str(&format!(
+                "      <trkpt lat=\"{}\" lon=\"{}\">",
+                location.latitude, location.longitude
+            ));
+            if let Some(ts) = location.epoch_secs() {
+                out.push_str(&format!("<time>{}</time>", format_rfc3339(ts)));
+            }
+            out.push_str("</trkpt>\n");
+        }
+        out.push_str("    </trkseg>\n  </trk>\n");
+    }
+
+    out.push_str("</gpx>\n");
+    out
+}
+
+/// KML 2.2: a folder per device with the track as a `LineString` and one
+/// time-stamped `Placemark` per fix
+fn to_kml(locations: &[&Location]) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <kml xmlns=\"http://www.opengis.net/kml/2.2\">\n<Document>\n\
+         <name>Device Location Tracker</name>\n",
+    );
+
+    for (device, fixes) in by_device(locations) {
+        let name = xml_escape(device);
+        out.push_str(&format!("<Folder>\n<name>{}</name>\n", name));
+
+        let coordinates: Vec<String> = fixes
+            .iter()
+            .map(|l| format!("{},{}", l.longitude, l.latitude))
+            .collect();
+        out.push_str(&format!(
+            "<Placemark>\n<name>{}</name>\n<LineString><tessellate>1</tessellate>\
+             <coordinates>{}</coordinates></LineString>\n</Placemark>\n",
+            name,
+            coordinates.join(" ")
+        ));
+
+        for location in fixes {
+            out.push_str("<Placemark>");
+            if let Some(ts) = location.epoch_secs() {
+                out.push_str(&format!("<TimeStamp><when>{}</when></TimeStamp>", format_rfc3339(ts)));
+            }
+            out.push_str(&format!(
+                "<Point><coordinates>{},{}</coordinates></Point></Placemark>\n",
+                location.longitude, location.latitude
+            ));
+        }
+        out.push_str("</Folder>\n");
+    }
+
+    out.push_str("</Document>\n</kml>\n");
+    out
+}
+
+/// GeoJSON `FeatureCollection` of `Point` features
+fn to_geojson(locations: &[&Location]) -> String {
+    let features: Vec<JsonValue> = locations
+        .iter()
+        .map(|location| {
+            let mut geometry = HashMap::new();
+            geometry.insert("type".to_string(), JsonValue::String("Point".to_string()));
+            geometry.insert(
+                "coordinates".to_string(),
+                JsonValue::Array(vec![
+                    JsonValue::Number(location.longitude),
+                    JsonValue::Number(location.latitude),
+                ]),
+            );
+
+            let mut properties = match location.to_json() {
+                JsonValue::Object(map) => map,
+                _ => HashMap::new(),
+            };
+            properties.remove("latitude");
+            properties.remove("longitude");
+            if let Some(ts) = location.epoch_secs() {
+                properties.insert("time".to_string(), JsonValue::String(format_rfc3339(ts)));
+            }
+
+            let mut feature = HashMap::new();
+            feature.insert("type".to_string(), JsonValue::String("Feature".to_string()));
+            feature.insert("geometry".to_string(), JsonValue::Object(geometry));
+            feature.insert("properties".to_string(), JsonValue::Object(properties));
+            JsonValue::Object(feature)
+        })
+        .collect();
+
+    let mut map = HashMap::new();
+    map.insert("type".to_string(), JsonValue::String("FeatureCollection".to_string()));
+    map.insert("features".to_string(), JsonValue::Array(features));
+    JsonValue::Object(map).to_string()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn to_csv(locations: &[&Location]) -> String {
+    let mut out = String::from("id,device_name,timestamp,time,latitude,longitude,accuracy\n");
+    for location in locations {
+        out.push_str(&format!(
+            "{},{},{},{},{},{},{}\n",
+            location.id,
+            csv_field(&location.device_name),
+            csv_field(&location.timestamp),
+            location.epoch_secs().map(format_rfc3339).unwrap_or_default(),
+            location.latitude,
+            location.longitude,
+            location.accuracy.map(|a| a.to_string()).unwrap_or_default()
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(id: u64, device: &str, lat: f64, lon: f64) -> Location {
+        Location {
+            id,
+            latitude: lat,
+            longitude: lon,
+            accuracy: Some(5.0),
+            timestamp: (1_700_000_000 + id).to_string(),
+            device_name: device.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn sample() -> Vec<Location> {
+        vec![
+            fix(1, "a & b", 25.1972, 55.2744),
+            fix(2, "truck", -23.55, -46.63),
+            fix(3, "a & b", 25.0805, 55.1399),
+        ]
+    }
+
+    #[test]
+    fn test_gpx_groups_by_device() {
+        let fixes = sample();
+        let refs: Vec<&Location> = fixes.iter().collect();
+        let gpx = export(Format::Gpx, &refs);
+
+        assert_eq!(gpx.matches("<trkseg>").count(), 2);
+        assert!(gpx.contains("<name>a &amp; b</name>"));
+        assert!(gpx.contains("<trkpt lat=\"25.1972\" lon=\"55.2744\"><time>2023-11-14T22:13:21Z</time></trkpt>"));
+    }
+
+    #[test]
+    fn test_kml_uses_lon_lat_order() {
+        let fixes = sample();
+        let refs: Vec<&Location> = fixes.iter().collect();
+        let kml = export(Format::Kml, &refs);
+
+        assert!(kml.contains("<coordinates>55.2744,25.1972 55.1399,25.0805</coordinates>"));
+        assert_eq!(kml.matches("<Point>").count(), 3);
+    }
+
+    #[test]
+    fn test_geojson_is_valid_json() {
+        let fixes = sample();
+        let refs: Vec<&Location> = fixes.iter().collect();
+        let json = avila_json::parse(&export(Format::GeoJson, &refs)).unwrap();
+
+        let features = json.as_object().unwrap()["features"].as_array().unwrap();
+        assert_eq!(features.len(), 3);
+        let coords = features[1].as_object().unwrap()["geometry"].as_object().unwrap()["coordinates"]
+            .as_array()
+            .unwrap();
+        assert_eq!(coords[0].as_f64(), Some(-46.63));
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut fixes = sample();
+        fixes[1].device_name = "say \"hi\", ok".to_string();
+        let refs: Vec<&Location> = fixes.iter().collect();
+        let csv = export(Format::Csv, &refs);
+
+        assert!(csv.starts_with("id,device_name,timestamp,time,latitude,longitude,accuracy\n"));
+        assert!(csv.contains("2,\"say \"\"hi\"\", ok\",1700000002,2023-11-14T22:13:22Z,-23.55,-46.63,5\n"));
+    }
+
+    #[test]
+    fn test_polyline_tracks_per_device() {
+        let fixes = sample();
+        let refs: Vec<&Location> = fixes.iter().collect();
+        let tracks = polyline_tracks(&refs, 5);
+
+        assert_eq!(tracks.len(), 2);
+        let track = tracks[0].as_object().unwrap();
+        assert_eq!(track["device"].as_str(), Some("a & b"));
+        let timestamps = track["timestamps"].as_array().unwrap();
+        assert_eq!(timestamps[1].as_str(), Some("1700000003"));
+        let decoded = polyline::decode(track["polyline"].as_str().unwrap(), 5).unwrap();
+        assert_eq!(decoded.len(), timestamps.len());
+        assert!((decoded[1].longitude - 55.1399).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,653 @@
+// Geofences (círculos e polígonos) e log de eventos enter/exit/dwell
+// Definições em JSON (reescritas só quando mudam); eventos num log JSON Lines append-only
+
+use crate::storage::{move_aside, write_atomic};
+use crate::Location;
+use avila_geo::{Coordinate, Polygon};
+use avila_json::JsonValue;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Oldest events are dropped beyond this
+const MAX_EVENTS: usize = 10_000;
+
+#[derive(Debug, Clone)]
+pub enum Shape {
+    Circle { center: Coordinate, radius_m: f64 },
+    /// May have holes and cross the antimeridian
+    Polygon(Polygon),
+}
+
+impl Shape {
+    pub fn contains(&self, point: &Coordinate) -> bool {
+        match self {
+            Shape::Circle { center, radius_m } => center.distance_to(point) * 1000.0 <= *radius_m,
+            Shape::Polygon(polygon) => polygon.contains(point),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Geofence {
+    pub id: u64,
+    pub name: String,
+    pub shape: Shape,
+    /// Emit a `dwell` event once a device has stayed inside this long
+    pub dwell_secs: Option<u64>,
+}
+
+fn coordinate_json(c: &Coordinate) -> JsonValue {
+    let mut map = HashMap::new();
+    map.insert("latitude".to_string(), JsonValue::Number(c.latitude));
+    map.insert("longitude".to_string(), JsonValue::Number(c.longitude));
+    JsonValue::Object(map)
+}
+
+fn coordinate_from_json(json: &JsonValue, field: &str) -> Result<Coordinate, String> {
+    let map = json.as_object().ok_or(format!("'{}' must be an object", field))?;
+    let number = |name: &str| map.get(name).and_then(|v| v.as_f64());
+    match (number("latitude"), number("longitude")) {
+        // Mesma validação da ingestão: NaN, infinito e fora da faixa
+        (Some(lat), Some(lon)) => Coordinate::try_new(lat, lon).map_err(|e| format!("'{}': {}", field, e)),
+        _ => Err(format!("'{}' needs a numeric 'latitude' and 'longitude'", field)),
+    }
+}
+
+impl Geofence {
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::Number(self.id as f64));
+        map.insert("name".to_string(), JsonValue::String(self.name.clone()));
+        match &self.shape {
+            Shape::Circle { center, radius_m } => {
+                map.insert("type".to_string(), JsonValue::String("circle".to_string()));
+                map.insert("center".to_string(), coordinate_json(center));
+                map.insert("radius".to_string(), JsonValue::Number(*radius_m));
+            }
+            Shape::Polygon(polygon) => {
+                let ring_json = |ring: &[Coordinate]| JsonValue::Array(ring.iter().map(coordinate_json).collect());
+                map.insert("type".to_string(), JsonValue::String("polygon".to_string()));
+                map.insert("vertices".to_string(), ring_json(&polygon.exterior));
+                if !polygon.holes.is_empty() {
+                    map.insert(
+                        "holes".to_string(),
+                        JsonValue::Array(polygon.holes.iter().map(|h| ring_json(h)).collect()),
+                    );
+                }
+            }
+        }
+        if let Some(dwell) = self.dwell_secs {
+            map.insert("dwell".to_string(), JsonValue::Number(dwell as f64));
+        }
+        JsonValue::Object(map)
+    }
+
+    /// Parse a definition; `id` is taken from the JSON if present, else 0.
+    /// Errors name the offending field.
+    pub fn from_json(json: &JsonValue) -> Result<Self, String> {
+        let map = json.as_object().ok_or("expected a JSON object")?;
+        let name = map
+            .get("name")
+            .and_then(|v| v.as_str())
+            .filter(|n| !n.trim().is_empty())
+            .ok_or("missing 'name'")?
+            .to_string();
+
+        let shape = match map.get("type").and_then(|v| v.as_str()) {
+            Some("circle") => {
+                let center = coordinate_from_json(map.get("center").ok_or("missing 'center'")?, "center")?;
+                let radius_m = map
+                    .get("radius")
+                    .and_then(|v| v.as_f64())
+                    .filter(|r| *r > 0.0)
+                    .ok_or("'radius' must be a positive number of meters")?;
+                Shape::Circle { center, radius_m }
+            }
+            Some("polygon") => {
+                let ring = |json: &JsonValue, field: &str| -> Result<Vec<Coordinate>, String> {
+                    json.as_array()
+                        .ok_or(format!("'{}' must be an array of coordinates", field))?
+                        .iter()
+                        .map(|v| coordinate_from_json(v, field))
+                        .collect()
+                };
+                let exterior = ring(map.get("vertices").ok_or("missing 'vertices'")?, "vertices")?;
+                let holes = match map.get("holes").and_then(|v| v.as_array()) {
+                    Some(holes) => holes.iter().map(|h| ring(h, "holes")).collect::<Result<Vec<_>, _>>()?,
+                    None => Vec::new(),
+                };
+
+                let polygon = Polygon::new(exterior, holes);
+                if polygon.exterior.len() < 3 {
+                    return Err("'vertices' needs at least 3 points".to_string());
+                }
+                if !polygon.is_valid() {
+                    return Err("each of 'holes' needs at least 3 points".to_string());
+                }
+                Shape::Polygon(polygon)
+            }
+            _ => return Err("'type' must be 'circle' or 'polygon'".to_string()),
+        };
+
+        let dwell_secs = match map.get("dwell") {
+            None | Some(JsonValue::Null) => None,
+            Some(v) => Some(v.as_f64().filter(|d| *d >= 0.0).ok_or("'dwell' must be seconds")? as u64),
+        };
+
+        Ok(Geofence {
+            id: map.get("id").and_then(|v| v.as_f64()).unwrap_or(0.0) as u64,
+            name,
+            shape,
+            dwell_secs,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    Enter,
+    Exit,
+    Dwell,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Enter => "enter",
+            EventKind::Exit => "exit",
+            EventKind::Dwell => "dwell",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "enter" => Some(EventKind::Enter),
+            "exit" => Some(EventKind::Exit),
+            "dwell" => Some(EventKind::Dwell),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GeofenceEvent {
+    pub id: u64,
+    pub kind: EventKind,
+    pub geofence_id: u64,
+    pub geofence: String,
+    pub device: String,
+    /// History id of the fix that triggered the event
+    pub location_id: u64,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub timestamp: u64,
+}
+
+impl GeofenceEvent {
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::Number(self.id as f64));
+        map.insert("type".to_string(), JsonValue::String(self.kind.as_str().to_string()));
+        map.insert("geofence_id".to_string(), JsonValue::Number(self.geofence_id as f64));
+        map.insert("geofence".to_string(), JsonValue::String(self.geofence.clone()));
+        map.insert("device".to_string(), JsonValue::String(self.device.clone()));
+        map.insert("location_id".to_string(), JsonValue::Number(self.location_id as f64));
+        map.insert("latitude".to_string(), JsonValue::Number(self.latitude));
+        map.insert("longitude".to_string(), JsonValue::Number(self.longitude));
+        map.insert("timestamp".to_string(), JsonValue::Number(self.timestamp as f64));
+        JsonValue::Object(map)
+    }
+
+    fn from_json(json: &JsonValue) -> Option<Self> {
+        let map = json.as_object()?;
+        let number = |name: &str| map.get(name).and_then(|v| v.as_f64());
+        let text = |name: &str| map.get(name).and_then(|v| v.as_str()).map(str::to_string);
+        Some(GeofenceEvent {
+            id: number("id")? as u64,
+            kind: EventKind::parse(map.get("type")?.as_str()?)?,
+            geofence_id: number("geofence_id")? as u64,
+            geofence: text("geofence")?,
+            device: text("device")?,
+            location_id: number("location_id").unwrap_or(0.0) as u64,
+            latitude: number("latitude")?,
+            longitude: number("longitude")?,
+            timestamp: number("timestamp")? as u64,
+        })
+    }
+}
+
+/// Filters for `GET /api/events`
+#[derive(Debug, Default)]
+pub struct EventQuery {
+    pub device: Option<String>,
+    pub geofence_id: Option<u64>,
+    pub kind: Option<EventKind>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    /// Most recent N matches
+    pub limit: Option<usize>,
+}
+
+impl EventQuery {
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        fn number<T: std::str::FromStr>(params: &HashMap<String, String>, name: &str) -> Result<Option<T>, String> {
+            match params.get(name) {
+                Some(v) => v.trim().parse().map(Some).map_err(|_| format!("invalid '{}'", name)),
+                None => Ok(None),
+            }
+        }
+
+        Ok(EventQuery {
+            device: params.get("device").cloned(),
+            geofence_id: number(params, "geofence")?,
+            kind: match params.get("type") {
+                Some(v) => Some(EventKind::parse(v).ok_or("invalid 'type'")?),
+                None => None,
+            },
+            from: number(params, "from")?,
+            to: number(params, "to")?,
+            limit: number(params, "limit")?,
+        })
+    }
+
+    fn matches(&self, event: &GeofenceEvent) -> bool {
+        self.device.as_ref().is_none_or(|d| &event.device == d)
+            && self.geofence_id.is_none_or(|id| event.geofence_id == id)
+            && self.kind.is_none_or(|k| event.kind == k)
+            && self.from.is_none_or(|from| event.timestamp >= from)
+            && self.to.is_none_or(|to| event.timestamp <= to)
+    }
+}
+
+/// Device inside a fence since `since`
+#[derive(Debug, Clone, Copy)]
+struct Presence {
+    since: u64,
+    dwell_reported: bool,
+}
+
+pub struct GeofenceStore {
+    path: PathBuf,
+    fences: BTreeMap<u64, Geofence>,
+    next_id: u64,
+    events: VecDeque<GeofenceEvent>,
+    next_event_id: u64,
+    presence: HashMap<(String, u64), Presence>,
+    /// `<name>.events.jsonl` next to the definitions
+    events_path: PathBuf,
+    events_log: File,
+    /// Lines in the log; rewritten with only the kept events once it doubles
+    logged: usize,
+}
+
+impl GeofenceStore {
+    /// Load definitions from `path` and events from the log next to it, or
+    /// start empty. An unreadable definitions file is moved aside, not fatal.
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let events_path = path.with_extension("events.jsonl");
+        let mut store = GeofenceStore {
+            path: path.to_path_buf(),
+            fences: BTreeMap::new(),
+            next_id: 1,
+            events: VecDeque::new(),
+            next_event_id: 1,
+            presence: HashMap::new(),
+            events_log: OpenOptions::new().create(true).append(true).open(&events_path)?,
+            events_path,
+            logged: 0,
+        };
+
+        // Versões antigas guardavam os eventos junto com as definições
+        let legacy_events = store.load_definitions()?;
+        store.load_events()?;
+        if !legacy_events.is_empty() && store.events.is_empty() {
+            store.events = legacy_events;
+            store.rewrite_events()?;
+        }
+        for event in &store.events {
+            store.next_event_id = store.next_event_id.max(event.id + 1);
+        }
+        Ok(store)
+    }
+
+    fn load_definitions(&mut self) -> io::Result<VecDeque<GeofenceEvent>> {
+        let data = match fs::read_to_string(&self.path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(VecDeque::new()),
+            Err(e) => return Err(e),
+        };
+        let json = avila_json::parse(&data).ok();
+        let Some(map) = json.as_ref().and_then(|json| json.as_object()) else {
+            let aside = move_aside(&self.path)?;
+            eprintln!("⚠️  {} is not valid JSON; moved to {}, starting without geofences", self.path.display(), aside.display());
+            return Ok(VecDeque::new());
+        };
+
+        for fence in map.get("geofences").and_then(|v| v.as_array()).into_iter().flatten() {
+            if let Ok(fence) = Geofence::from_json(fence) {
+                self.next_id = self.next_id.max(fence.id + 1);
+                self.fences.insert(fence.id, fence);
+            }
+        }
+        if let Some(next) = map.get("next_id").and_then(|v| v.as_f64()) {
+            self.next_id = self.next_id.max(next as u64);
+        }
+        Ok(map
+            .get("events")
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(GeofenceEvent::from_json)
+            .collect())
+    }
+
+    /// Replay the event log. A last line cut short by a crash is truncated
+    /// away so the next append starts on a fresh line.
+    fn load_events(&mut self) -> io::Result<()> {
+        let data = fs::read(&self.events_path)?;
+        let complete = data.iter().rposition(|&b| b == b'\n').map_or(0, |pos| pos + 1);
+        for line in String::from_utf8_lossy(&data[..complete]).lines() {
+            self.logged += 1;
+            match avila_json::parse(line).ok().as_ref().and_then(GeofenceEvent::from_json) {
+                Some(event) => self.events.push_back(event),
+                None => eprintln!("⚠️  Skipping corrupt geofence event in {}", self.events_path.display()),
+            }
+        }
+        if complete < data.len() {
+            eprintln!("⚠️  Discarding truncated event at end of {}", self.events_path.display());
+            self.events_log.set_len(complete as u64)?;
+        }
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+        Ok(())
+    }
+
+    /// Rewrite the definitions file (events live in their own log)
+    fn save(&self) -> io::Result<()> {
+        let mut map = HashMap::new();
+        map.insert("next_id".to_string(), JsonValue::Number(self.next_id as f64));
+        map.insert(
+            "geofences".to_string(),
+            JsonValue::Array(self.fences.values().map(Geofence::to_json).collect()),
+        );
+        write_atomic(&self.path, JsonValue::Object(map).to_string().as_bytes())
+    }
+
+    fn append_events(&mut self, events: &[GeofenceEvent]) -> io::Result<()> {
+        if self.logged + events.len() > 2 * MAX_EVENTS {
+            return self.rewrite_events();
+        }
+        let mut lines = String::new();
+        for event in events {
+            lines.push_str(&event.to_json().to_string());
+            lines.push('\n');
+        }
+        self.events_log.write_all(lines.as_bytes())?;
+        self.logged += events.len();
+        Ok(())
+    }
+
+    /// Replace the log with the events still kept in memory
+    fn rewrite_events(&mut self) -> io::Result<()> {
+        let mut lines = String::new();
+        for event in &self.events {
+            lines.push_str(&event.to_json().to_string());
+            lines.push('\n');
+        }
+        write_atomic(&self.events_path, lines.as_bytes())?;
+        self.events_log = OpenOptions::new().append(true).open(&self.events_path)?;
+        self.logged = self.events.len();
+        Ok(())
+    }
+
+    pub fn list(&self) -> Vec<&Geofence> {
+        self.fences.values().collect()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&Geofence> {
+        self.fences.get(&id)
+    }
+
+    pub fn create(&mut self, mut fence: Geofence) -> io::Result<Geofence> {
+        fence.id = self.next_id;
+        self.next_id += 1;
+        self.fences.insert(fence.id, fence.clone());
+        self.save()?;
+        Ok(fence)
+    }
+
+    /// Replace an existing fence; presence inside it is re-evaluated from scratch
+    pub fn update(&mut self, id: u64, mut fence: Geofence) -> io::Result<Option<Geofence>> {
+        if !self.fences.contains_key(&id) {
+            return Ok(None);
+        }
+        fence.id = id;
+        self.fences.insert(id, fence.clone());
+        self.presence.retain(|(_, fence_id), _| *fence_id != id);
+        self.save()?;
+        Ok(Some(fence))
+    }
+
+    pub fn delete(&mut self, id: u64) -> io::Result<bool> {
+        if self.fences.remove(&id).is_none() {
+            return Ok(false);
+        }
+        self.presence.retain(|(_, fence_id), _| *fence_id != id);
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Compare a device's previous and new fix against every fence and log
+    /// the resulting transitions. `dwell` fires on the first fix at least
+    /// `dwell` seconds after entering.
+    pub fn evaluate(&mut self, previous: Option<&Location>, current: &Location) -> Vec<GeofenceEvent> {
+        let now = current.epoch_secs().unwrap_or(0);
+        let point = Coordinate::new(current.latitude, current.longitude);
+        let before = previous.map(|p| Coordinate::new(p.latitude, p.longitude));
+
+        let mut emitted = Vec::new();
+        for fence in self.fences.values() {
+            let was_inside = before.as_ref().is_some_and(|b| fence.shape.contains(b));
+            let is_inside = fence.shape.contains(&point);
+            let key = (current.device_name.clone(), fence.id);
+
+            let kind = match (was_inside, is_inside) {
+                (false, true) => {
+                    self.presence.insert(key, Presence { since: now, dwell_reported: false });
+                    Some(EventKind::Enter)
+                }
+                (true, false) => {
+                    self.presence.remove(&key);
+                    Some(EventKind::Exit)
+                }
+                (true, true) => {
+                    // Sem estado (ex.: após reinício): conta a partir do fix anterior
+                    let since = previous.and_then(|p| p.epoch_secs()).unwrap_or(now);
+                    let presence = self.presence.entry(key).or_insert(Presence { since, dwell_reported: false });
+                    match fence.dwell_secs {
+                        Some(dwell) if !presence.dwell_reported && now.saturating_sub(presence.since) >= dwell => {
+                            presence.dwell_reported = true;
+                            Some(EventKind::Dwell)
+                        }
+                        _ => None,
+                    }
+                }
+                (false, false) => None,
+            };
+
+            if let Some(kind) = kind {
+                emitted.push(GeofenceEvent {
+                    id: 0,
+                    kind,
+                    geofence_id: fence.id,
+                    geofence: fence.name.clone(),
+                    device: current.device_name.clone(),
+                    location_id: current.id,
+                    latitude: current.latitude,
+                    longitude: current.longitude,
+                    timestamp: now,
+                });
+            }
+        }
+
+        if emitted.is_empty() {
+            return emitted;
+        }
+        for event in &mut emitted {
+            event.id = self.next_event_id;
+            self.next_event_id += 1;
+            self.events.push_back(event.clone());
+        }
+        while self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+        if let Err(e) = self.append_events(&emitted) {
+            eprintln!("❌ Failed to persist geofence events: {}", e);
+        }
+        emitted
+    }
+
+    pub fn events(&self, query: &EventQuery) -> Vec<&GeofenceEvent> {
+        let matching: Vec<&GeofenceEvent> = self.events.iter().filter(|e| query.matches(e)).collect();
+        let skip = query.limit.map_or(0, |limit| matching.len().saturating_sub(limit));
+        matching.into_iter().skip(skip).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gps-geofence-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("geofences.json")
+    }
+
+    fn fix(id: u64, lat: f64, lon: f64, ts: u64) -> Location {
+        Location {
+            id,
+            latitude: lat,
+            longitude: lon,
+            timestamp: ts.to_string(),
+            device_name: "car".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn circle(dwell: Option<u64>) -> Geofence {
+        let json = avila_json::parse(
+            r#"{"name":"Burj","type":"circle","center":{"latitude":25.1972,"longitude":55.2744},"radius":500}"#,
+        )
+        .unwrap();
+        Geofence { dwell_secs: dwell, ..Geofence::from_json(&json).unwrap() }
+    }
+
+    #[test]
+    fn test_shapes() {
+        let burj = Coordinate::new(25.1972, 55.2744);
+        assert!(circle(None).shape.contains(&Coordinate::new(25.2, 55.2744)));
+        assert!(!circle(None).shape.contains(&Coordinate::new(25.21, 55.2744)));
+
+        let square = Geofence::from_json(
+            &avila_json::parse(
+                r#"{"name":"sq","type":"polygon",
+                    "vertices":[{"latitude":25,"longitude":55},{"latitude":25,"longitude":55.5},
+                                {"latitude":25.5,"longitude":55.5},{"latitude":25.5,"longitude":55}],
+                    "holes":[[{"latitude":25.19,"longitude":55.27},{"latitude":25.19,"longitude":55.28},
+                              {"latitude":25.2,"longitude":55.28}]]}"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(square.shape.contains(&burj));
+        assert!(!square.shape.contains(&Coordinate::new(24.9, 55.2)));
+        assert!(!square.shape.contains(&Coordinate::new(25.195, 55.279)));
+        assert!(square.to_json().as_object().unwrap().contains_key("holes"));
+    }
+
+    #[test]
+    fn test_enter_dwell_exit() {
+        let mut store = GeofenceStore::open(&temp_path("events")).unwrap();
+        store.create(circle(Some(60))).unwrap();
+
+        let outside = fix(1, 25.3, 55.2744, 1000);
+        let inside = fix(2, 25.1972, 55.2744, 1010);
+        let still = fix(3, 25.1975, 55.2744, 1080);
+        let left = fix(4, 25.3, 55.2744, 1100);
+
+        assert!(store.evaluate(None, &outside).is_empty());
+        assert_eq!(store.evaluate(Some(&outside), &inside)[0].kind, EventKind::Enter);
+        assert_eq!(store.evaluate(Some(&inside), &still)[0].kind, EventKind::Dwell);
+        assert!(store.evaluate(Some(&still), &still).is_empty());
+        assert_eq!(store.evaluate(Some(&still), &left)[0].kind, EventKind::Exit);
+
+        let query = EventQuery { kind: Some(EventKind::Exit), ..Default::default() };
+        assert_eq!(store.events(&query).len(), 1);
+        assert_eq!(store.events(&EventQuery { limit: Some(2), ..Default::default() })[0].id, 2);
+    }
+
+    #[test]
+    fn test_persists_across_reopen() {
+        let path = temp_path("reopen");
+        let mut store = GeofenceStore::open(&path).unwrap();
+        let fence = store.create(circle(None)).unwrap();
+        store.evaluate(None, &fix(1, 25.1972, 55.2744, 1000));
+
+        let reopened = GeofenceStore::open(&path).unwrap();
+        assert_eq!(reopened.get(fence.id).unwrap().name, "Burj");
+        assert_eq!(reopened.events(&EventQuery::default()).len(), 1);
+        assert_eq!(reopened.next_id, fence.id + 1);
+    }
+
+    #[test]
+    fn test_recovers_from_corrupt_files() {
+        let path = temp_path("corrupt");
+        let mut store = GeofenceStore::open(&path).unwrap();
+        store.create(circle(None)).unwrap();
+        store.evaluate(None, &fix(1, 25.1972, 55.2744, 1000));
+        store.evaluate(Some(&fix(1, 25.1972, 55.2744, 1000)), &fix(2, 25.3, 55.2744, 1100));
+        drop(store);
+
+        // Crash no meio de um evento: a linha incompleta é descartada
+        let events_path = path.with_extension("events.jsonl");
+        let mut log = OpenOptions::new().append(true).open(&events_path).unwrap();
+        log.write_all(b"{\"id\":3,\"ty").unwrap();
+        let mut store = GeofenceStore::open(&path).unwrap();
+        assert_eq!(store.events(&EventQuery::default()).len(), 2);
+        store.evaluate(None, &fix(3, 25.1972, 55.2744, 1200));
+        assert_eq!(GeofenceStore::open(&path).unwrap().events(&EventQuery::default()).len(), 3);
+
+        // Definições ilegíveis não impedem a abertura e ficam guardadas à parte
+        fs::write(&path, "{not json").unwrap();
+        let store = GeofenceStore::open(&path).unwrap();
+        assert!(store.list().is_empty());
+        assert_eq!(fs::read_to_string(path.with_extension("json.corrupt")).unwrap(), "{not json");
+    }
+
+    #[test]
+    fn test_rejects_invalid_definitions() {
+        let parse = |json: &str| Geofence::from_json(&avila_json::parse(json).unwrap()).unwrap_err();
+        assert_eq!(parse(r#"{"type":"circle"}"#), "missing 'name'");
+        assert_eq!(
+            parse(r#"{"name":"x","type":"circle","center":{"latitude":1,"longitude":2},"radius":-5}"#),
+            "'radius' must be a positive number of meters"
+        );
+        assert_eq!(
+            parse(r#"{"name":"x","type":"polygon","vertices":[{"latitude":1,"longitude":2}]}"#),
+            "'vertices' needs at least 3 points"
+        );
+        assert_eq!(
+            parse(r#"{"name":"x","type":"circle","center":{"latitude":95,"longitude":2},"radius":5}"#),
+            "'center': latitude 95 is outside [-90, 90]"
+        );
+        assert_eq!(
+            parse(r#"{"name":"x","type":"circle","center":{"latitude":1},"radius":5}"#),
+            "'center' needs a numeric 'latitude' and 'longitude'"
+        );
+    }
+}
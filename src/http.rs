@@ -0,0 +1,178 @@
+// Parsing de requisições HTTP/1.1 - std puro
+// Linha de requisição, query string, headers e body
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::net::TcpStream;
+
+/// Upper bound on accepted request bodies
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+#[derive(Debug)]
+pub struct Request {
+    pub method: String,
+    /// Path without the query string
+    pub path: String,
+    /// Decoded query parameters; the last occurrence wins
+    pub query: HashMap<String, String>,
+    /// Header names are lowercased
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+impl Request {
+    /// Query parameters merged with a form-urlencoded body (body wins)
+    pub fn form_params(&self) -> HashMap<String, String> {
+        let mut params = self.query.clone();
+        let is_form = self
+            .headers
+            .get("content-type")
+            .is_some_and(|ct| ct.starts_with("application/x-www-form-urlencoded"));
+        if is_form {
+            params.extend(parse_query(self.body.trim()));
+        }
+        params
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum RequestError {
+    /// Unreadable or malformed request line
+    Malformed,
+    /// `Content-Length` above [`MAX_BODY`]
+    TooLarge,
+}
+
+/// Read one request from the stream
+pub fn read_request(stream: &TcpStream) -> Result<Request, RequestError> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|_| RequestError::Malformed)?;
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().ok_or(RequestError::Malformed)?.to_string();
+    let target = parts.next().ok_or(RequestError::Malformed)?;
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)),
+        None => (target.to_string(), HashMap::new()),
+    };
+
+    // Headers até linha vazia
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(|_| RequestError::Malformed)? == 0 {
+            break;
+        }
+        let line = line.trim_end_matches(['\r', '\n']);
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.insert(name.trim().to_lowercase(), value.trim().to_string());
+        }
+    }
+
+    // Ler body se existir (do mesmo buffer, que pode já conter parte dele)
+    let mut body = String::new();
+    let content_length: usize = headers
+        .get("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    if content_length > MAX_BODY {
+        return Err(RequestError::TooLarge);
+    }
+    if content_length > 0 {
+        let mut buffer = vec![0u8; content_length];
+        if reader.read_exact(&mut buffer).is_ok() {
+            body = String::from_utf8_lossy(&buffer).to_string();
+        }
+    }
+
+    Ok(Request { method, path, query, headers, body })
+}
+
+/// Parse `a=1&b=two%20words` (also used for form-urlencoded bodies)
+pub fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) => (form_decode(key), form_decode(value)),
+            None => (form_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Decode a query or form component: '+' is a space, then %XX escapes
+fn form_decode(input: &str) -> String {
+    percent_decode(&input.replace('+', " "))
+}
+
+/// Decode %XX escapes from a path segment; '+' stays literal
+pub fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(byte) => {
+                        out.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => out.push(b'%'),
+                }
+            }
+            b => out.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&out).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("Seu%20PC"), "Seu PC");
+        assert_eq!(percent_decode("a+b"), "a+b");
+        assert_eq!(form_decode("a+b%2Bc"), "a b+c");
+        assert_eq!(percent_decode("S%C3%A3o"), "São");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("device=Seu+PC&limit=10&flag&bbox=1,2,3,4");
+        assert_eq!(query["device"], "Seu PC");
+        assert_eq!(query["limit"], "10");
+        assert_eq!(query["flag"], "");
+        assert_eq!(query["bbox"], "1,2,3,4");
+    }
+
+    #[test]
+    fn test_oversized_body_is_refused() {
+        use std::io::Write;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        write!(client, "POST /api/import HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY + 1).unwrap();
+        assert_eq!(read_request(&server).unwrap_err(), RequestError::TooLarge);
+
+        write!(client, "\r\n").unwrap();
+        assert_eq!(read_request(&server).unwrap_err(), RequestError::Malformed);
+    }
+}
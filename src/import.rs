@@ -0,0 +1,578 @@
+// Importação de trilhas: GPX, KML, GeoJSON e CSV
+// Cada ponto rejeitado vira um erro com o número da linha/ponto
+
+use crate::export::Format;
+use crate::time::parse_rfc3339;
+use crate::Location;
+use avila_geo::Coordinate;
+use avila_json::JsonValue;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    /// Device name for records that don't carry one
+    pub device: Option<String>,
+    /// Timestamp for records that don't carry one (e.g. KML `LineString` vertices)
+    pub default_timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowError {
+    /// 1-based line (CSV) or point/feature number (GPX, KML, GeoJSON)
+    pub row: usize,
+    pub message: String,
+}
+
+impl RowError {
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("row".to_string(), JsonValue::Number(self.row as f64));
+        map.insert("message".to_string(), JsonValue::String(self.message.clone()));
+        JsonValue::Object(map)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub locations: Vec<Location>,
+    pub errors: Vec<RowError>,
+}
+
+/// Accumulates the report while a document is parsed
+struct Collector<'a> {
+    options: &'a ImportOptions,
+    report: ImportReport,
+}
+
+impl Collector<'_> {
+    fn error(&mut self, row: usize, message: impl Into<String>) {
+        self.report.errors.push(RowError { row, message: message.into() });
+    }
+
+    /// Validate one point and either keep it or record why it was rejected
+    fn add(
+        &mut self,
+        row: usize,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+        timestamp: Option<u64>,
+        device: Option<&str>,
+        accuracy: Option<f64>,
+    ) {
+        let options = self.options;
+        let Some(latitude) = latitude else {
+            return self.error(row, "missing or invalid latitude");
+        };
+        let Some(longitude) = longitude else {
+            return self.error(row, "missing or invalid longitude");
+        };
+        // Mesma regra da ingestão ao vivo
+        if let Err(e) = Coordinate::try_new(latitude, longitude) {
+            return self.error(row, e.to_string());
+        }
+        let timestamp = match timestamp.or(options.default_timestamp) {
+            Some(ts) => ts,
+            None => return self.error(row, "missing or invalid timestamp"),
+        };
+        let device_name = device
+            .map(str::to_string)
+            .or_else(|| options.device.clone())
+            .unwrap_or_else(|| "Imported".to_string());
+
+        self.report.locations.push(Location {
+            id: 0,
+            latitude,
+            longitude,
+            accuracy,
+            timestamp: timestamp.to_string(),
+            device_name,
+            ..Default::default()
+        });
+    }
+}
+
+/// Guess the format from the document itself
+pub fn detect_format(data: &str) -> Format {
+    let head = data.trim_start();
+    if head.starts_with('{') {
+        Format::GeoJson
+    } else if head.starts_with('<') {
+        if head.contains("<kml") {
+            Format::Kml
+        } else {
+            Format::Gpx
+        }
+    } else {
+        Format::Csv
+    }
+}
+
+pub fn parse(format: Format, data: &str, options: &ImportOptions) -> ImportReport {
+    let mut out = Collector { options, report: ImportReport::default() };
+    match format {
+        Format::Gpx => parse_gpx(data, &mut out),
+        Format::Kml => parse_kml(data, &mut out),
+        Format::GeoJson => parse_geojson(data, &mut out),
+        Format::Csv => parse_csv(data, &mut out),
+    }
+    out.report
+}
+
+/// Epoch seconds or an ISO 8601 date-time
+fn parse_timestamp(value: &str) -> Option<u64> {
+    let value = value.trim();
+    value.parse::<u64>().ok().or_else(|| parse_rfc3339(value))
+}
+
+// ---------- XML ----------
+
+/// One occurrence of `<tag ...>inner</tag>` (or `<tag .../>`)
+struct Element<'a> {
+    attrs: &'a str,
+    inner: &'a str,
+}
+
+impl Element<'_> {
+    fn attr(&self, name: &str) -> Option<String> {
+        for quote in ['"', '\''] {
+            let pattern = format!("{}={}", name, quote);
+            let mut search = self.attrs;
+            while let Some(pos) = search.find(&pattern) {
+                let preceded_ok = pos == 0 || search.as_bytes()[pos - 1].is_ascii_whitespace();
+                let rest = &search[pos + pattern.len()..];
+                if preceded_ok {
+                    return rest.find(quote).map(|end| xml_unescape(&rest[..end]));
+                }
+                search = rest;
+            }
+        }
+        None
+    }
+}
+
+/// Non-nesting scan for elements named `tag`, in document order
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<Element<'a>> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let mut found = Vec::new();
+    let mut pos = 0;
+
+    while let Some(start) = xml[pos..].find(&open) {
+        let after_name = pos + start + open.len();
+        let next = xml[after_name..].chars().next();
+        if !matches!(next, Some(c) if c == '>' || c == '/' || c.is_whitespace()) {
+            pos = after_name;
+            continue;
+        }
+
+        let tag_end = match xml[after_name..].find('>') {
+            Some(i) => after_name + i,
+            None => break,
+        };
+        let attrs = &xml[after_name..tag_end];
+
+        if let Some(attrs) = attrs.strip_suffix('/') {
+            found.push(Element { attrs, inner: "" });
+            pos = tag_end + 1;
+        } else {
+            match xml[tag_end + 1..].find(&close) {
+                Some(i) => {
+                    found.push(Element { attrs, inner: &xml[tag_end + 1..tag_end + 1 + i] });
+                    pos = tag_end + 1 + i + close.len();
+                }
+                None => break,
+            }
+        }
+    }
+    found
+}
+
+fn child_text(xml: &str, tag: &str) -> Option<String> {
+    elements(xml, tag).first().map(|e| xml_unescape(e.inner.trim()))
+}
+
+fn xml_unescape(s: &str) -> String {
+    let s = s.trim();
+    let s = s
+        .strip_prefix("<![CDATA[")
+        .and_then(|inner| inner.strip_suffix("]]>"))
+        .unwrap_or(s);
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn parse_gpx(data: &str, out: &mut Collector) {
+    let mut row = 0;
+    let tracks = elements(data, "trk");
+
+    // Sem <trk>: aceitar trkpt soltos no documento
+    let scopes: Vec<(Option<String>, &str)> = if tracks.is_empty() {
+        vec![(None, data)]
+    } else {
+        tracks
+            .iter()
+            .map(|trk| {
+                let header = trk.inner.split("<trkseg").next().unwrap_or("");
+                (child_text(header, "name"), trk.inner)
+            })
+            .collect()
+    };
+
+    for (name, scope) in scopes {
+        for point in elements(scope, "trkpt") {
+            row += 1;
+            let latitude = point.attr("lat").and_then(|v| v.trim().parse().ok());
+            let longitude = point.attr("lon").and_then(|v| v.trim().parse().ok());
+            let timestamp = child_text(point.inner, "time").and_then(|t| parse_timestamp(&t));
+            out.add(row, latitude, longitude, timestamp, name.as_deref(), None);
+        }
+    }
+
+    if row == 0 {
+        out.error(0, "no <trkpt> elements found");
+    }
+}
+
+fn parse_kml(data: &str, out: &mut Collector) {
+    let mut row = 0;
+    let folders = elements(data, "Folder");
+
+    let scopes: Vec<(Option<String>, &str)> = if folders.is_empty() {
+        vec![(None, data)]
+    } else {
+        folders
+            .iter()
+            .map(|folder| {
+                let header = folder.inner.split("<Placemark").next().unwrap_or("");
+                (child_text(header, "name"), folder.inner)
+            })
+            .collect()
+    };
+
+    for (folder_name, scope) in scopes {
+        let placemarks = elements(scope, "Placemark");
+        // Nosso export traz a trilha duas vezes: LineString sem horários e um Point datado por fix.
+        // Havendo Points datados, a LineString é só desenho e importá-la duplicaria os fixes
+        let timed_points = placemarks
+            .iter()
+            .any(|p| child_text(p.inner, "TimeStamp").is_some() && !elements(p.inner, "Point").is_empty());
+
+        for placemark in placemarks {
+            let device = folder_name.clone().or_else(|| child_text(placemark.inner, "name"));
+            let device = device.as_deref();
+
+            // gx:Track: <when> e <gx:coord> pareados
+            for track in elements(placemark.inner, "gx:Track") {
+                let whens = elements(track.inner, "when");
+                for (i, coord) in elements(track.inner, "gx:coord").iter().enumerate() {
+                    row += 1;
+                    let parts: Vec<f64> = coord
+                        .inner
+                        .split_whitespace()
+                        .filter_map(|v| v.parse().ok())
+                        .collect();
+                    let timestamp = whens.get(i).and_then(|w| parse_timestamp(w.inner));
+                    out.add(row, parts.get(1).copied(), parts.first().copied(), timestamp, device, None);
+                }
+            }
+
+            let when = child_text(placemark.inner, "TimeStamp").and_then(|ts| {
+                child_text(&ts, "when").or(Some(ts)).and_then(|w| parse_timestamp(&w))
+            });
+
+            for geometry in ["Point", "LineString"] {
+                if geometry == "LineString" && timed_points {
+                    continue;
+                }
+                for element in elements(placemark.inner, geometry) {
+                    let coordinates = child_text(element.inner, "coordinates").unwrap_or_default();
+                    for tuple in coordinates.split_whitespace() {
+                        row += 1;
+                        let parts: Vec<Option<f64>> =
+                            tuple.split(',').map(|v| v.trim().parse().ok()).collect();
+                        let lon = parts.first().copied().flatten();
+                        let lat = parts.get(1).copied().flatten();
+                        let timestamp = if geometry == "Point" { when } else { None };
+                        out.add(row, lat, lon, timestamp, device, None);
+                    }
+                }
+            }
+        }
+    }
+
+    if row == 0 {
+        out.error(0, "no coordinates found");
+    }
+}
+
+fn parse_geojson(data: &str, out: &mut Collector) {
+    let json = match avila_json::parse(data) {
+        Ok(json) => json,
+        Err(e) => return out.error(0, format!("invalid JSON: {:?}", e)),
+    };
+
+    let features: Vec<&JsonValue> = match json.as_object().and_then(|m| m.get("type")).and_then(|t| t.as_str()) {
+        Some("FeatureCollection") => json
+            .as_object()
+            .and_then(|m| m.get("features"))
+            .and_then(|f| f.as_array())
+            .map(|f| f.iter().collect())
+            .unwrap_or_default(),
+        Some("Feature") => vec![&json],
+        _ => return out.error(0, "expected a Feature or FeatureCollection"),
+    };
+
+    for (index, feature) in features.iter().enumerate() {
+        let row = index + 1;
+        let feature = match feature.as_object() {
+            Some(feature) => feature,
+            None => {
+                out.error(row, "feature is not an object");
+                continue;
+            }
+        };
+        let empty = HashMap::new();
+        let properties = feature.get("properties").and_then(|p| p.as_object()).unwrap_or(&empty);
+        let device = ["device_name", "device", "name"]
+            .iter()
+            .find_map(|key| properties.get(*key).and_then(|v| v.as_str()));
+        let accuracy = properties.get("accuracy").and_then(|v| v.as_f64());
+        let timestamp = ["timestamp", "time"].iter().find_map(|key| json_timestamp(properties.get(*key)?));
+
+        let geometry = match feature.get("geometry").and_then(|g| g.as_object()) {
+            Some(geometry) => geometry,
+            None => {
+                out.error(row, "missing geometry");
+                continue;
+            }
+        };
+        let coordinates = geometry.get("coordinates").and_then(|c| c.as_array());
+
+        match (geometry.get("type").and_then(|t| t.as_str()), coordinates) {
+            (Some("Point"), Some(position)) => {
+                let (lat, lon) = position_lat_lon(position);
+                out.add(row, lat, lon, timestamp, device, accuracy);
+            }
+            (Some("LineString"), Some(positions)) => {
+                // Convenção do togeojson: properties.coordTimes paralelo às coordenadas
+                let times = properties.get("coordTimes").and_then(|t| t.as_array());
+                for (i, position) in positions.iter().enumerate() {
+                    let (lat, lon) = position.as_array().map(|p| position_lat_lon(p)).unwrap_or((None, None));
+                    let ts = times.and_then(|t| t.get(i)).and_then(json_timestamp).or(timestamp);
+                    out.add(row, lat, lon, ts, device, accuracy);
+                }
+            }
+            (Some(other), _) if other != "Point" && other != "LineString" => {
+                out.error(row, format!("unsupported geometry type {}", other))
+            }
+            _ => out.error(row, "invalid geometry"),
+        }
+    }
+}
+
+fn position_lat_lon(position: &[JsonValue]) -> (Option<f64>, Option<f64>) {
+    (
+        position.get(1).and_then(|v| v.as_f64()),
+        position.first().and_then(|v| v.as_f64()),
+    )
+}
+
+fn json_timestamp(value: &JsonValue) -> Option<u64> {
+    match value {
+        JsonValue::Number(n) if *n >= 0.0 => Some(*n as u64),
+        JsonValue::String(s) => parse_timestamp(s),
+        _ => None,
+    }
+}
+
+// ---------- CSV ----------
+
+/// Split one CSV record, honouring quotes and doubled quotes
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+/// Split into records, keeping newlines that appear inside quoted fields
+fn csv_records(data: &str) -> Vec<(usize, String)> {
+    let mut records = Vec::new();
+    let mut current = String::new();
+    let mut start_line = 1;
+    let mut quotes = 0;
+
+    for (i, line) in data.lines().enumerate() {
+        if current.is_empty() {
+            start_line = i + 1;
+        } else {
+            current.push('\n');
+        }
+        current.push_str(line);
+        quotes += line.matches('"').count();
+        if quotes % 2 == 0 {
+            records.push((start_line, std::mem::take(&mut current)));
+            quotes = 0;
+        }
+    }
+    if !current.is_empty() {
+        records.push((start_line, current));
+    }
+    records
+}
+
+fn parse_csv(data: &str, out: &mut Collector) {
+    let mut records = csv_records(data).into_iter().filter(|(_, r)| !r.trim().is_empty());
+
+    let header: Vec<String> = match records.next() {
+        Some((_, line)) => csv_fields(&line).iter().map(|h| h.trim().to_lowercase()).collect(),
+        None => return out.error(0, "empty CSV"),
+    };
+    let column = |names: &[&str]| header.iter().position(|h| names.contains(&h.as_str()));
+
+    let lat_col = column(&["latitude", "lat"]);
+    let lon_col = column(&["longitude", "lon", "lng"]);
+    let time_col = column(&["timestamp", "time", "date", "datetime"]);
+    let device_col = column(&["device_name", "device", "name"]);
+    let accuracy_col = column(&["accuracy", "acc"]);
+
+    if lat_col.is_none() || lon_col.is_none() {
+        return out.error(1, "header must have latitude and longitude columns");
+    }
+
+    for (row, line) in records {
+        let fields = csv_fields(&line);
+        let get = |col: Option<usize>| col.and_then(|c| fields.get(c)).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        let accuracy = get(accuracy_col).and_then(|v| v.parse().ok());
+        out.add(
+            row,
+            get(lat_col).and_then(|v| v.parse().ok()),
+            get(lon_col).and_then(|v| v.parse().ok()),
+            get(time_col).and_then(parse_timestamp),
+            get(device_col),
+            accuracy,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export;
+
+    fn options() -> ImportOptions {
+        ImportOptions::default()
+    }
+
+    #[test]
+    fn test_gpx_trkpt() {
+        let gpx = r#"<?xml version="1.0"?>
+<gpx version="1.1"><trk><name>Bike &amp; Run</name><trkseg>
+  <trkpt lat="25.1972" lon="55.2744"><ele>5</ele><time>2023-11-14T22:13:20Z</time></trkpt>
+  <trkpt lat='25.0805' lon='55.1399'><time>2023-11-14T22:14:20Z</time></trkpt>
+  <trkpt lat="95" lon="55"><time>2023-11-14T22:15:20Z</time></trkpt>
+  <trkpt lat="25" lon="55"/>
+</trkseg></trk></gpx>"#;
+        let report = parse(Format::Gpx, gpx, &options());
+
+        assert_eq!(report.locations.len(), 2);
+        assert_eq!(report.locations[0].device_name, "Bike & Run");
+        assert_eq!(report.locations[1].timestamp, "1700000060");
+        assert_eq!(report.errors[0], RowError { row: 3, message: "latitude 95 is outside [-90, 90]".to_string() });
+        assert_eq!(report.errors[1].row, 4);
+    }
+
+    #[test]
+    fn test_kml_points_and_linestring_fallback() {
+        let kml = r#"<kml><Document><Placemark><name>truck</name>
+<TimeStamp><when>2023-11-14T22:13:20Z</when></TimeStamp>
+<Point><coordinates>55.2744,25.1972,0</coordinates></Point></Placemark>
+<Placemark><name>truck</name><LineString><coordinates>55.1,25.1 55.2,25.2</coordinates></LineString></Placemark>
+</Document></kml>"#;
+
+        // Com Points datados, a LineString é só o desenho da trilha
+        let report = parse(Format::Kml, kml, &options());
+        assert_eq!(report.locations.len(), 1);
+        assert_eq!(report.locations[0].latitude, 25.1972);
+        assert!(report.errors.is_empty());
+        let with_default = ImportOptions { default_timestamp: Some(42), ..options() };
+        assert_eq!(parse(Format::Kml, kml, &with_default).locations.len(), 1);
+
+        // Só LineString: os vértices precisam de um horário padrão
+        let line = r#"<kml><Placemark><LineString><coordinates>55.1,25.1 55.2,25.2</coordinates></LineString></Placemark></kml>"#;
+        assert_eq!(parse(Format::Kml, line, &options()).errors.len(), 2);
+        assert_eq!(parse(Format::Kml, line, &with_default).locations.len(), 2);
+    }
+
+    #[test]
+    fn test_geojson_features() {
+        let geojson = r#"{"type":"FeatureCollection","features":[
+ {"type":"Feature","geometry":{"type":"Point","coordinates":[55.2744,25.1972]},"properties":{"device_name":"a","time":"2023-11-14T22:13:20Z"}},
+ {"type":"Feature","geometry":{"type":"LineString","coordinates":[[1,2],[3,4]]},"properties":{"coordTimes":[100,200]}},
+ {"type":"Feature","geometry":{"type":"Polygon","coordinates":[]},"properties":{}}
+]}"#;
+        let report = parse(Format::GeoJson, geojson, &ImportOptions { device: Some("fallback".to_string()), ..options() });
+
+        assert_eq!(report.locations.len(), 3);
+        assert_eq!(report.locations[0].timestamp, "1700000000");
+        assert_eq!(report.locations[2].device_name, "fallback");
+        assert_eq!(report.locations[2].timestamp, "200");
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].row, 3);
+    }
+
+    #[test]
+    fn test_csv_with_header() {
+        let csv = "Lat,Lon,Time,Device,Accuracy\n\
+                   25.1,55.2,1700000000,\"truck, 1\",5\n\
+                   abc,55.2,1700000000,truck,\n\
+                   \n\
+                   25.3,55.4,2023-11-14T22:13:20Z,truck,\n";
+        let report = parse(Format::Csv, csv, &options());
+
+        assert_eq!(report.locations.len(), 2);
+        assert_eq!(report.locations[0].device_name, "truck, 1");
+        assert_eq!(report.locations[0].accuracy, Some(5.0));
+        assert_eq!(report.errors, vec![RowError { row: 3, message: "missing or invalid latitude".to_string() }]);
+    }
+
+    #[test]
+    fn test_round_trip_through_export() {
+        let original = [
+            Location { id: 1, latitude: 25.1972, longitude: 55.2744, accuracy: None, timestamp: "1700000000".to_string(), device_name: "a".to_string(), ..Default::default() },
+            Location { id: 2, latitude: -23.55, longitude: -46.63, accuracy: None, timestamp: "1700000100".to_string(), device_name: "b".to_string(), ..Default::default() },
+        ];
+        let refs: Vec<&Location> = original.iter().collect();
+
+        for format in [Format::Gpx, Format::Kml, Format::GeoJson, Format::Csv] {
+            let data = export::export(format, &refs);
+            assert_eq!(detect_format(&data), format);
+            let report = parse(format, &data, &options());
+            assert!(report.errors.is_empty(), "{:?}: {:?}", format, report.errors);
+            let got: Vec<(f64, f64, &str, &str)> = report
+                .locations
+                .iter()
+                .map(|l| (l.latitude, l.longitude, l.timestamp.as_str(), l.device_name.as_str()))
+                .collect();
+            assert_eq!(got, vec![(25.1972, 55.2744, "1700000000", "a"), (-23.55, -46.63, "1700000100", "b")]);
+        }
+    }
+}
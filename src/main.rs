@@ -1,28 +1,84 @@
 // Device Location Tracker - 100% usando ecossistema Arxis
 // ZERO dependências externas! 🦀🚀
 
+mod analysis;
+mod config;
+mod crypto;
+mod devices;
+mod export;
+mod geofence;
+mod http;
+mod import;
+mod nmea;
+mod osmand;
+mod owntracks;
+mod quality;
+mod query;
+mod retention;
+mod spatial;
+mod stats;
+mod storage;
+mod stream;
+mod time;
+mod webhook;
+mod websocket;
+
+use avila_geo::kalman::{Estimate, Fix, KalmanFilter};
 use avila_json::JsonValue;
-use avila_geo::Coordinate;
-use std::collections::HashMap;
-use std::fs;
+use config::Config;
+use devices::DeviceRegistry;
+use geofence::{EventQuery, Geofence, GeofenceStore};
+use http::Request;
+use quality::{Quality, QualityFilter};
+use query::HistoryQuery;
+use retention::{RetentionPolicy, RetentionStatus};
+use stats::DistanceModel;
+use std::collections::{HashMap, VecDeque};
 use std::io::{BufRead, BufReader, Write};
 use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::Duration;
+use storage::Storage;
+use stream::Broadcaster;
+use webhook::{Webhook, WebhookStore};
+use websocket::{Message, MessageReader, Opcode, Subscription};
 use std::path::Path;
 
-#[derive(Debug, Clone)]
+/// Idle interval after which live connections get a keep-alive (SSE comment, WebSocket ping)
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Error assumed by the Kalman filter for fixes sent without `accuracy`, in meters
+const UNKNOWN_ACCURACY: f64 = 30.0;
+
+#[derive(Debug, Clone, Default)]
 struct Location {
+    /// Assigned by `LocationHistory::push`; 0 until stored
+    id: u64,
     latitude: f64,
     longitude: f64,
     accuracy: Option<f64>,
     timestamp: String,
     device_name: String,
+    /// Meters above sea level
+    altitude: Option<f64>,
+    /// Meters per second
+    speed: Option<f64>,
+    /// Degrees clockwise from true north
+    course: Option<f64>,
+    /// Battery level in percent
+    battery: Option<f64>,
+    /// Kalman-smoothed position, when `GPS_KALMAN` is on; the fields above stay raw
+    filtered: Option<Estimate>,
+    /// Set when the ingestion filter flagged the fix
+    quality: Option<Quality>,
 }
 
 impl Location {
     fn to_json(&self) -> JsonValue {
         let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::Number(self.id as f64));
         map.insert("latitude".to_string(), JsonValue::Number(self.latitude));
         map.insert("longitude".to_string(), JsonValue::Number(self.longitude));
 
@@ -35,17 +91,54 @@ impl Location {
         map.insert("timestamp".to_string(), JsonValue::String(self.timestamp.clone()));
         map.insert("device_name".to_string(), JsonValue::String(self.device_name.clone()));
 
+        // Campos opcionais só aparecem quando informados
+        for (key, value) in [
+            ("altitude", self.altitude),
+            ("speed", self.speed),
+            ("course", self.course),
+            ("battery", self.battery),
+        ] {
+            if let Some(value) = value {
+                map.insert(key.to_string(), JsonValue::Number(value));
+            }
+        }
+
+        if let Some(estimate) = &self.filtered {
+            let mut filtered = HashMap::new();
+            filtered.insert("latitude".to_string(), JsonValue::Number(estimate.coordinate.latitude));
+            filtered.insert("longitude".to_string(), JsonValue::Number(estimate.coordinate.longitude));
+            filtered.insert("accuracy".to_string(), JsonValue::Number(estimate.accuracy));
+            filtered.insert("speed".to_string(), JsonValue::Number(estimate.speed()));
+            filtered.insert("course".to_string(), JsonValue::Number(estimate.course()));
+            map.insert("filtered".to_string(), JsonValue::Object(filtered));
+        }
+        if let Some(quality) = self.quality {
+            map.insert("quality".to_string(), JsonValue::String(quality.as_str().to_string()));
+        }
+
         JsonValue::Object(map)
     }
 
+    /// Timestamp as seconds since the Unix epoch
+    fn epoch_secs(&self) -> Option<u64> {
+        self.timestamp.parse().ok()
+    }
+
     fn from_json(json: &JsonValue) -> Option<Self> {
         if let JsonValue::Object(map) = json {
             Some(Location {
+                id: map.get("id").and_then(|v| v.as_f64()).unwrap_or(0.0) as u64,
                 latitude: map.get("latitude")?.as_f64()?,
                 longitude: map.get("longitude")?.as_f64()?,
                 accuracy: map.get("accuracy").and_then(|v| v.as_f64()),
                 timestamp: map.get("timestamp")?.as_str()?.to_string(),
                 device_name: map.get("device_name")?.as_str()?.to_string(),
+                altitude: map.get("altitude").and_then(|v| v.as_f64()),
+                speed: map.get("speed").and_then(|v| v.as_f64()),
+                course: map.get("course").and_then(|v| v.as_f64()),
+                battery: map.get("battery").and_then(|v| v.as_f64()),
+                filtered: map.get("filtered").and_then(estimate_from_json),
+                quality: map.get("quality").and_then(|v| v.as_str()).and_then(Quality::parse),
             })
         } else {
             None
@@ -53,12 +146,100 @@ impl Location {
     }
 }
 
+fn estimate_from_json(json: &JsonValue) -> Option<Estimate> {
+    let map = json.as_object()?;
+    let field = |name: &str| map.get(name).and_then(|v| v.as_f64());
+    let (speed, course) = (field("speed")?, field("course")?.to_radians());
+    Some(Estimate {
+        coordinate: avila_geo::Coordinate::new(field("latitude")?, field("longitude")?),
+        velocity_east: speed * course.sin(),
+        velocity_north: speed * course.cos(),
+        accuracy: field("accuracy")?,
+    })
+}
+
 #[derive(Debug, Clone)]
 struct LocationHistory {
-    locations: Vec<Location>,
+    locations: VecDeque<Location>,
+    /// Next id to hand out; never reused, even after a clear
+    next_id: u64,
+    /// Kept in sync by every method that adds or removes locations
+    index: spatial::GeohashIndex,
 }
 
 impl LocationHistory {
+    fn new() -> Self {
+        LocationHistory {
+            locations: VecDeque::new(),
+            next_id: 1,
+            index: spatial::GeohashIndex::new(),
+        }
+    }
+
+    /// Assign the next id and append
+    fn push(&mut self, mut location: Location) -> &Location {
+        location.id = self.next_id;
+        self.next_id += 1;
+        self.index.insert(&location);
+        self.locations.push_back(location);
+        self.locations.back().unwrap()
+    }
+
+    /// Append a location loaded from disk, keeping its id if it has one
+    fn restore(&mut self, location: Location) {
+        if location.id == 0 {
+            self.push(location);
+        } else {
+            self.next_id = self.next_id.max(location.id + 1);
+            self.index.insert(&location);
+            self.locations.push_back(location);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.locations.clear();
+        self.index.clear();
+    }
+
+    fn pop_front(&mut self) -> Option<Location> {
+        let location = self.locations.pop_front()?;
+        self.index.remove(&location);
+        Some(location)
+    }
+
+    /// Keep only the locations for which `keep` returns true
+    fn retain(&mut self, mut keep: impl FnMut(&Location) -> bool) {
+        let index = &mut self.index;
+        self.locations.retain(|location| {
+            let kept = keep(location);
+            if !kept {
+                index.remove(location);
+            }
+            kept
+        });
+    }
+
+    /// Location by id; ids grow with insertion order, so a binary search works
+    fn get(&self, id: u64) -> Option<&Location> {
+        let at = self.locations.partition_point(|location| location.id < id);
+        self.locations.get(at).filter(|location| location.id == id)
+    }
+
+    /// Parse a GPX/KML/GeoJSON/CSV document and append every valid point.
+    /// Returns the stored locations (with ids) and the rejected rows.
+    fn import(
+        &mut self,
+        format: export::Format,
+        data: &str,
+        options: &import::ImportOptions,
+    ) -> import::ImportReport {
+        let mut report = import::parse(format, data, options);
+        for location in report.locations.iter_mut() {
+            *location = self.push(location.clone()).clone();
+        }
+        report
+    }
+
     fn to_json(&self) -> JsonValue {
         let locations_array: Vec<JsonValue> = self.locations
             .iter()
@@ -67,17 +248,21 @@ impl LocationHistory {
 
         let mut map = HashMap::new();
         map.insert("locations".to_string(), JsonValue::Array(locations_array));
+        map.insert("next_id".to_string(), JsonValue::Number(self.next_id as f64));
         JsonValue::Object(map)
     }
 
     fn from_json(json: &JsonValue) -> Option<Self> {
         if let JsonValue::Object(map) = json {
             if let Some(JsonValue::Array(arr)) = map.get("locations") {
-                let locations: Vec<Location> = arr
-                    .iter()
-                    .filter_map(Location::from_json)
-                    .collect();
-                return Some(LocationHistory { locations });
+                let mut history = LocationHistory::new();
+                if let Some(next_id) = map.get("next_id").and_then(|v| v.as_f64()) {
+                    history.next_id = history.next_id.max(next_id as u64);
+                }
+                for location in arr.iter().filter_map(Location::from_json) {
+                    history.restore(location);
+                }
+                return Some(history);
             }
         }
         None
@@ -85,101 +270,272 @@ impl LocationHistory {
 }
 
 struct AppState {
-    current_location: Mutex<Option<Location>>,
+    devices: Mutex<DeviceRegistry>,
     history: Mutex<LocationHistory>,
+    storage: Storage,
+    retention: RetentionPolicy,
+    retention_status: Mutex<RetentionStatus>,
+    /// Live subscribers (SSE) for newly ingested fixes
+    broadcaster: Broadcaster,
+    geofences: Mutex<GeofenceStore>,
+    webhooks: Arc<Mutex<WebhookStore>>,
+    /// Battery percentage below which `battery.low` fires
+    low_battery: f64,
+    distance_model: DistanceModel,
+    /// Kalman process noise; `None` stores raw fixes only
+    kalman_noise: Option<f64>,
+    /// One filter per device, fed in arrival order
+    filters: Mutex<HashMap<String, KalmanFilter>>,
+    quality: Mutex<QualityFilter>,
 }
 
-const HISTORY_FILE: &str = "location_history.json";
-
 impl AppState {
-    fn new() -> Self {
-        let history = if Path::new(HISTORY_FILE).exists() {
-            if let Ok(data) = fs::read_to_string(HISTORY_FILE) {
-                if let Ok(json) = avila_json::parse(&data) {
-                    LocationHistory::from_json(&json).unwrap_or(LocationHistory {
-                        locations: Vec::new(),
-                    })
-                } else {
-                    LocationHistory {
-                        locations: Vec::new(),
+    fn new(config: &Config) -> std::io::Result<Self> {
+        let (storage, history) = Storage::open(
+            Path::new(&config.history_file),
+            config.fsync,
+            config.compact_every,
+            config.compact_interval,
+        )?;
+
+        // Reconstruir registro de dispositivos e filtro de qualidade a partir do histórico;
+        // fixes marcados não contam como posição do dispositivo
+        let mut devices = DeviceRegistry::new();
+        let mut quality = QualityFilter::new(config.quality);
+        for location in history.locations.iter().filter(|l| l.quality.is_none()) {
+            devices.record(location);
+            quality.seed(location);
+        }
+
+        Ok(AppState {
+            devices: Mutex::new(devices),
+            history: Mutex::new(history),
+            storage,
+            retention: config.retention.clone(),
+            retention_status: Mutex::new(RetentionStatus::default()),
+            broadcaster: Broadcaster::new(),
+            geofences: Mutex::new(GeofenceStore::open(Path::new(&config.geofence_file))?),
+            webhooks: Arc::new(Mutex::new(WebhookStore::open(Path::new(&config.webhook_file), config.webhook_retry)?)),
+            low_battery: config.low_battery,
+            distance_model: config.distance_model,
+            kalman_noise: config.kalman_noise,
+            filters: Mutex::new(HashMap::new()),
+            quality: Mutex::new(quality),
+        })
+    }
+
+    /// Attach the device's Kalman estimate, when smoothing is enabled.
+    /// Fixes without a readable timestamp or older than the last one stay raw.
+    fn smooth(&self, mut location: Location) -> Location {
+        let (Some(noise), Some(timestamp)) = (self.kalman_noise, location.epoch_secs()) else {
+            return location;
+        };
+        if let Ok(mut filters) = self.filters.lock() {
+            let filter = filters
+                .entry(location.device_name.clone())
+                .or_insert_with(|| KalmanFilter::new(noise));
+            location.filtered = filter.update(&Fix {
+                timestamp: timestamp as f64,
+                coordinate: avila_geo::Coordinate::new(location.latitude, location.longitude),
+                accuracy: location.accuracy.unwrap_or(UNKNOWN_ACCURACY),
+            });
+        }
+        location
+    }
+
+    /// Store a new fix: history, append-only log, retention cap, device
+    /// registry, live subscribers and geofence transitions
+    ///
+    /// Fixes failing the quality filter come back as `Err` in reject mode; in
+    /// flag mode they are stored with `quality` set but do not move the device,
+    /// feed the Kalman filter or fire events.
+    fn ingest(&self, mut location: Location) -> Result<Location, Quality> {
+        if let Ok(mut filter) = self.quality.lock() {
+            location.quality = filter.check(&location);
+            if let Some(quality) = location.quality {
+                if filter.mode() == quality::Mode::Reject {
+                    return Err(quality);
+                }
+            }
+        }
+        if location.quality.is_none() {
+            location = self.smooth(location);
+        }
+
+        let stored = match self.history.lock() {
+            Ok(mut history) => {
+                let stored = history.push(location).clone();
+                if let Err(e) = self.storage.append(&stored) {
+                    eprintln!("❌ Failed to persist location: {}", e);
+                }
+                self.retention.enforce_on_insert(&mut history);
+                // Publicar sob o lock do histórico mantém a ordem dos ids
+                self.broadcaster.publish(&stored);
+                stored
+            }
+            Err(_) => location,
+        };
+        if stored.quality.is_some() {
+            return Ok(stored);
+        }
+
+        // Atualizar última localização do dispositivo e avaliar geofences
+        // contra o fix anterior (fixes antigos, ex. importados, não geram eventos)
+        let mut notifications: Vec<(&str, JsonValue)> = Vec::new();
+        if let Ok(mut devices) = self.devices.lock() {
+            let previous = devices.get(&stored.device_name).map(|d| d.location.clone());
+            let is_latest = previous
+                .as_ref()
+                .is_none_or(|p| stored.epoch_secs() >= p.epoch_secs());
+            devices.record(&stored);
+
+            if previous.is_none() {
+                notifications.push(("device.new", device_notification(&stored)));
+            }
+            if is_latest {
+                // Só na transição para abaixo do limite, não a cada fix
+                let was_low = previous.as_ref().and_then(|p| p.battery).is_some_and(|b| b < self.low_battery);
+                if stored.battery.is_some_and(|b| b < self.low_battery) && !was_low {
+                    let mut data = device_notification(&stored);
+                    if let JsonValue::Object(map) = &mut data {
+                        map.insert("threshold".to_string(), JsonValue::Number(self.low_battery));
                     }
+                    notifications.push(("battery.low", data));
                 }
-            } else {
-                LocationHistory {
-                    locations: Vec::new(),
+
+                if let Ok(mut geofences) = self.geofences.lock() {
+                    for event in geofences.evaluate(previous.as_ref(), &stored) {
+                        println!("🚧 {} {} {}", event.device, event.kind.as_str(), event.geofence);
+                        let name = match event.kind {
+                            geofence::EventKind::Enter => "geofence.enter",
+                            geofence::EventKind::Exit => "geofence.exit",
+                            geofence::EventKind::Dwell => "geofence.dwell",
+                        };
+                        notifications.push((name, event.to_json()));
+                    }
                 }
             }
-        } else {
-            LocationHistory {
-                locations: Vec::new(),
+        }
+
+        if !notifications.is_empty() {
+            if let Ok(mut webhooks) = self.webhooks.lock() {
+                webhooks.enqueue(notifications, unix_now());
             }
+        }
+
+        Ok(stored)
+    }
+
+    /// Apply the retention policy and, if anything was removed, compact so
+    /// the expired data is also gone from disk
+    fn enforce_retention(&self) {
+        let removed = match self.history.lock() {
+            Ok(mut history) => self.retention.prune(&mut history, unix_now()),
+            Err(_) => return,
         };
 
-        AppState {
-            current_location: Mutex::new(None),
-            history: Mutex::new(history),
+        if removed > 0 {
+            if let Err(e) = self.storage.compact(&self.history) {
+                eprintln!("❌ Compaction after pruning failed: {}", e);
+                return;
+            }
+            println!("🧹 Retention removed {} locations", removed);
         }
-    }
 
-    fn save_history(&self) {
-        if let Ok(history) = self.history.lock() {
-            let json = history.to_json();
-            let json_str = json.to_string();
-            let _ = fs::write(HISTORY_FILE, json_str);
+        if let Ok(mut status) = self.retention_status.lock() {
+            status.last_run = Some(unix_now());
+            status.last_removed = removed;
+            status.total_removed += removed as u64;
         }
     }
 }
 
-fn handle_client(mut stream: TcpStream, state: Arc<AppState>) {
-    let buf_reader = BufReader::new(&stream);
-    let mut lines = buf_reader.lines();
+/// Webhook data for device-level events
+fn device_notification(location: &Location) -> JsonValue {
+    let mut map = HashMap::new();
+    map.insert("device".to_string(), JsonValue::String(location.device_name.clone()));
+    map.insert("location".to_string(), location.to_json());
+    JsonValue::Object(map)
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
 
-    // Parse request line
-    if let Some(Ok(request_line)) = lines.next() {
-        let parts: Vec<&str> = request_line.split_whitespace().collect();
-        if parts.len() < 2 {
+fn handle_client(mut stream: TcpStream, state: Arc<AppState>) {
+    let request = match http::read_request(&stream) {
+        Ok(request) => request,
+        Err(http::RequestError::Malformed) => {
             send_response(&mut stream, 400, "text/plain", b"Bad Request");
             return;
         }
-
-        let method = parts[0];
-        let path = parts[1];
-
-        // Skip headers até linha vazia
-        let mut content_length = 0;
-        while let Some(Ok(line)) = lines.next() {
-            if line.is_empty() {
-                break;
-            }
-            if line.to_lowercase().starts_with("content-length:") {
-                if let Some(len_str) = line.split(':').nth(1) {
-                    content_length = len_str.trim().parse().unwrap_or(0);
-                }
-            }
+        Err(http::RequestError::TooLarge) => {
+            send_response(&mut stream, 413, "text/plain", b"Payload Too Large");
+            return;
         }
+    };
+    let body = &request.body;
 
-        // Ler body se existir
-        let mut body = String::new();
-        if content_length > 0 {
-            let mut buffer = vec![0u8; content_length];
-            if let Ok(reader) = stream.try_clone() {
-                use std::io::Read;
-                let mut reader = reader;
-                if reader.read_exact(&mut buffer).is_ok() {
-                    body = String::from_utf8_lossy(&buffer).to_string();
-                }
-            }
+    // OsmAnd / Traccar Client: parâmetros na query string ou no body
+    if request.path == "/" && (request.method == "GET" || request.method == "POST") {
+        let params = request.form_params();
+        if osmand::is_report(&params) {
+            return osmand_report(&mut stream, &state, &params);
         }
+    }
 
-        // Rotear requisição
-        match (method, path) {
-            ("GET", "/") => serve_index(&mut stream),
-            ("GET", "/api/location") => get_current_location(&mut stream, &state),
-            ("POST", "/api/location") => update_location(&mut stream, &state, &body),
-            ("GET", "/api/history") => get_history(&mut stream, &state),
-            ("DELETE", "/api/history/clear") => clear_history(&mut stream, &state),
-            _ => send_response(&mut stream, 404, "text/plain", b"Not Found"),
+    // Rotear requisição
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/") => serve_index(&mut stream),
+        ("GET", "/api/location") => get_current_location(&mut stream, &state),
+        ("POST", "/api/location") => update_location(&mut stream, &state, body),
+        ("POST", "/api/owntracks") => owntracks_location(&mut stream, &state, &request),
+        ("GET", "/api/ws") => websocket_session(stream, &state, &request),
+        ("GET", "/api/stream") => stream_locations(stream, &state, &request),
+        ("GET", "/api/history") => get_history(&mut stream, &state, &request),
+        ("GET", "/api/history/export") => export_history(&mut stream, &state, &request),
+        ("POST", "/api/history/import") => import_history(&mut stream, &state, &request),
+        ("DELETE", "/api/history/clear") => clear_history(&mut stream, &state),
+        ("GET", "/api/nearby") => get_nearby(&mut stream, &state, &request),
+        ("GET", "/api/clusters") => get_clusters(&mut stream, &state, &request),
+        ("GET", "/api/retention") => get_retention(&mut stream, &state),
+        ("GET", "/api/geofences") => list_geofences(&mut stream, &state),
+        ("POST", "/api/geofences") => create_geofence(&mut stream, &state, body),
+        (method, p) if p.starts_with("/api/geofences/") => {
+            geofence_by_id(&mut stream, &state, method, &p["/api/geofences/".len()..], body)
+        }
+        ("GET", "/api/webhooks") => list_webhooks(&mut stream, &state),
+        ("POST", "/api/webhooks") => create_webhook(&mut stream, &state, body),
+        ("GET", "/api/webhooks/dead-letters") => list_dead_letters(&mut stream, &state),
+        ("POST", p) if p.starts_with("/api/webhooks/dead-letters/") && p.ends_with("/retry") => {
+            let id = &p["/api/webhooks/dead-letters/".len()..p.len() - "/retry".len()];
+            retry_dead_letter(&mut stream, &state, id);
+        }
+        ("DELETE", p) if p.starts_with("/api/webhooks/") => {
+            delete_webhook(&mut stream, &state, &p["/api/webhooks/".len()..]);
+        }
+        ("GET", "/api/events") => list_events(&mut stream, &state, &request),
+        ("GET", "/api/devices") => list_devices(&mut stream, &state),
+        ("GET", p) if p.starts_with("/api/devices/") && p.ends_with("/location") => {
+            let id = &p["/api/devices/".len()..p.len() - "/location".len()];
+            get_device_location(&mut stream, &state, &http::percent_decode(id));
+        }
+        ("GET", p) if p.starts_with("/api/devices/") && p.ends_with("/distance") => {
+            let id = &p["/api/devices/".len()..p.len() - "/distance".len()];
+            get_device_distance(&mut stream, &state, &http::percent_decode(id), &request);
+        }
+        ("GET", p) if p.starts_with("/api/devices/") && p.ends_with("/trips") => {
+            let id = &p["/api/devices/".len()..p.len() - "/trips".len()];
+            get_device_trips(&mut stream, &state, &http::percent_decode(id), &request);
+        }
+        ("GET", p) if p.starts_with("/api/devices/") && p.ends_with("/stays") => {
+            let id = &p["/api/devices/".len()..p.len() - "/stays".len()];
+            get_device_stays(&mut stream, &state, &http::percent_decode(id), &request);
         }
+        _ => send_response(&mut stream, 404, "text/plain", b"Not Found"),
     }
 }
 
@@ -189,9 +545,9 @@ fn serve_index(stream: &mut TcpStream) {
 }
 
 fn get_current_location(stream: &mut TcpStream, state: &Arc<AppState>) {
-    if let Ok(location) = state.current_location.lock() {
-        if let Some(loc) = &*location {
-            let json = loc.to_json();
+    if let Ok(devices) = state.devices.lock() {
+        if let Some(device) = devices.latest() {
+            let json = device.location.to_json();
             let json_str = json.to_string();
             send_response(stream, 200, "application/json", json_str.as_bytes());
         } else {
@@ -205,63 +561,901 @@ fn get_current_location(stream: &mut TcpStream, state: &Arc<AppState>) {
 
 fn update_location(stream: &mut TcpStream, state: &Arc<AppState>, body: &str) {
     // Parse JSON body
-    if let Ok(json) = avila_json::parse(body) {
-        if let JsonValue::Object(map) = json {
-            let latitude = map.get("latitude").and_then(|v| v.as_f64());
-            let longitude = map.get("longitude").and_then(|v| v.as_f64());
-
-            if let (Some(lat), Some(lng)) = (latitude, longitude) {
-                let accuracy = map.get("accuracy").and_then(|v| v.as_f64());
-                let device_name = map.get("device_name")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or("Unknown Device")
-                    .to_string();
-
-                // Timestamp atual (formato simples)
-                let timestamp = format!("{}", std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap().as_secs());
-
-                let new_location = Location {
-                    latitude: lat,
-                    longitude: lng,
-                    accuracy,
-                    timestamp,
-                    device_name,
-                };
-
-                // Atualizar localização atual
-                if let Ok(mut current) = state.current_location.lock() {
-                    *current = Some(new_location.clone());
+    if let Ok(JsonValue::Object(map)) = avila_json::parse(body) {
+        match location_from_report(&map) {
+            Ok(new_location) => {
+                match state.ingest(new_location) {
+                    Ok(stored) => {
+                        let mut map = HashMap::new();
+                        map.insert("status".to_string(), JsonValue::String("success".to_string()));
+                        map.insert(
+                            "message".to_string(),
+                            JsonValue::String("Location updated successfully".to_string()),
+                        );
+                        if let Some(quality) = stored.quality {
+                            map.insert("quality".to_string(), JsonValue::String(quality.as_str().to_string()));
+                        }
+                        let json_str = JsonValue::Object(map).to_string();
+                        send_response(stream, 200, "application/json", json_str.as_bytes());
+                    }
+                    Err(quality) => send_rejected(stream, quality),
                 }
+                return;
+            }
+            Err(ReportError::Invalid { field, reason }) => {
+                return send_invalid_field(stream, field, &reason);
+            }
+            Err(ReportError::Missing) => {}
+        }
+    }
 
-                // Adicionar ao histórico
-                if let Ok(mut history) = state.history.lock() {
-                    history.locations.push(new_location);
+    send_response(stream, 400, "text/plain", b"Invalid JSON");
+}
 
-                    // Manter apenas últimas 1000
-                    if history.locations.len() > 1000 {
-                        history.locations.remove(0);
-                    }
-                }
+/// Why a location report was rejected
+enum ReportError {
+    /// No usable `latitude`/`longitude` (or `position`) at all
+    Missing,
+    /// A field is present but its value is unacceptable
+    Invalid { field: &'static str, reason: String },
+}
+
+impl From<avila_geo::CoordinateError> for ReportError {
+    fn from(error: avila_geo::CoordinateError) -> Self {
+        ReportError::Invalid { field: error.field(), reason: error.to_string() }
+    }
+}
+
+/// Why an OwnTracks or OsmAnd report could not be decoded
+#[derive(Debug, PartialEq)]
+pub enum DecodeError {
+    /// Missing or unreadable parameter (400)
+    Malformed(String),
+    /// Readable but impossible position (422, same answer as `/api/location`)
+    Coordinate(avila_geo::CoordinateError),
+}
+
+impl From<&str> for DecodeError {
+    fn from(message: &str) -> Self {
+        DecodeError::Malformed(message.to_string())
+    }
+}
+
+impl From<String> for DecodeError {
+    fn from(message: String) -> Self {
+        DecodeError::Malformed(message)
+    }
+}
+
+impl From<avila_geo::CoordinateError> for DecodeError {
+    fn from(error: avila_geo::CoordinateError) -> Self {
+        DecodeError::Coordinate(error)
+    }
+}
+
+/// Fix reported by a client in our own JSON format (`POST /api/location`, WebSocket)
+fn location_from_report(map: &HashMap<String, JsonValue>) -> Result<Location, ReportError> {
+    let (latitude, longitude) = report_position(map)?;
+    let coordinate = avila_geo::Coordinate::try_new(latitude, longitude)?;
+    let device_name = map.get("device_name")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Unknown Device")
+        .to_string();
+
+    Ok(Location {
+        id: 0,
+        latitude: coordinate.latitude,
+        longitude: coordinate.longitude,
+        accuracy: map.get("accuracy").and_then(|v| v.as_f64()),
+        // Timestamp atual (formato simples)
+        timestamp: unix_now().to_string(),
+        device_name,
+        altitude: map.get("altitude").and_then(|v| v.as_f64()),
+        speed: map.get("speed").and_then(|v| v.as_f64()),
+        course: map.get("course").and_then(|v| v.as_f64()),
+        battery: map.get("battery").and_then(|v| v.as_f64()),
+        filtered: None,
+        quality: None,
+    })
+}
+
+/// Numeric `latitude`/`longitude`; when absent, the same fields as text
+/// (`25°11'49.9"N`, `N25 11.832`) or a single `position` string with both
+fn report_position(map: &HashMap<String, JsonValue>) -> Result<(f64, f64), ReportError> {
+    let axis = |field: &'static str, parse: fn(&str) -> Result<f64, avila_geo::ParseCoordinateError>| {
+        match map.get(field) {
+            Some(JsonValue::Number(n)) => Ok(Some(*n)),
+            Some(JsonValue::String(s)) => parse(s)
+                .map(Some)
+                .map_err(|e| ReportError::Invalid { field, reason: e.to_string() }),
+            Some(_) => Err(ReportError::Invalid { field, reason: format!("{} must be a number or a string", field) }),
+            None => Ok(None),
+        }
+    };
+    let latitude = axis("latitude", avila_geo::dms::parse_latitude)?;
+    let longitude = axis("longitude", avila_geo::dms::parse_longitude)?;
+
+    match (latitude, longitude) {
+        (Some(latitude), Some(longitude)) => Ok((latitude, longitude)),
+        (None, None) => {
+            let position = map.get("position").and_then(|v| v.as_str()).ok_or(ReportError::Missing)?;
+            let coordinate: avila_geo::Coordinate = position
+                .parse()
+                .map_err(|e: avila_geo::ParseCoordinateError| ReportError::Invalid {
+                    field: "position",
+                    reason: e.to_string(),
+                })?;
+            Ok((coordinate.latitude, coordinate.longitude))
+        }
+        _ => Err(ReportError::Missing),
+    }
+}
+
+fn owntracks_location(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let payload = match avila_json::parse(&request.body) {
+        Ok(payload) => payload,
+        Err(_) => return send_error(stream, 400, "invalid JSON"),
+    };
 
-                // Salvar
-                state.save_history();
+    let device = owntracks::device_id(&payload, &request.headers);
+    match owntracks::to_location(&payload, &device, unix_now()) {
+        // Rejeitados ainda respondem 200: o app reenviaria o mesmo fix para sempre
+        Ok(Some(location)) => {
+            if let Err(quality) = state.ingest(location) {
+                eprintln!("❌ OwnTracks fix from {} rejected: {}", device, quality.as_str());
+            }
+        }
+        // Outros tipos (transition, waypoint, lwt...) são aceitos e ignorados
+        Ok(None) => {}
+        Err(DecodeError::Malformed(message)) => return send_error(stream, 400, &message),
+        Err(DecodeError::Coordinate(e)) => return send_invalid_field(stream, e.field(), &e.to_string()),
+    }
+
+    let friends = match state.devices.lock() {
+        Ok(devices) => owntracks::friends(&devices, &device),
+        Err(_) => JsonValue::Array(Vec::new()),
+    };
+    send_response(stream, 200, "application/json", friends.to_string().as_bytes());
+}
+
+fn osmand_report(stream: &mut TcpStream, state: &Arc<AppState>, params: &HashMap<String, String>) {
+    match osmand::to_location(params, unix_now()) {
+        Ok(location) => {
+            // Mesmo rejeitado responde 200, senão o cliente trava a fila reenviando o fix
+            let device = location.device_name.clone();
+            if let Err(quality) = state.ingest(location) {
+                eprintln!("❌ OsmAnd fix from {} rejected: {}", device, quality.as_str());
+            }
+            send_response(stream, 200, "text/plain", b"");
+        }
+        Err(DecodeError::Malformed(message)) => send_response(stream, 400, "text/plain", message.as_bytes()),
+        Err(DecodeError::Coordinate(e)) => send_invalid_field(stream, e.field(), &e.to_string()),
+    }
+}
+
+/// Raw NMEA 0183 stream: one sentence per line, until the client disconnects
+fn handle_nmea_client(stream: TcpStream, state: Arc<AppState>) {
+    let peer = stream.peer_addr().map(|a| a.ip().to_string()).unwrap_or_default();
+    let mut device = format!("nmea-{}", peer);
+    let mut accumulator = avila_geo::nmea::FixAccumulator::new();
+    let (mut fixes, mut rejected, mut dropped) = (0u64, 0u64, 0u64);
+
+    for (index, line) in BufReader::new(stream).split(b'\n').enumerate() {
+        let line = match line {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(_) => break,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
 
-                let success = r#"{"status":"success","message":"Location updated successfully"}"#;
-                send_response(stream, 200, "application/json", success.as_bytes());
+        if index == 0 {
+            if let Some(name) = nmea::device_from_handshake(line) {
+                device = name;
+                continue;
+            }
+        }
+
+        // Sentenças não suportadas (GSV, GLL...) são apenas ignoradas
+        let sentence = match avila_geo::nmea::parse(line) {
+            Ok(sentence) => sentence,
+            Err(avila_geo::nmea::NmeaError::UnsupportedSentence(_)) => continue,
+            Err(_) => {
+                rejected += 1;
+                continue;
+            }
+        };
+        if let Some(fix) = accumulator.push(sentence) {
+            match state.ingest(nmea::to_location(&fix, &device, unix_now())) {
+                Ok(_) => fixes += 1,
+                Err(_) => dropped += 1,
+            }
+        }
+    }
+    if let Some(fix) = accumulator.finish() {
+        match state.ingest(nmea::to_location(&fix, &device, unix_now())) {
+            Ok(_) => fixes += 1,
+            Err(_) => dropped += 1,
+        }
+    }
+
+    println!(
+        "📡 NMEA connection from {} closed: {} fixes, {} rejected sentences, {} fixes dropped by quality filter",
+        device, fixes, rejected, dropped
+    );
+}
+
+/// Server-Sent Events: holds the connection open and pushes every new fix.
+/// `Last-Event-ID` (or `?last_event_id=`) replays the stored fixes after that id.
+fn stream_locations(mut stream: TcpStream, state: &Arc<AppState>, request: &Request) {
+    let device = request.query.get("device").cloned();
+    let matches = |location: &Location| device.as_ref().is_none_or(|d| &location.device_name == d);
+    let last_id = request
+        .headers
+        .get("last-event-id")
+        .or_else(|| request.query.get("last_event_id"))
+        .and_then(|v| v.trim().parse::<u64>().ok());
+
+    // Assinar e copiar o backlog sob o mesmo lock: sem lacunas nem duplicatas
+    let (receiver, backlog) = match state.history.lock() {
+        Ok(history) => {
+            let receiver = state.broadcaster.subscribe();
+            let backlog: Vec<Location> = match last_id {
+                Some(last) => history
+                    .locations
+                    .iter()
+                    .filter(|l| l.id > last && matches(l))
+                    .cloned()
+                    .collect(),
+                None => Vec::new(),
+            };
+            (receiver, backlog)
+        }
+        Err(_) => return send_error(&mut stream, 500, "Internal Server Error"),
+    };
+
+    // Cliente travado não deve segurar a thread para sempre
+    let _ = stream.set_write_timeout(Some(KEEPALIVE_INTERVAL * 2));
+    let header = "HTTP/1.1 200 OK\r\n\
+                  Content-Type: text/event-stream\r\n\
+                  Cache-Control: no-cache\r\n\
+                  Access-Control-Allow-Origin: *\r\n\
+                  Connection: keep-alive\r\n\r\n\
+                  retry: 3000\n\n";
+    if stream.write_all(header.as_bytes()).is_err() {
+        return;
+    }
+    println!("📺 Stream client connected ({} listening)", state.broadcaster.subscriber_count());
+
+    for location in &backlog {
+        if stream.write_all(stream::sse_event(location).as_bytes()).is_err() {
+            return;
+        }
+    }
+
+    loop {
+        let chunk = match receiver.recv_timeout(KEEPALIVE_INTERVAL) {
+            Ok(location) if matches(&location) => stream::sse_event(&location),
+            Ok(_) => continue,
+            // Comentário SSE mantém proxies e o navegador cientes da conexão
+            Err(RecvTimeoutError::Timeout) => ": keep-alive\n\n".to_string(),
+            Err(RecvTimeoutError::Disconnected) => return,
+        };
+        if stream.write_all(chunk.as_bytes()).and_then(|_| stream.flush()).is_err() {
+            return;
+        }
+    }
+}
+
+/// WebSocket session: `subscribe`/`unsubscribe` to devices, push fixes with
+/// `location`, and receive `location` events for the subscribed devices
+fn websocket_session(mut stream: TcpStream, state: &Arc<AppState>, request: &Request) {
+    let key = match request.headers.get("sec-websocket-key") {
+        Some(key) if websocket::is_upgrade(&request.headers) => key,
+        _ => return send_error(&mut stream, 400, "expected a WebSocket upgrade"),
+    };
+    if request.headers.get("sec-websocket-version").map(|v| v.trim()) != Some("13") {
+        return send_error(&mut stream, 400, "unsupported WebSocket version (expected 13)");
+    }
+
+    let handshake = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\r\n",
+        websocket::accept_key(key)
+    );
+    let writer = match stream.try_clone() {
+        Ok(writer) => Arc::new(Mutex::new(writer)),
+        Err(_) => return,
+    };
+    if stream.write_all(handshake.as_bytes()).is_err() {
+        return;
+    }
+    // Pings a cada KEEPALIVE_INTERVAL: sem nenhum frame em 3 intervalos, o cliente morreu
+    let _ = stream.set_read_timeout(Some(KEEPALIVE_INTERVAL * 3));
+    let _ = stream.set_write_timeout(Some(KEEPALIVE_INTERVAL * 2));
+
+    let subscription = Arc::new(Mutex::new(Subscription::default()));
+    let closed = Arc::new(AtomicBool::new(false));
+    let send = |writer: &Mutex<TcpStream>, opcode: Opcode, payload: &[u8]| match writer.lock() {
+        Ok(mut w) => websocket::write_frame(&mut *w, opcode, payload).is_ok(),
+        Err(_) => false,
+    };
+
+    // Envio dos eventos em outra thread; a leitura segue nesta
+    let receiver = state.broadcaster.subscribe();
+    {
+        let (writer, subscription, closed) = (Arc::clone(&writer), Arc::clone(&subscription), Arc::clone(&closed));
+        thread::spawn(move || loop {
+            let delivered = match receiver.recv_timeout(KEEPALIVE_INTERVAL) {
+                _ if closed.load(Ordering::Relaxed) => return,
+                Ok(location) => {
+                    let wanted = subscription.lock().is_ok_and(|s| s.matches(&location.device_name));
+                    !wanted || send(&writer, Opcode::Text, ws_location_event(&location).to_string().as_bytes())
+                }
+                Err(RecvTimeoutError::Timeout) => send(&writer, Opcode::Ping, b""),
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            if !delivered {
                 return;
             }
+        });
+    }
+
+    let mut reader = MessageReader::new();
+    loop {
+        match reader.next(&mut stream) {
+            Ok(Message::Text(text)) => {
+                let reply = ws_handle_message(state, &subscription, &text);
+                if !send(&writer, Opcode::Text, reply.to_string().as_bytes()) {
+                    break;
+                }
+            }
+            Ok(Message::Binary(_)) => {
+                if let Ok(mut w) = writer.lock() {
+                    let _ = websocket::write_close(&mut *w, websocket::CLOSE_UNSUPPORTED_DATA, "binary messages are not supported");
+                }
+                break;
+            }
+            Ok(Message::Ping(payload)) => {
+                if !send(&writer, Opcode::Pong, &payload) {
+                    break;
+                }
+            }
+            Ok(Message::Pong) => {}
+            Ok(Message::Close(code)) => {
+                // Ecoar o código recebido, como pede a RFC, exceto os reservados
+                if let Ok(mut w) = writer.lock() {
+                    let _ = websocket::write_close(&mut *w, websocket::echo_close_code(code), "");
+                }
+                break;
+            }
+            Err(e) => {
+                if let (Some(code), Ok(mut w)) = (e.close_code(), writer.lock()) {
+                    let _ = websocket::write_close(&mut *w, code, e.reason());
+                }
+                break;
+            }
         }
     }
 
-    send_response(stream, 400, "text/plain", b"Invalid JSON");
+    closed.store(true, Ordering::Relaxed);
+    let _ = stream.shutdown(std::net::Shutdown::Both);
+}
+
+/// Reply to one client text message
+fn ws_handle_message(state: &Arc<AppState>, subscription: &Mutex<Subscription>, text: &str) -> JsonValue {
+    let reply = |kind: &str, key: &str, value: JsonValue| {
+        let mut map = HashMap::new();
+        map.insert("type".to_string(), JsonValue::String(kind.to_string()));
+        map.insert(key.to_string(), value);
+        JsonValue::Object(map)
+    };
+    let error = |message: &str| reply("error", "message", JsonValue::String(message.to_string()));
+
+    let map = match avila_json::parse(text) {
+        Ok(JsonValue::Object(map)) => map,
+        _ => return error("expected a JSON object"),
+    };
+    // `devices` ausente = todos os dispositivos
+    let devices = map.get("devices").and_then(|v| v.as_array()).map(|list| {
+        list.iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect::<Vec<_>>()
+    });
+
+    match map.get("type").and_then(|v| v.as_str()) {
+        Some(kind @ ("subscribe" | "unsubscribe")) => match subscription.lock() {
+            Ok(mut subscription) => {
+                if kind == "subscribe" {
+                    subscription.subscribe(devices);
+                } else {
+                    subscription.unsubscribe(devices);
+                }
+                subscription.to_json()
+            }
+            Err(_) => error("internal error"),
+        },
+        Some("location") => match location_from_report(&map) {
+            Ok(location) => match state.ingest(location) {
+                Ok(stored) => reply("ack", "id", JsonValue::Number(stored.id as f64)),
+                Err(quality) => {
+                    let mut map = HashMap::new();
+                    map.insert("type".to_string(), JsonValue::String("error".to_string()));
+                    map.insert("quality".to_string(), JsonValue::String(quality.as_str().to_string()));
+                    map.insert(
+                        "message".to_string(),
+                        JsonValue::String("location rejected by quality filter".to_string()),
+                    );
+                    JsonValue::Object(map)
+                }
+            },
+            Err(ReportError::Missing) => error("location requires 'latitude' and 'longitude'"),
+            Err(ReportError::Invalid { field, reason }) => {
+                let mut map = HashMap::new();
+                map.insert("type".to_string(), JsonValue::String("error".to_string()));
+                map.insert("field".to_string(), JsonValue::String(field.to_string()));
+                map.insert("message".to_string(), JsonValue::String(reason));
+                JsonValue::Object(map)
+            }
+        },
+        _ => error("unknown message type"),
+    }
+}
+
+fn ws_location_event(location: &Location) -> JsonValue {
+    let mut map = HashMap::new();
+    map.insert("type".to_string(), JsonValue::String("location".to_string()));
+    map.insert("location".to_string(), location.to_json());
+    JsonValue::Object(map)
+}
+
+fn get_history(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let query = match HistoryQuery::from_params(&request.query) {
+        Ok(query) => query,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    // Polyline: uma string por dispositivo em vez de um objeto por ponto
+    let polyline_precision = match request.query.get("encoding").map(String::as_str) {
+        None | Some("json") => None,
+        Some("polyline") => match request.query.get("precision").map(String::as_str) {
+            None | Some("5") => Some(5),
+            Some("6") => Some(6),
+            Some(_) => return send_error(stream, 400, "invalid 'precision', expected 5 or 6"),
+        },
+        Some(_) => return send_error(stream, 400, "invalid 'encoding', expected json or polyline"),
+    };
+
+    if let Ok(history) = state.history.lock() {
+        let page = query.run(&history);
+        let mut map = HashMap::new();
+        if let Some(precision) = polyline_precision {
+            let tracks = export::polyline_tracks(&page.locations, precision);
+            map.insert("encoding".to_string(), JsonValue::String("polyline".to_string()));
+            map.insert("precision".to_string(), JsonValue::Number(precision as f64));
+            map.insert("tracks".to_string(), JsonValue::Array(tracks));
+        } else {
+            let locations_array: Vec<JsonValue> = page
+                .locations
+                .iter()
+                .map(|loc| loc.to_json())
+                .collect();
+            map.insert("locations".to_string(), JsonValue::Array(locations_array));
+        }
+        map.insert(
+            "next_cursor".to_string(),
+            page.next_cursor.map_or(JsonValue::Null, JsonValue::String),
+        );
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+fn list_devices(stream: &mut TcpStream, state: &Arc<AppState>) {
+    if let Ok(devices) = state.devices.lock() {
+        let devices_array: Vec<JsonValue> = devices
+            .list()
+            .iter()
+            .map(|device| device.to_json())
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert("devices".to_string(), JsonValue::Array(devices_array));
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+fn get_device_location(stream: &mut TcpStream, state: &Arc<AppState>, id: &str) {
+    if let Ok(devices) = state.devices.lock() {
+        if let Some(device) = devices.get(id) {
+            let json_str = device.location.to_json().to_string();
+            send_response(stream, 200, "application/json", json_str.as_bytes());
+        } else {
+            let error = r#"{"error":"Device not found"}"#;
+            send_response(stream, 404, "application/json", error.as_bytes());
+        }
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+/// `?model=` for distance figures, falling back to `GPS_DISTANCE_MODEL`
+fn distance_model_param(request: &Request, state: &AppState) -> Result<DistanceModel, String> {
+    match request.query.get("model").map(String::as_str) {
+        Some(value) => DistanceModel::parse(value)
+            .ok_or_else(|| "invalid 'model', expected spherical or ellipsoidal".to_string()),
+        None => Ok(state.distance_model),
+    }
+}
+
+/// History query for one device's track: `from`/`to` apply, order is always ascending
+fn device_track_query(request: &Request, id: &str) -> Result<HistoryQuery, String> {
+    let mut params = request.query.clone();
+    params.insert("device".to_string(), id.to_string());
+    params.remove("order");
+    HistoryQuery::from_params(&params)
+}
+
+/// Distance travelled by one device, optionally within `from`/`to`
+fn get_device_distance(stream: &mut TcpStream, state: &Arc<AppState>, id: &str, request: &Request) {
+    let (model, query) = match (distance_model_param(request, state), device_track_query(request, id)) {
+        (Ok(model), Ok(query)) => (model, query),
+        (Err(message), _) | (_, Err(message)) => return send_error(stream, 400, &message),
+    };
+
+    if let Ok(history) = state.history.lock() {
+        let track = query.filter(&history);
+        if track.is_empty() {
+            return send_error(stream, 404, "No locations for device");
+        }
+
+        let timestamp = |location: Option<&&Location>| {
+            location.map_or(JsonValue::Null, |l| JsonValue::String(l.timestamp.clone()))
+        };
+        let mut map = HashMap::new();
+        map.insert("device".to_string(), JsonValue::String(id.to_string()));
+        map.insert("model".to_string(), JsonValue::String(model.as_str().to_string()));
+        map.insert("distance_m".to_string(), JsonValue::Number(stats::track_distance(&track, model)));
+        map.insert("points".to_string(), JsonValue::Number(track.len() as f64));
+        map.insert("from".to_string(), timestamp(track.first()));
+        map.insert("to".to_string(), timestamp(track.last()));
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+/// Required finite number from the query string; the error names the parameter
+fn query_number(request: &Request, name: &str) -> Result<f64, String> {
+    match request.query.get(name).map(|v| v.trim()) {
+        None | Some("") => Err(format!("missing '{}'", name)),
+        Some(value) => value
+            .parse::<f64>()
+            .ok()
+            .filter(|v| v.is_finite())
+            .ok_or_else(|| format!("invalid '{}'", name)),
+    }
+}
+
+fn get_nearby(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let center = match (query_number(request, "lat"), query_number(request, "lon")) {
+        (Ok(lat), Ok(lon)) => match avila_geo::Coordinate::try_new(lat, lon) {
+            Ok(center) => center,
+            Err(e) => return send_error(stream, 400, &e.to_string()),
+        },
+        (Err(message), _) | (_, Err(message)) => return send_error(stream, 400, &message),
+    };
+    let radius = match query_number(request, "radius") {
+        Ok(radius) if radius > 0.0 => radius,
+        Ok(_) => return send_error(stream, 400, "invalid 'radius'"),
+        Err(message) => return send_error(stream, 400, &message),
+    };
+    let query = match HistoryQuery::from_params(&request.query) {
+        Ok(query) => query,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    if let Ok(history) = state.history.lock() {
+        let found: Vec<(&Location, f64)> = spatial::nearby(&history, &center, radius)
+            .into_iter()
+            .filter(|(location, _)| query.matches(location))
+            .collect();
+
+        // Resumo por dispositivo, na ordem da primeira passagem
+        let mut devices: Vec<(&str, usize, f64, &str, &str)> = Vec::new();
+        for (location, distance) in &found {
+            let timestamp = location.timestamp.as_str();
+            match devices.iter_mut().find(|d| d.0 == location.device_name) {
+                Some(device) => {
+                    device.1 += 1;
+                    device.2 = device.2.min(*distance);
+                    device.4 = timestamp;
+                }
+                None => devices.push((&location.device_name, 1, *distance, timestamp, timestamp)),
+            }
+        }
+        let devices_array: Vec<JsonValue> = devices
+            .into_iter()
+            .map(|(device, count, closest, first_seen, last_seen)| {
+                let mut map = HashMap::new();
+                map.insert("device".to_string(), JsonValue::String(device.to_string()));
+                map.insert("count".to_string(), JsonValue::Number(count as f64));
+                map.insert("closest_m".to_string(), JsonValue::Number(closest));
+                map.insert("first_seen".to_string(), JsonValue::String(first_seen.to_string()));
+                map.insert("last_seen".to_string(), JsonValue::String(last_seen.to_string()));
+                JsonValue::Object(map)
+            })
+            .collect();
+        let locations_array: Vec<JsonValue> = found
+            .iter()
+            .take(query.limit.unwrap_or(usize::MAX))
+            .map(|(location, distance)| {
+                let mut json = location.to_json();
+                if let JsonValue::Object(map) = &mut json {
+                    map.insert("distance_m".to_string(), JsonValue::Number(*distance));
+                }
+                json
+            })
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert("latitude".to_string(), JsonValue::Number(center.latitude));
+        map.insert("longitude".to_string(), JsonValue::Number(center.longitude));
+        map.insert("radius_m".to_string(), JsonValue::Number(radius));
+        map.insert("total".to_string(), JsonValue::Number(found.len() as f64));
+        map.insert("devices".to_string(), JsonValue::Array(devices_array));
+        map.insert("locations".to_string(), JsonValue::Array(locations_array));
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+fn get_clusters(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let precision = match request.query.get("precision").map(|v| v.trim().parse::<usize>()) {
+        None => 5,
+        Some(Ok(precision)) if (1..=spatial::INDEX_PRECISION).contains(&precision) => precision,
+        Some(_) => {
+            let message = format!("invalid 'precision', expected 1 to {}", spatial::INDEX_PRECISION);
+            return send_error(stream, 400, &message);
+        }
+    };
+    let bbox = match request.query.get("bbox") {
+        Some(value) => match query::BBox::parse(value) {
+            Some(bbox) => Some(bbox),
+            None => return send_error(stream, 400, "invalid 'bbox', expected minLon,minLat,maxLon,maxLat"),
+        },
+        None => None,
+    };
+
+    if let Ok(history) = state.history.lock() {
+        let clusters_array: Vec<JsonValue> = history
+            .index
+            .clusters(precision)
+            .into_iter()
+            .filter(|cluster| bbox.is_none_or(|b| b.contains(cluster.center.latitude, cluster.center.longitude)))
+            .map(|cluster| {
+                let mut map = HashMap::new();
+                map.insert("geohash".to_string(), JsonValue::String(cluster.geohash));
+                map.insert("count".to_string(), JsonValue::Number(cluster.count as f64));
+                map.insert("latitude".to_string(), JsonValue::Number(cluster.center.latitude));
+                map.insert("longitude".to_string(), JsonValue::Number(cluster.center.longitude));
+                JsonValue::Object(map)
+            })
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert("precision".to_string(), JsonValue::Number(precision as f64));
+        map.insert("clusters".to_string(), JsonValue::Array(clusters_array));
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+/// Positive integer query parameter with a default
+fn query_seconds(request: &Request, name: &str, default: u64) -> Result<u64, String> {
+    match request.query.get(name).map(|v| v.trim()) {
+        None | Some("") => Ok(default),
+        Some(value) => value.parse::<u64>().ok().filter(|v| *v > 0).ok_or_else(|| format!("invalid '{}'", name)),
+    }
+}
+
+fn get_device_trips(stream: &mut TcpStream, state: &Arc<AppState>, id: &str, request: &Request) {
+    let (model, query, max_gap, (radius, min_duration)) = match (
+        distance_model_param(request, state),
+        device_track_query(request, id),
+        query_seconds(request, "gap", 600),
+        stay_params(request),
+    ) {
+        (Ok(model), Ok(query), Ok(max_gap), Ok(stay)) => (model, query, max_gap, stay),
+        (Err(message), _, _, _) | (_, Err(message), _, _) | (_, _, Err(message), _) | (_, _, _, Err(message)) => {
+            return send_error(stream, 400, &message)
+        }
+    };
+
+    send_segments(stream, state, id, &query, "trips", |track| {
+        analysis::trips(track, max_gap, radius, min_duration)
+            .into_iter()
+            .map(|range| analysis::segment_json(&track[range], model))
+            .collect()
+    });
+}
+
+fn get_device_stays(stream: &mut TcpStream, state: &Arc<AppState>, id: &str, request: &Request) {
+    let (model, query, (radius, min_duration)) = match (
+        distance_model_param(request, state),
+        device_track_query(request, id),
+        stay_params(request),
+    ) {
+        (Ok(model), Ok(query), Ok(stay)) => (model, query, stay),
+        (Err(message), _, _) | (_, Err(message), _) | (_, _, Err(message)) => {
+            return send_error(stream, 400, &message)
+        }
+    };
+
+    send_segments(stream, state, id, &query, "stays", |track| {
+        analysis::stay_points(track, radius, min_duration)
+            .into_iter()
+            .map(|range| analysis::segment_json(&track[range], model))
+            .collect()
+    });
+}
+
+/// Stay point `radius` (meters, default 200) and `duration` (seconds, default 1800)
+fn stay_params(request: &Request) -> Result<(f64, u64), String> {
+    let radius = match request.query.get("radius") {
+        None => 200.0,
+        Some(_) => match query_number(request, "radius")? {
+            radius if radius > 0.0 => radius,
+            _ => return Err("invalid 'radius'".to_string()),
+        },
+    };
+    Ok((radius, query_seconds(request, "duration", 1800)?))
+}
+
+/// Run `segments` over the device's track (fixes flagged by the quality
+/// filter left out) and send them under `key`
+fn send_segments(
+    stream: &mut TcpStream,
+    state: &Arc<AppState>,
+    id: &str,
+    query: &HistoryQuery,
+    key: &str,
+    segments: impl Fn(&[&Location]) -> Vec<JsonValue>,
+) {
+    if let Ok(history) = state.history.lock() {
+        let track: Vec<&Location> = query
+            .filter(&history)
+            .into_iter()
+            .filter(|location| location.quality.is_none())
+            .collect();
+        if track.is_empty() {
+            return send_error(stream, 404, "No locations for device");
+        }
+
+        let mut map = HashMap::new();
+        map.insert("device".to_string(), JsonValue::String(id.to_string()));
+        map.insert(key.to_string(), JsonValue::Array(segments(&track)));
+        let json_str = JsonValue::Object(map).to_string();
+        send_response(stream, 200, "application/json", json_str.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+fn export_history(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let format = match request.query.get("format").map(String::as_str) {
+        Some(value) => match export::Format::parse(value) {
+            Some(format) => format,
+            None => return send_error(stream, 400, "invalid 'format', expected gpx, kml, geojson or csv"),
+        },
+        None => return send_error(stream, 400, "missing 'format'"),
+    };
+    let query = match HistoryQuery::from_params(&request.query) {
+        Ok(query) => query,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    if let Ok(history) = state.history.lock() {
+        let page = query.run(&history);
+        let body = export::export(format, &page.locations);
+        let disposition = format!("attachment; filename=\"location_history.{}\"", format.extension());
+        let mut headers = vec![("Content-Disposition", disposition.as_str())];
+        // Com `limit` o arquivo pode estar cortado: o cursor da próxima página vai no header
+        if let Some(cursor) = &page.next_cursor {
+            headers.push(("X-Next-Cursor", cursor));
+            headers.push(("Access-Control-Expose-Headers", "X-Next-Cursor"));
+        }
+        send_response_with_headers(stream, 200, format.content_type(), &headers, body.as_bytes());
+    } else {
+        send_response(stream, 500, "text/plain", b"Internal Server Error");
+    }
+}
+
+fn import_history(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    if request.body.trim().is_empty() {
+        return send_error(stream, 400, "empty body");
+    }
+    let format = match request.query.get("format") {
+        Some(value) => match export::Format::parse(value) {
+            Some(format) => format,
+            None => return send_error(stream, 400, "invalid 'format', expected gpx, kml, geojson or csv"),
+        },
+        None => import::detect_format(&request.body),
+    };
+    let default_timestamp = match request.query.get("timestamp") {
+        Some(value) => match value.parse() {
+            Ok(ts) => Some(ts),
+            Err(_) => return send_error(stream, 400, "invalid 'timestamp'"),
+        },
+        None => None,
+    };
+    let options = import::ImportOptions {
+        device: request.query.get("device").cloned(),
+        default_timestamp,
+    };
+
+    let (report, pruned) = match state.history.lock() {
+        Ok(mut history) => {
+            // Trilhas importadas não passam por state.ingest: são dados históricos, fora de ordem
+            // em relação aos fixes ao vivo, então ficam sem filtro de qualidade e sem Kalman
+            let mut report = history.import(format, &request.body, &options);
+            for location in &report.locations {
+                if let Err(e) = state.storage.append(location) {
+                    eprintln!("❌ Failed to persist location: {}", e);
+                }
+            }
+            state.retention.enforce_on_insert(&mut history);
+
+            // O limite de retenção pode descartar na hora parte do que foi importado
+            let parsed = report.locations.len();
+            report.locations.retain(|location| history.get(location.id).is_some());
+            let pruned = parsed - report.locations.len();
+            (report, pruned)
+        }
+        Err(_) => return send_response(stream, 500, "text/plain", b"Internal Server Error"),
+    };
+
+    if let Ok(mut devices) = state.devices.lock() {
+        for location in &report.locations {
+            devices.record(location);
+        }
+    }
+
+    let mut map = HashMap::new();
+    map.insert("imported".to_string(), JsonValue::Number(report.locations.len() as f64));
+    map.insert("pruned".to_string(), JsonValue::Number(pruned as f64));
+    map.insert(
+        "errors".to_string(),
+        JsonValue::Array(report.errors.iter().map(|e| e.to_json()).collect()),
+    );
+    let json_str = JsonValue::Object(map).to_string();
+    send_response(stream, 200, "application/json", json_str.as_bytes());
 }
 
-fn get_history(stream: &mut TcpStream, state: &Arc<AppState>) {
+fn get_retention(stream: &mut TcpStream, state: &Arc<AppState>) {
+    let status = match state.retention_status.lock() {
+        Ok(status) => status.clone(),
+        Err(_) => return send_response(stream, 500, "text/plain", b"Internal Server Error"),
+    };
+
     if let Ok(history) = state.history.lock() {
-        let json = history.to_json();
-        let json_str = json.to_string();
+        let mut map = HashMap::new();
+        map.insert("policy".to_string(), state.retention.to_json());
+        map.insert("status".to_string(), status.to_json(&history));
+        let json_str = JsonValue::Object(map).to_string();
         send_response(stream, 200, "application/json", json_str.as_bytes());
     } else {
         send_response(stream, 500, "text/plain", b"Internal Server Error");
@@ -270,34 +1464,267 @@ fn get_history(stream: &mut TcpStream, state: &Arc<AppState>) {
 
 fn clear_history(stream: &mut TcpStream, state: &Arc<AppState>) {
     if let Ok(mut history) = state.history.lock() {
-        history.locations.clear();
+        history.clear();
+        if let Err(e) = state.storage.log_clear() {
+            eprintln!("❌ Failed to persist clear: {}", e);
+        }
+    }
+
+    // Compactar já, para que os dados apagados saiam do disco
+    if let Err(e) = state.storage.compact(&state.history) {
+        eprintln!("❌ Compaction failed: {}", e);
     }
-    state.save_history();
 
     let success = r#"{"status":"success","message":"History cleared successfully"}"#;
     send_response(stream, 200, "application/json", success.as_bytes());
 }
 
+fn list_geofences(stream: &mut TcpStream, state: &Arc<AppState>) {
+    if let Ok(geofences) = state.geofences.lock() {
+        let list = geofences.list().into_iter().map(Geofence::to_json).collect();
+        let mut map = HashMap::new();
+        map.insert("geofences".to_string(), JsonValue::Array(list));
+        send_response(stream, 200, "application/json", JsonValue::Object(map).to_string().as_bytes());
+    } else {
+        send_error(stream, 500, "Internal Server Error");
+    }
+}
+
+fn create_geofence(stream: &mut TcpStream, state: &Arc<AppState>, body: &str) {
+    let fence = match avila_json::parse(body) {
+        Ok(json) => Geofence::from_json(&json),
+        Err(_) => Err("invalid JSON".to_string()),
+    };
+    let fence = match fence {
+        Ok(fence) => fence,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    let created = match state.geofences.lock() {
+        Ok(mut geofences) => geofences.create(fence),
+        Err(_) => return send_error(stream, 500, "Internal Server Error"),
+    };
+    match created {
+        Ok(fence) => send_response(stream, 201, "application/json", fence.to_json().to_string().as_bytes()),
+        Err(e) => {
+            eprintln!("❌ Failed to save geofences: {}", e);
+            send_error(stream, 500, "failed to save geofence");
+        }
+    }
+}
+
+/// `GET`, `PUT` and `DELETE /api/geofences/{id}`
+fn geofence_by_id(stream: &mut TcpStream, state: &Arc<AppState>, method: &str, id: &str, body: &str) {
+    let id: u64 = match id.parse() {
+        Ok(id) => id,
+        Err(_) => return send_error(stream, 404, "geofence not found"),
+    };
+    let replacement = match method {
+        "PUT" => {
+            let fence = match avila_json::parse(body) {
+                Ok(json) => Geofence::from_json(&json),
+                Err(_) => Err("invalid JSON".to_string()),
+            };
+            match fence {
+                Ok(fence) => Some(fence),
+                Err(message) => return send_error(stream, 400, &message),
+            }
+        }
+        "GET" | "DELETE" => None,
+        _ => return send_response(stream, 404, "text/plain", b"Not Found"),
+    };
+
+    let mut geofences = match state.geofences.lock() {
+        Ok(geofences) => geofences,
+        Err(_) => return send_error(stream, 500, "Internal Server Error"),
+    };
+    let result = match (method, replacement) {
+        ("PUT", Some(fence)) => geofences.update(id, fence).map(|f| f.map(|f| f.to_json())),
+        ("DELETE", _) => geofences.delete(id).map(|deleted| {
+            deleted.then(|| {
+                let mut map = HashMap::new();
+                map.insert("status".to_string(), JsonValue::String("deleted".to_string()));
+                JsonValue::Object(map)
+            })
+        }),
+        _ => Ok(geofences.get(id).map(Geofence::to_json)),
+    };
+
+    match result {
+        Ok(Some(json)) => send_response(stream, 200, "application/json", json.to_string().as_bytes()),
+        Ok(None) => send_error(stream, 404, "geofence not found"),
+        Err(e) => {
+            eprintln!("❌ Failed to save geofences: {}", e);
+            send_error(stream, 500, "failed to save geofences");
+        }
+    }
+}
+
+fn list_events(stream: &mut TcpStream, state: &Arc<AppState>, request: &Request) {
+    let query = match EventQuery::from_params(&request.query) {
+        Ok(query) => query,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    if let Ok(geofences) = state.geofences.lock() {
+        let events = geofences.events(&query).into_iter().map(|e| e.to_json()).collect();
+        let mut map = HashMap::new();
+        map.insert("events".to_string(), JsonValue::Array(events));
+        send_response(stream, 200, "application/json", JsonValue::Object(map).to_string().as_bytes());
+    } else {
+        send_error(stream, 500, "Internal Server Error");
+    }
+}
+
+fn list_webhooks(stream: &mut TcpStream, state: &Arc<AppState>) {
+    if let Ok(webhooks) = state.webhooks.lock() {
+        let list = webhooks.list().into_iter().map(Webhook::to_json).collect();
+        let mut map = HashMap::new();
+        map.insert("webhooks".to_string(), JsonValue::Array(list));
+        map.insert("pending".to_string(), JsonValue::Number(webhooks.pending() as f64));
+        map.insert(
+            "dead_letters".to_string(),
+            JsonValue::Number(webhooks.dead_letters().count() as f64),
+        );
+        send_response(stream, 200, "application/json", JsonValue::Object(map).to_string().as_bytes());
+    } else {
+        send_error(stream, 500, "Internal Server Error");
+    }
+}
+
+fn create_webhook(stream: &mut TcpStream, state: &Arc<AppState>, body: &str) {
+    let hook = match avila_json::parse(body) {
+        Ok(json) => Webhook::from_json(&json),
+        Err(_) => Err("invalid JSON".to_string()),
+    };
+    let hook = match hook {
+        Ok(hook) => hook,
+        Err(message) => return send_error(stream, 400, &message),
+    };
+
+    let created = match state.webhooks.lock() {
+        Ok(mut webhooks) => webhooks.create(hook),
+        Err(_) => return send_error(stream, 500, "Internal Server Error"),
+    };
+    match created {
+        Ok(hook) => send_response(stream, 201, "application/json", hook.to_json().to_string().as_bytes()),
+        Err(e) => {
+            eprintln!("❌ Failed to save webhooks: {}", e);
+            send_error(stream, 500, "failed to save webhook");
+        }
+    }
+}
+
+fn delete_webhook(stream: &mut TcpStream, state: &Arc<AppState>, id: &str) {
+    let id: u64 = match id.parse() {
+        Ok(id) => id,
+        Err(_) => return send_error(stream, 404, "webhook not found"),
+    };
+    let deleted = match state.webhooks.lock() {
+        Ok(mut webhooks) => webhooks.delete(id),
+        Err(_) => return send_error(stream, 500, "Internal Server Error"),
+    };
+    match deleted {
+        Ok(true) => send_response(stream, 200, "application/json", br#"{"status":"deleted"}"#),
+        Ok(false) => send_error(stream, 404, "webhook not found"),
+        Err(e) => {
+            eprintln!("❌ Failed to save webhooks: {}", e);
+            send_error(stream, 500, "failed to save webhooks");
+        }
+    }
+}
+
+fn list_dead_letters(stream: &mut TcpStream, state: &Arc<AppState>) {
+    if let Ok(webhooks) = state.webhooks.lock() {
+        let list = webhooks.dead_letters().map(|d| d.to_json()).collect();
+        let mut map = HashMap::new();
+        map.insert("dead_letters".to_string(), JsonValue::Array(list));
+        send_response(stream, 200, "application/json", JsonValue::Object(map).to_string().as_bytes());
+    } else {
+        send_error(stream, 500, "Internal Server Error");
+    }
+}
+
+fn retry_dead_letter(stream: &mut TcpStream, state: &Arc<AppState>, id: &str) {
+    let id: u64 = match id.parse() {
+        Ok(id) => id,
+        Err(_) => return send_error(stream, 404, "dead letter not found"),
+    };
+    let requeued = match state.webhooks.lock() {
+        Ok(mut webhooks) => webhooks.retry_dead_letter(id, unix_now()),
+        Err(_) => return send_error(stream, 500, "Internal Server Error"),
+    };
+    match requeued {
+        Ok(true) => send_response(stream, 200, "application/json", br#"{"status":"queued"}"#),
+        Ok(false) => send_error(stream, 404, "dead letter not found (or its webhook was deleted)"),
+        Err(e) => {
+            eprintln!("❌ Failed to save webhooks: {}", e);
+            send_error(stream, 500, "failed to save webhooks");
+        }
+    }
+}
+
+fn send_error(stream: &mut TcpStream, status: u16, message: &str) {
+    let mut map = HashMap::new();
+    map.insert("error".to_string(), JsonValue::String(message.to_string()));
+    let json_str = JsonValue::Object(map).to_string();
+    send_response(stream, status, "application/json", json_str.as_bytes());
+}
+
+/// 422 for a fix dropped by the quality filter: `{"error":...,"quality":"duplicate"}`
+fn send_rejected(stream: &mut TcpStream, quality: Quality) {
+    let mut map = HashMap::new();
+    map.insert("error".to_string(), JsonValue::String("location rejected by quality filter".to_string()));
+    map.insert("quality".to_string(), JsonValue::String(quality.as_str().to_string()));
+    let json_str = JsonValue::Object(map).to_string();
+    send_response(stream, 422, "application/json", json_str.as_bytes());
+}
+
+/// 422 naming the offending field: `{"error":...,"field":"latitude"}`
+fn send_invalid_field(stream: &mut TcpStream, field: &str, reason: &str) {
+    let mut map = HashMap::new();
+    map.insert("error".to_string(), JsonValue::String(reason.to_string()));
+    map.insert("field".to_string(), JsonValue::String(field.to_string()));
+    let json_str = JsonValue::Object(map).to_string();
+    send_response(stream, 422, "application/json", json_str.as_bytes());
+}
+
 fn send_response(stream: &mut TcpStream, status: u16, content_type: &str, body: &[u8]) {
+    send_response_with_headers(stream, status, content_type, &[], body);
+}
+
+fn send_response_with_headers(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) {
     let status_text = match status {
         200 => "OK",
+        201 => "Created",
         400 => "Bad Request",
         404 => "Not Found",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
         500 => "Internal Server Error",
         _ => "Unknown",
     };
 
-    let response = format!(
+    let mut response = format!(
         "HTTP/1.1 {} {}\r\n\
          Content-Type: {}\r\n\
          Content-Length: {}\r\n\
          Access-Control-Allow-Origin: *\r\n\
-         Access-Control-Allow-Methods: GET, POST, DELETE, OPTIONS\r\n\
+         Access-Control-Allow-Methods: GET, POST, PUT, DELETE, OPTIONS\r\n\
          Access-Control-Allow-Headers: Content-Type\r\n\
-         Connection: close\r\n\
-         \r\n",
+         Connection: close\r\n",
         status, status_text, content_type, body.len()
     );
+    for (name, value) in headers {
+        response.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    response.push_str("\r\n");
 
     let _ = stream.write_all(response.as_bytes());
     let _ = stream.write_all(body);
@@ -311,8 +1738,57 @@ fn main() -> std::io::Result<()> {
     println!("🚀 Using Arxis Ecosystem");
     println!("----------------------------------------");
 
+    let config = Config::from_env();
     let listener = TcpListener::bind("0.0.0.0:8080")?;
-    let state = Arc::new(AppState::new());
+    let state = Arc::new(AppState::new(&config)?);
+
+    // Retenção: limpeza periódica em background
+    {
+        let state = Arc::clone(&state);
+        let interval = config.retention_interval;
+        thread::spawn(move || loop {
+            state.enforce_retention();
+            thread::sleep(interval);
+        });
+    }
+
+    // Manutenção do armazenamento: fsync periódico e compactação
+    {
+        let state = Arc::clone(&state);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_millis(200));
+            if let Err(e) = state.storage.maintain(&state.history) {
+                eprintln!("❌ Storage maintenance failed: {}", e);
+            }
+        });
+    }
+
+    // Listener NMEA 0183 bruto (uma thread por conexão)
+    if let Some(addr) = &config.nmea_addr {
+        let nmea_listener = TcpListener::bind(addr)?;
+        println!("📡 NMEA 0183 listener on {}", addr);
+        let state = Arc::clone(&state);
+        thread::spawn(move || {
+            for stream in nmea_listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let state = Arc::clone(&state);
+                        thread::spawn(move || handle_nmea_client(stream, state));
+                    }
+                    Err(e) => eprintln!("❌ NMEA connection error: {}", e),
+                }
+            }
+        });
+    }
+
+    // Entrega de webhooks: a cada segundo, um worker por webhook com entregas vencidas
+    {
+        let state = Arc::clone(&state);
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(1));
+            webhook::run_due(&state.webhooks, unix_now());
+        });
+    }
 
     println!("✅ Server ready! Open http://localhost:8080 in your browser");
 
@@ -332,3 +1808,93 @@ fn main() -> std::io::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quality::Mode;
+    use std::fs;
+    use std::io::Read;
+
+    fn state(name: &str, configure: impl FnOnce(&mut Config)) -> Arc<AppState> {
+        let dir = std::env::temp_dir().join(format!("gps-main-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let path = |file: &str| dir.join(file).to_string_lossy().into_owned();
+
+        let mut config = Config::from_env();
+        config.history_file = path("history.json");
+        config.geofence_file = path("geofences.json");
+        config.webhook_file = path("webhooks.json");
+        config.kalman_noise = None;
+        config.retention = RetentionPolicy::default();
+        config.quality.max_accuracy = Some(500.0);
+        configure(&mut config);
+        Arc::new(AppState::new(&config).unwrap())
+    }
+
+    /// Send one request through `handle_client` and return (status, body)
+    fn call(state: &Arc<AppState>, method: &str, path: &str, body: &str) -> (u16, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        write!(client, "{} {} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        handle_client(server, Arc::clone(state));
+
+        let mut response = String::new();
+        client.read_to_string(&mut response).unwrap();
+        let status = response[9..12].parse().unwrap();
+        let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+        (status, body)
+    }
+
+    fn report(accuracy: f64) -> String {
+        format!(r#"{{"latitude":25.1972,"longitude":55.2744,"accuracy":{},"device_name":"phone"}}"#, accuracy)
+    }
+
+    #[test]
+    fn test_quality_flag_mode_stores_marked_fix() {
+        let state = state("flag", |config| config.quality.mode = Mode::Flag);
+        assert_eq!(call(&state, "POST", "/api/location", &report(10.0)).0, 200);
+
+        let (status, body) = call(&state, "POST", "/api/location", &report(900.0));
+        assert_eq!(status, 200);
+        let json = avila_json::parse(&body).unwrap();
+        assert_eq!(json.as_object().unwrap()["quality"].as_str(), Some("low_accuracy"));
+
+        // Guardado no histórico, mas a posição do dispositivo continua a do fix bom
+        assert_eq!(state.history.lock().unwrap().locations.len(), 2);
+        let devices = state.devices.lock().unwrap();
+        assert_eq!(devices.latest().unwrap().location.accuracy, Some(10.0));
+    }
+
+    #[test]
+    fn test_quality_reject_mode_answers_422() {
+        let state = state("reject", |config| config.quality.mode = Mode::Reject);
+        let (status, body) = call(&state, "POST", "/api/location", &report(900.0));
+        assert_eq!(status, 422);
+        let json = avila_json::parse(&body).unwrap();
+        assert_eq!(json.as_object().unwrap()["quality"].as_str(), Some("low_accuracy"));
+        assert!(state.history.lock().unwrap().locations.is_empty());
+
+        let (status, body) = call(&state, "POST", "/api/location", &report(10.0));
+        assert_eq!(status, 200);
+        assert!(!body.contains("quality"));
+    }
+
+    #[test]
+    fn test_import_reports_fixes_pruned_by_retention() {
+        let state = state("import", |config| config.retention.global.max_entries = Some(2));
+        let csv = "device_name,timestamp,latitude,longitude\nold,100,1,2\nnew,200,1.1,2\nnew,300,1.2,2\n";
+        let (status, body) = call(&state, "POST", "/api/history/import?format=csv", csv);
+        assert_eq!(status, 200);
+
+        let json = avila_json::parse(&body).unwrap();
+        let json = json.as_object().unwrap();
+        assert_eq!(json["imported"].as_f64(), Some(2.0));
+        assert_eq!(json["pruned"].as_f64(), Some(1.0));
+        let devices = state.devices.lock().unwrap();
+        let ids: Vec<String> = devices.list().into_iter().map(|device| device.id.clone()).collect();
+        assert_eq!(ids, vec!["new".to_string()]);
+    }
+}
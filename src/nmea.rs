@@ -0,0 +1,72 @@
+// Ingestão de streams NMEA 0183 brutos via TCP
+// Uma conexão = um dispositivo; linha opcional `DEVICE <nome>` antes das sentenças
+
+use crate::Location;
+use avila_geo::nmea::Fix;
+
+/// Knots to meters per second
+pub const KNOTS_TO_MS: f64 = 0.514_444;
+
+/// Typical user equivalent range error, used to turn HDOP into meters
+pub(crate) const UERE_METERS: f64 = 5.0;
+
+/// Device name from a `DEVICE <name>` handshake line
+pub fn device_from_handshake(line: &str) -> Option<String> {
+    let (keyword, name) = line.trim().split_once(char::is_whitespace)?;
+    let name = name.trim();
+    if keyword.eq_ignore_ascii_case("DEVICE") && !name.is_empty() {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+/// Fixes without a date (GGA-only receivers) are stamped with `now`
+pub fn to_location(fix: &Fix, device: &str, now: u64) -> Location {
+    Location {
+        id: 0,
+        latitude: fix.coordinate.latitude,
+        longitude: fix.coordinate.longitude,
+        accuracy: fix.hdop.map(|hdop| hdop * UERE_METERS),
+        timestamp: fix.timestamp.unwrap_or(now).to_string(),
+        device_name: device.to_string(),
+        altitude: fix.altitude,
+        speed: fix.speed_knots.map(|knots| knots * KNOTS_TO_MS),
+        course: fix.course,
+        battery: None,
+        filtered: None,
+        quality: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use avila_geo::nmea::{parse, FixAccumulator};
+
+    #[test]
+    fn test_handshake() {
+        assert_eq!(device_from_handshake("DEVICE truck 7\r\n"), Some("truck 7".to_string()));
+        assert_eq!(device_from_handshake("device boat"), Some("boat".to_string()));
+        assert_eq!(device_from_handshake("DEVICE "), None);
+        assert_eq!(device_from_handshake("$GPRMC,..."), None);
+    }
+
+    #[test]
+    fn test_fix_to_location() {
+        // Receptor só com GGA: o fix sai sem data no fim do ciclo
+        let mut acc = FixAccumulator::new();
+        assert!(acc
+            .push(parse("$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47").unwrap())
+            .is_none());
+        assert!(acc.push(parse("$GPVTG,054.7,T,034.4,M,005.5,N,010.2,K*48").unwrap()).is_none());
+
+        let fix = acc.finish().unwrap();
+        let location = to_location(&fix, "boat", 42);
+        assert_eq!(location.timestamp, "42");
+        assert_eq!(location.altitude, Some(545.4));
+        assert!((location.accuracy.unwrap() - 4.5).abs() < 1e-9);
+        assert!((location.speed.unwrap() - 5.5 * KNOTS_TO_MS).abs() < 1e-9);
+        assert_eq!(location.course, Some(54.7));
+    }
+}
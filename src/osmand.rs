@@ -0,0 +1,125 @@
+// Protocolo OsmAnd / Traccar Client
+// GET ou POST /?id=...&lat=...&lon=...&timestamp=...&speed=...
+
+use crate::nmea::{KNOTS_TO_MS, UERE_METERS};
+use crate::time::parse_rfc3339;
+use crate::{DecodeError, Location};
+use std::collections::HashMap;
+
+/// True when the parameters look like an OsmAnd/Traccar report
+pub fn is_report(params: &HashMap<String, String>) -> bool {
+    (params.contains_key("id") || params.contains_key("deviceid"))
+        && (params.contains_key("lat") || params.contains_key("location"))
+}
+
+/// Decode a report. `speed` arrives in knots, as Traccar Client sends it.
+pub fn to_location(params: &HashMap<String, String>, now: u64) -> Result<Location, DecodeError> {
+    let get = |name: &str| params.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+    let number = |name: &str| -> Result<Option<f64>, String> {
+        match get(name) {
+            Some(v) => v.parse().map(Some).map_err(|_| format!("invalid '{}'", name)),
+            None => Ok(None),
+        }
+    };
+
+    let device = get("id").or_else(|| get("deviceid")).ok_or("missing 'id'")?;
+
+    let (latitude, longitude) = match get("location") {
+        // Formato alternativo: location=lat,lon
+        Some(pair) => {
+            let (lat, lon) = pair.split_once(',').ok_or("invalid 'location'")?;
+            (
+                lat.trim().parse().map_err(|_| "invalid 'location'")?,
+                lon.trim().parse().map_err(|_| "invalid 'location'")?,
+            )
+        }
+        None => (
+            number("lat")?.ok_or("missing 'lat'")?,
+            number("lon")?.ok_or("missing 'lon'")?,
+        ),
+    };
+    // "NaN" e "inf" passam pelo parse de f64
+    avila_geo::Coordinate::try_new(latitude, longitude)?;
+
+    let timestamp = match get("timestamp") {
+        Some(value) => parse_timestamp(value).ok_or("invalid 'timestamp'")?,
+        None => now,
+    };
+
+    Ok(Location {
+        id: 0,
+        latitude,
+        longitude,
+        // HDOP não tem unidade; vira metros como nas sentenças NMEA
+        accuracy: number("accuracy")?.or(number("hdop")?.map(|hdop| hdop * UERE_METERS)),
+        timestamp: timestamp.to_string(),
+        device_name: device.to_string(),
+        altitude: number("altitude")?,
+        speed: number("speed")?.map(|knots| knots * KNOTS_TO_MS),
+        course: number("bearing")?.or(number("heading")?),
+        battery: number("batt")?.or(number("battery")?),
+        filtered: None,
+        quality: None,
+    })
+}
+
+/// Seconds, milliseconds or an ISO 8601 date-time
+fn parse_timestamp(value: &str) -> Option<u64> {
+    match value.parse::<f64>() {
+        Ok(n) if n >= 1e11 => Some((n / 1000.0) as u64),
+        Ok(n) if n >= 0.0 => Some(n as u64),
+        Ok(_) => None,
+        Err(_) => parse_rfc3339(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http::parse_query;
+    use avila_geo::CoordinateError;
+
+    #[test]
+    fn test_traccar_report() {
+        let params = parse_query(
+            "id=123456&lat=25.1972&lon=55.2744&timestamp=1700000000&speed=10&bearing=90.5&altitude=12&accuracy=8&batt=77",
+        );
+        assert!(is_report(&params));
+
+        let location = to_location(&params, 0).unwrap();
+        assert_eq!(location.device_name, "123456");
+        assert_eq!(location.timestamp, "1700000000");
+        assert!((location.speed.unwrap() - 5.14444).abs() < 1e-6);
+        assert_eq!(location.course, Some(90.5));
+        assert_eq!(location.battery, Some(77.0));
+    }
+
+    #[test]
+    fn test_alternative_fields() {
+        let params = parse_query("deviceid=truck&location=-23.5%2C-46.6&timestamp=2023-11-14T22%3A13%3A20Z");
+        let location = to_location(&params, 0).unwrap();
+        assert_eq!(location.latitude, -23.5);
+        assert_eq!(location.timestamp, "1700000000");
+
+        let millis = parse_query("id=x&lat=1&lon=2&timestamp=1700000000123");
+        assert_eq!(to_location(&millis, 0).unwrap().timestamp, "1700000000");
+
+        let hdop = parse_query("id=x&lat=1&lon=2&hdop=1.5");
+        assert_eq!(to_location(&hdop, 0).unwrap().accuracy, Some(7.5));
+        let both = parse_query("id=x&lat=1&lon=2&hdop=1.5&accuracy=4");
+        assert_eq!(to_location(&both, 0).unwrap().accuracy, Some(4.0));
+    }
+
+    #[test]
+    fn test_rejects_bad_values() {
+        assert!(!is_report(&parse_query("lat=1&lon=2")));
+        let error = |query: &str| to_location(&parse_query(query), 0).unwrap_err();
+        assert_eq!(error("id=x&lat=abc&lon=2"), DecodeError::from("invalid 'lat'"));
+        assert_eq!(error("id=x&lat=1"), DecodeError::from("missing 'lon'"));
+        assert_eq!(error("id=x&lat=NaN&lon=2"), DecodeError::Coordinate(CoordinateError::NotFinite { field: "latitude" }));
+        assert_eq!(
+            error("id=x&location=1,200"),
+            DecodeError::Coordinate(CoordinateError::OutOfRange { field: "longitude", value: 200.0 })
+        );
+    }
+}
@@ -0,0 +1,177 @@
+// Compatibilidade com o app OwnTracks (modo HTTP)
+// https://owntracks.org/booklet/tech/json/
+
+use crate::devices::DeviceRegistry;
+use crate::{DecodeError, Location};
+use avila_json::JsonValue;
+use std::collections::HashMap;
+
+/// Device key for a report: `user/device` from the `X-Limit-U`/`X-Limit-D`
+/// headers the app sends, else the payload `topic`, else the tracker id
+pub fn device_id(payload: &JsonValue, headers: &HashMap<String, String>) -> String {
+    if let (Some(user), Some(device)) = (headers.get("x-limit-u"), headers.get("x-limit-d")) {
+        return format!("{}/{}", user, device);
+    }
+
+    let field = |name: &str| {
+        payload
+            .as_object()
+            .and_then(|map| map.get(name))
+            .and_then(|v| v.as_str())
+            .map(str::to_string)
+    };
+
+    if let Some(topic) = field("topic") {
+        return topic.strip_prefix("owntracks/").unwrap_or(&topic).to_string();
+    }
+    if let Some(user) = headers.get("x-limit-u") {
+        return user.clone();
+    }
+    field("tid").unwrap_or_else(|| "OwnTracks".to_string())
+}
+
+/// Map a `_type: location` payload to a `Location`. Other message types yield `Ok(None)`.
+pub fn to_location(payload: &JsonValue, device: &str, now: u64) -> Result<Option<Location>, DecodeError> {
+    let map = payload.as_object().ok_or("expected a JSON object")?;
+
+    if map.get("_type").and_then(|v| v.as_str()) != Some("location") {
+        return Ok(None);
+    }
+
+    let number = |name: &str| map.get(name).and_then(|v| v.as_f64());
+    let latitude = number("lat").ok_or("missing 'lat'")?;
+    let longitude = number("lon").ok_or("missing 'lon'")?;
+    avila_geo::Coordinate::try_new(latitude, longitude)?;
+    let timestamp = number("tst").filter(|t| *t >= 0.0).map_or(now, |t| t as u64);
+
+    Ok(Some(Location {
+        id: 0,
+        latitude,
+        longitude,
+        accuracy: number("acc"),
+        timestamp: timestamp.to_string(),
+        device_name: device.to_string(),
+        altitude: number("alt"),
+        // OwnTracks envia km/h
+        speed: number("vel").map(|kmh| kmh / 3.6),
+        course: number("cog"),
+        battery: number("batt"),
+        filtered: None,
+        quality: None,
+    }))
+}
+
+/// Latest position of every other device, as the array OwnTracks expects in reply
+pub fn friends(devices: &DeviceRegistry, except: &str) -> JsonValue {
+    let friends = devices
+        .list()
+        .into_iter()
+        .filter(|device| device.id != except)
+        .map(|device| {
+            let location = &device.location;
+            let mut map = HashMap::new();
+            map.insert("_type".to_string(), JsonValue::String("location".to_string()));
+            map.insert("topic".to_string(), JsonValue::String(format!("owntracks/{}", device.id)));
+            map.insert("tid".to_string(), JsonValue::String(tracker_id(&device.id)));
+            map.insert("lat".to_string(), JsonValue::Number(location.latitude));
+            map.insert("lon".to_string(), JsonValue::Number(location.longitude));
+            map.insert(
+                "tst".to_string(),
+                JsonValue::Number(location.epoch_secs().unwrap_or(0) as f64),
+            );
+            for (key, value) in [
+                ("acc", location.accuracy),
+                ("alt", location.altitude),
+                ("vel", location.speed.map(|ms| ms * 3.6)),
+                ("cog", location.course),
+                ("batt", location.battery),
+            ] {
+                if let Some(value) = value {
+                    map.insert(key.to_string(), JsonValue::Number(value.round()));
+                }
+            }
+            JsonValue::Object(map)
+        })
+        .collect();
+
+    JsonValue::Array(friends)
+}
+
+/// Two-character label shown on the map, taken from the last path segment
+fn tracker_id(device: &str) -> String {
+    let name = device.rsplit('/').next().unwrap_or(device);
+    let tid: String = name.chars().filter(|c| c.is_alphanumeric()).take(2).collect();
+    if tid.is_empty() {
+        "??".to_string()
+    } else {
+        tid.to_uppercase()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(json: &str) -> JsonValue {
+        avila_json::parse(json).unwrap()
+    }
+
+    #[test]
+    fn test_location_payload() {
+        let p = payload(
+            r#"{"_type":"location","lat":25.1972,"lon":55.2744,"acc":12,"alt":40,"vel":36,"cog":270,"batt":81,"tst":1700000000,"tid":"jd"}"#,
+        );
+        let location = to_location(&p, "jane/phone", 0).unwrap().unwrap();
+
+        assert_eq!(location.timestamp, "1700000000");
+        assert_eq!(location.accuracy, Some(12.0));
+        assert_eq!(location.speed, Some(10.0));
+        assert_eq!(location.battery, Some(81.0));
+        assert_eq!(location.device_name, "jane/phone");
+    }
+
+    #[test]
+    fn test_other_types_are_ignored() {
+        let p = payload(r#"{"_type":"transition","event":"enter"}"#);
+        assert!(to_location(&p, "x", 0).unwrap().is_none());
+        assert!(to_location(&payload(r#"{"_type":"location","lon":1}"#), "x", 0).is_err());
+        assert_eq!(
+            to_location(&payload(r#"{"_type":"location","lat":95,"lon":1}"#), "x", 0).unwrap_err(),
+            DecodeError::Coordinate(avila_geo::CoordinateError::OutOfRange { field: "latitude", value: 95.0 })
+        );
+    }
+
+    #[test]
+    fn test_device_id_resolution() {
+        let p = payload(r#"{"_type":"location","tid":"jd","topic":"owntracks/jane/phone"}"#);
+        let mut headers = HashMap::new();
+        assert_eq!(device_id(&p, &headers), "jane/phone");
+
+        headers.insert("x-limit-u".to_string(), "bob".to_string());
+        headers.insert("x-limit-d".to_string(), "tablet".to_string());
+        assert_eq!(device_id(&p, &headers), "bob/tablet");
+
+        assert_eq!(device_id(&payload(r#"{"tid":"jd"}"#), &HashMap::new()), "jd");
+    }
+
+    #[test]
+    fn test_friends_excludes_reporter() {
+        let mut devices = DeviceRegistry::new();
+        for name in ["jane/phone", "bob/tablet"] {
+            devices.record(&Location {
+                latitude: 1.0,
+                longitude: 2.0,
+                timestamp: "100".to_string(),
+                device_name: name.to_string(),
+                ..Default::default()
+            });
+        }
+
+        let reply = friends(&devices, "jane/phone");
+        let list = reply.as_array().unwrap();
+        assert_eq!(list.len(), 1);
+        let friend = list[0].as_object().unwrap();
+        assert_eq!(friend["topic"].as_str(), Some("owntracks/bob/tablet"));
+        assert_eq!(friend["tid"].as_str(), Some("TA"));
+    }
+}
@@ -0,0 +1,214 @@
+// Filtro de qualidade na ingestão: saltos impossíveis, precisão ruim e duplicatas
+// Em modo `flag` o fix é guardado com o motivo em `quality`; em `reject` é descartado
+
+use crate::Location;
+use avila_geo::Coordinate;
+use std::collections::HashMap;
+
+/// Why a fix is suspect
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Quality {
+    /// Reaching it from the last good fix needs more than the speed limit
+    ImplausibleSpeed,
+    /// `accuracy` is worse than the limit
+    LowAccuracy,
+    /// Same device, timestamp and position as the previous fix
+    Duplicate,
+}
+
+impl Quality {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "implausible_speed" => Some(Quality::ImplausibleSpeed),
+            "low_accuracy" => Some(Quality::LowAccuracy),
+            "duplicate" => Some(Quality::Duplicate),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Quality::ImplausibleSpeed => "implausible_speed",
+            Quality::LowAccuracy => "low_accuracy",
+            Quality::Duplicate => "duplicate",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Off,
+    /// Store suspect fixes with their `quality`
+    Flag,
+    /// Drop suspect fixes
+    Reject,
+}
+
+impl Mode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "off" => Some(Mode::Off),
+            "flag" => Some(Mode::Flag),
+            "reject" => Some(Mode::Reject),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityPolicy {
+    pub mode: Mode,
+    /// Meters per second; `None` disables the check
+    pub max_speed: Option<f64>,
+    /// Meters; `None` disables the check
+    pub max_accuracy: Option<f64>,
+}
+
+/// What the filter remembers of a fix
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Point {
+    timestamp: Option<u64>,
+    coordinate: Coordinate,
+    accuracy: f64,
+}
+
+impl Point {
+    fn of(location: &Location) -> Self {
+        Point {
+            timestamp: location.epoch_secs(),
+            coordinate: Coordinate::new(location.latitude, location.longitude),
+            accuracy: location.accuracy.unwrap_or(0.0),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct DeviceTrack {
+    last: Option<Point>,
+    last_good: Option<Point>,
+    /// Fix rejected for speed; confirmed as a real relocation if the next one agrees with it
+    jump: Option<Point>,
+}
+
+#[derive(Debug)]
+pub struct QualityFilter {
+    policy: QualityPolicy,
+    devices: HashMap<String, DeviceTrack>,
+}
+
+impl QualityFilter {
+    pub fn new(policy: QualityPolicy) -> Self {
+        QualityFilter { policy, devices: HashMap::new() }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.policy.mode
+    }
+
+    /// Remember a stored good fix without checking it (history loaded at startup)
+    pub fn seed(&mut self, location: &Location) {
+        let point = Point::of(location);
+        let track = self.devices.entry(location.device_name.clone()).or_default();
+        track.last = Some(point);
+        track.last_good = Some(point);
+    }
+
+    /// Classify `location` against the device's previous fixes and remember it
+    pub fn check(&mut self, location: &Location) -> Option<Quality> {
+        if self.policy.mode == Mode::Off {
+            return None;
+        }
+        let point = Point::of(location);
+        let track = self.devices.entry(location.device_name.clone()).or_default();
+
+        if track.last == Some(point) {
+            return Some(Quality::Duplicate);
+        }
+        track.last = Some(point);
+
+        if self.policy.max_accuracy.is_some_and(|max| point.accuracy > max) {
+            return Some(Quality::LowAccuracy);
+        }
+
+        if let (Some(max_speed), Some(good)) = (self.policy.max_speed, track.last_good) {
+            if implied_speed(&good, &point).is_some_and(|speed| speed > max_speed) {
+                // Dois fixes seguidos coerentes entre si: mudança real de lugar (ex. teleporte)
+                let confirmed = track
+                    .jump
+                    .is_some_and(|jump| implied_speed(&jump, &point).is_some_and(|speed| speed <= max_speed));
+                if !confirmed {
+                    track.jump = Some(point);
+                    return Some(Quality::ImplausibleSpeed);
+                }
+            }
+        }
+
+        track.last_good = Some(point);
+        track.jump = None;
+        None
+    }
+}
+
+/// Meters per second needed to move between two fixes, giving each the
+/// benefit of its accuracy. `None` when a timestamp is unknown.
+fn implied_speed(from: &Point, to: &Point) -> Option<f64> {
+    let seconds = from.timestamp?.abs_diff(to.timestamp?).max(1);
+    let meters = from.coordinate.distance_to(&to.coordinate) * 1000.0;
+    Some((meters - from.accuracy - to.accuracy).max(0.0) / seconds as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(mode: Mode) -> QualityPolicy {
+        QualityPolicy { mode, max_speed: Some(50.0), max_accuracy: Some(200.0) }
+    }
+
+    fn fix(timestamp: u64, latitude: f64, longitude: f64, accuracy: f64) -> Location {
+        Location {
+            latitude,
+            longitude,
+            accuracy: Some(accuracy),
+            timestamp: timestamp.to_string(),
+            device_name: "phone".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_duplicate_and_accuracy() {
+        let mut filter = QualityFilter::new(policy(Mode::Flag));
+        assert_eq!(filter.check(&fix(100, -23.55, -46.63, 10.0)), None);
+        assert_eq!(filter.check(&fix(100, -23.55, -46.63, 10.0)), Some(Quality::Duplicate));
+        assert_eq!(filter.check(&fix(110, -23.55, -46.63, 900.0)), Some(Quality::LowAccuracy));
+        assert_eq!(filter.check(&fix(120, -23.5501, -46.63, 10.0)), None);
+
+        let mut off = QualityFilter::new(policy(Mode::Off));
+        off.check(&fix(100, -23.55, -46.63, 10.0));
+        assert_eq!(off.check(&fix(100, -23.55, -46.63, 10.0)), None);
+    }
+
+    #[test]
+    fn test_glitch_is_flagged_and_track_recovers() {
+        let mut filter = QualityFilter::new(policy(Mode::Reject));
+        assert_eq!(filter.check(&fix(100, -23.55, -46.63, 10.0)), None);
+        // 11 km em 10 s
+        assert_eq!(filter.check(&fix(110, -23.45, -46.63, 10.0)), Some(Quality::ImplausibleSpeed));
+        assert_eq!(filter.check(&fix(120, -23.5502, -46.63, 10.0)), None);
+
+        // Saltos pequenos dentro da precisão não contam como velocidade
+        assert_eq!(filter.check(&fix(121, -23.5508, -46.63, 40.0)), None);
+    }
+
+    #[test]
+    fn test_confirmed_relocation_is_accepted() {
+        let mut filter = QualityFilter::new(policy(Mode::Flag));
+        filter.seed(&fix(100, -23.55, -46.63, 10.0));
+        // Teleporte para Dubai: o primeiro fix é marcado, o seguinte coerente confirma
+        assert_eq!(filter.check(&fix(110, 25.1972, 55.2744, 10.0)), Some(Quality::ImplausibleSpeed));
+        assert_eq!(filter.check(&fix(120, 25.1973, 55.2744, 10.0)), None);
+        assert_eq!(filter.check(&fix(130, 25.1974, 55.2744, 10.0)), None);
+        assert_eq!(Quality::parse(Quality::ImplausibleSpeed.as_str()), Some(Quality::ImplausibleSpeed));
+    }
+}
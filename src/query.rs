@@ -0,0 +1,328 @@
+// Consultas ao histórico: dispositivo, intervalo de tempo, bbox e paginação
+
+use crate::{Location, LocationHistory};
+use avila_geo::{simplify, Coordinate};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SimplifyMethod {
+    DouglasPeucker,
+    Visvalingam,
+}
+
+/// `minLon,minLat,maxLon,maxLat`; `min_lon > max_lon` means the box crosses the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BBox {
+    pub fn parse(value: &str) -> Option<Self> {
+        let parts: Vec<f64> = value
+            .split(',')
+            .map(|p| p.trim().parse::<f64>().ok().filter(|v| v.is_finite()))
+            .collect::<Option<Vec<f64>>>()?;
+        if parts.len() != 4 || parts[1] > parts[3] {
+            return None;
+        }
+        Some(BBox { min_lon: parts[0], min_lat: parts[1], max_lon: parts[2], max_lat: parts[3] })
+    }
+
+    pub fn contains(&self, latitude: f64, longitude: f64) -> bool {
+        let lat_ok = latitude >= self.min_lat && latitude <= self.max_lat;
+        let lon_ok = if self.min_lon <= self.max_lon {
+            longitude >= self.min_lon && longitude <= self.max_lon
+        } else {
+            longitude >= self.min_lon || longitude <= self.max_lon
+        };
+        lat_ok && lon_ok
+    }
+}
+
+/// Position in the (timestamp, id) ordering; serialized as an opaque token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Cursor {
+    timestamp: u64,
+    id: u64,
+}
+
+impl Cursor {
+    fn encode(&self) -> String {
+        format!("{:x}-{:x}", self.timestamp, self.id)
+    }
+
+    fn decode(token: &str) -> Option<Self> {
+        let (timestamp, id) = token.split_once('-')?;
+        Some(Cursor {
+            timestamp: u64::from_str_radix(timestamp, 16).ok()?,
+            id: u64::from_str_radix(id, 16).ok()?,
+        })
+    }
+
+    fn of(location: &Location) -> Self {
+        Cursor { timestamp: location.epoch_secs().unwrap_or(0), id: location.id }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HistoryQuery {
+    pub device: Option<String>,
+    pub from: Option<u64>,
+    pub to: Option<u64>,
+    pub bbox: Option<BBox>,
+    pub limit: Option<usize>,
+    cursor: Option<Cursor>,
+    pub order: Order,
+    /// Tolerance in meters for thinning each device's track in the page
+    pub simplify: Option<f64>,
+    pub simplify_method: SimplifyMethod,
+}
+
+pub struct Page<'a> {
+    pub locations: Vec<&'a Location>,
+    pub next_cursor: Option<String>,
+}
+
+impl HistoryQuery {
+    /// Build from query parameters. The error names the offending parameter.
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self, String> {
+        let get = |name: &str| params.get(name).map(|v| v.trim()).filter(|v| !v.is_empty());
+
+        let from = match get("from") {
+            Some(v) => Some(v.parse().map_err(|_| "invalid 'from' timestamp".to_string())?),
+            None => None,
+        };
+        let to = match get("to") {
+            Some(v) => Some(v.parse().map_err(|_| "invalid 'to' timestamp".to_string())?),
+            None => None,
+        };
+        let bbox = match get("bbox") {
+            Some(v) => Some(BBox::parse(v).ok_or_else(|| {
+                "invalid 'bbox', expected minLon,minLat,maxLon,maxLat".to_string()
+            })?),
+            None => None,
+        };
+        let limit = match get("limit") {
+            Some(v) => match v.parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => return Err("invalid 'limit'".to_string()),
+            },
+            None => None,
+        };
+        let cursor = match get("cursor") {
+            Some(v) => Some(Cursor::decode(v).ok_or_else(|| "invalid 'cursor'".to_string())?),
+            None => None,
+        };
+        let order = match get("order") {
+            None | Some("asc") => Order::Asc,
+            Some("desc") => Order::Desc,
+            Some(_) => return Err("invalid 'order', expected asc or desc".to_string()),
+        };
+        let simplify = match get("simplify") {
+            Some(v) => match v.parse::<f64>() {
+                Ok(meters) if meters.is_finite() && meters >= 0.0 => Some(meters),
+                _ => return Err("invalid 'simplify', expected a tolerance in meters".to_string()),
+            },
+            None => None,
+        };
+        let simplify_method = match get("simplify_method") {
+            None | Some("douglas-peucker") => SimplifyMethod::DouglasPeucker,
+            Some("visvalingam") => SimplifyMethod::Visvalingam,
+            Some(_) => {
+                return Err("invalid 'simplify_method', expected douglas-peucker or visvalingam".to_string())
+            }
+        };
+
+        Ok(HistoryQuery {
+            device: get("device").map(str::to_string),
+            from,
+            to,
+            bbox,
+            limit,
+            cursor,
+            order,
+            simplify,
+            simplify_method,
+        })
+    }
+
+    pub fn matches(&self, location: &Location) -> bool {
+        if let Some(device) = &self.device {
+            if &location.device_name != device {
+                return false;
+            }
+        }
+        let timestamp = location.epoch_secs();
+        if let Some(from) = self.from {
+            if timestamp.is_none_or(|t| t < from) {
+                return false;
+            }
+        }
+        if let Some(to) = self.to {
+            if timestamp.is_none_or(|t| t > to) {
+                return false;
+            }
+        }
+        if let Some(bbox) = &self.bbox {
+            if !bbox.contains(location.latitude, location.longitude) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Every matching location in the requested order, ignoring limit and cursor
+    pub fn filter<'a>(&self, history: &'a LocationHistory) -> Vec<&'a Location> {
+        let mut matches: Vec<&Location> = history
+            .locations
+            .iter()
+            .filter(|location| self.matches(location))
+            .collect();
+
+        matches.sort_by_key(|location| Cursor::of(location));
+        if self.order == Order::Desc {
+            matches.reverse();
+        }
+        matches
+    }
+
+    pub fn run<'a>(&self, history: &'a LocationHistory) -> Page<'a> {
+        let mut matches = self.filter(history);
+
+        if let Some(cursor) = self.cursor {
+            matches.retain(|location| match self.order {
+                Order::Asc => Cursor::of(location) > cursor,
+                Order::Desc => Cursor::of(location) < cursor,
+            });
+        }
+
+        let mut next_cursor = None;
+        if let Some(limit) = self.limit {
+            if matches.len() > limit {
+                matches.truncate(limit);
+                next_cursor = matches.last().map(|location| Cursor::of(location).encode());
+            }
+        }
+
+        // Depois da paginação: o cursor continua apontando para o último ponto bruto
+        if let Some(tolerance) = self.simplify {
+            matches = self.simplified(matches, tolerance);
+        }
+
+        Page { locations: matches, next_cursor }
+    }
+
+    /// Simplify each device's track separately, keeping the original order
+    fn simplified<'a>(&self, locations: Vec<&'a Location>, tolerance: f64) -> Vec<&'a Location> {
+        let mut tracks: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, location) in locations.iter().enumerate() {
+            tracks.entry(location.device_name.as_str()).or_default().push(i);
+        }
+
+        let mut keep = vec![false; locations.len()];
+        for track in tracks.values() {
+            let points: Vec<Coordinate> = track
+                .iter()
+                .map(|&i| Coordinate::new(locations[i].latitude, locations[i].longitude))
+                .collect();
+            let kept = match self.simplify_method {
+                SimplifyMethod::DouglasPeucker => simplify::douglas_peucker_indices(&points, tolerance),
+                SimplifyMethod::Visvalingam => simplify::visvalingam_whyatt_indices(&points, tolerance),
+            };
+            for k in kept {
+                keep[track[k]] = true;
+            }
+        }
+
+        locations.into_iter().zip(keep).filter_map(|(location, kept)| kept.then_some(location)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history() -> LocationHistory {
+        let mut history = LocationHistory::new();
+        for (i, (device, lat, lon)) in [("a", 25.0, 55.0), ("b", 40.7, -74.0), ("a", 25.1, 55.1), ("a", 48.8, 2.3)]
+            .iter()
+            .enumerate()
+        {
+            history.push(Location {
+                id: 0,
+                latitude: *lat,
+                longitude: *lon,
+                accuracy: None,
+                timestamp: (100 + i as u64 * 10).to_string(),
+                device_name: device.to_string(),
+                ..Default::default()
+            });
+        }
+        history
+    }
+
+    fn query(pairs: &[(&str, &str)]) -> Result<HistoryQuery, String> {
+        let params = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        HistoryQuery::from_params(&params)
+    }
+
+    #[test]
+    fn test_filters() {
+        let history = history();
+        let q = query(&[("device", "a"), ("from", "110"), ("bbox", "50,20,60,30")]).unwrap();
+        let page = q.run(&history);
+        assert_eq!(page.locations.len(), 1);
+        assert_eq!(page.locations[0].timestamp, "120");
+    }
+
+    #[test]
+    fn test_pagination_desc() {
+        let history = history();
+        let first = query(&[("limit", "3"), ("order", "desc")]).unwrap().run(&history);
+        assert_eq!(first.locations[0].timestamp, "130");
+        let cursor = first.next_cursor.unwrap();
+
+        let second = query(&[("limit", "3"), ("order", "desc"), ("cursor", &cursor)]).unwrap().run(&history);
+        let stamps: Vec<&str> = second.locations.iter().map(|l| l.timestamp.as_str()).collect();
+        assert_eq!(stamps, vec!["100"]);
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_antimeridian_bbox() {
+        let bbox = BBox::parse("170,-10,-170,10").unwrap();
+        assert!(bbox.contains(0.0, 179.5));
+        assert!(bbox.contains(0.0, -179.5));
+        assert!(!bbox.contains(0.0, 0.0));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        assert!(query(&[("bbox", "1,2,3")]).unwrap_err().contains("bbox"));
+        assert!(query(&[("order", "up")]).unwrap_err().contains("order"));
+        assert!(query(&[("limit", "0")]).is_err());
+        assert!(query(&[("simplify", "-5")]).unwrap_err().contains("simplify"));
+        assert!(query(&[("simplify_method", "fast")]).unwrap_err().contains("simplify_method"));
+    }
+
+    #[test]
+    fn test_simplify_per_device() {
+        let history = history();
+        let page = query(&[("simplify", "1000000")]).unwrap().run(&history);
+        let stamps: Vec<&str> = page.locations.iter().map(|l| l.timestamp.as_str()).collect();
+        assert_eq!(stamps, vec!["100", "110", "130"]);
+
+        let page = query(&[("simplify", "0"), ("simplify_method", "visvalingam")]).unwrap().run(&history);
+        assert_eq!(page.locations.len(), 4);
+        assert_eq!(history.locations.len(), 4);
+    }
+}
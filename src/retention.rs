@@ -0,0 +1,241 @@
+// Políticas de retenção do histórico
+// Limite por quantidade, por idade e por dispositivo
+
+use crate::{Location, LocationHistory};
+use avila_json::JsonValue;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Limits for one scope (global or a single device). `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Limits {
+    pub max_entries: Option<usize>,
+    pub max_age: Option<Duration>,
+}
+
+impl Limits {
+    /// Parse `"30d"`, `"500"` or `"30d,500"` (age in days and/or entry count)
+    pub fn parse(spec: &str) -> Option<Self> {
+        let mut limits = Limits::default();
+        for token in spec.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if let Some(days) = token.strip_suffix('d') {
+                let days: u64 = days.parse().ok()?;
+                limits.max_age = Some(Duration::from_secs(days * 86_400));
+            } else {
+                limits.max_entries = Some(token.parse().ok()?);
+            }
+        }
+        Some(limits)
+    }
+
+    fn to_json(self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert(
+            "max_entries".to_string(),
+            self.max_entries.map_or(JsonValue::Null, |n| JsonValue::Number(n as f64)),
+        );
+        map.insert(
+            "max_age_secs".to_string(),
+            self.max_age.map_or(JsonValue::Null, |d| JsonValue::Number(d.as_secs() as f64)),
+        );
+        JsonValue::Object(map)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    /// Applied to the whole history
+    pub global: Limits,
+    /// Applied to each device that has no override
+    pub per_device: Limits,
+    /// Device-specific limits, replacing `per_device` for that device
+    pub overrides: HashMap<String, Limits>,
+}
+
+impl RetentionPolicy {
+    /// Parse overrides in the form `device=30d,500;other=7d`
+    pub fn parse_overrides(spec: &str) -> Option<HashMap<String, Limits>> {
+        let mut overrides = HashMap::new();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (device, limits) = entry.split_once('=')?;
+            overrides.insert(device.trim().to_string(), Limits::parse(limits)?);
+        }
+        Some(overrides)
+    }
+
+    fn device_limits(&self, device: &str) -> Limits {
+        self.overrides.get(device).copied().unwrap_or(self.per_device)
+    }
+
+    /// Cheap enforcement on insert: only the global count cap, trimmed from the front
+    pub fn enforce_on_insert(&self, history: &mut LocationHistory) {
+        if let Some(max) = self.global.max_entries {
+            while history.locations.len() > max {
+                history.pop_front();
+            }
+        }
+    }
+
+    /// Full pass applying every rule. Returns how many locations were removed.
+    ///
+    /// Locations whose timestamp cannot be read are treated as expired whenever
+    /// an age limit applies, so nothing of unknown age outlives the policy.
+    pub fn prune(&self, history: &mut LocationHistory, now: u64) -> usize {
+        let before = history.locations.len();
+
+        // Quantos registros de cada dispositivo precisam sair (os mais antigos)
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for location in &history.locations {
+            *counts.entry(location.device_name.as_str()).or_insert(0) += 1;
+        }
+        let mut excess: HashMap<String, usize> = counts
+            .into_iter()
+            .filter_map(|(device, count)| {
+                let max = self.device_limits(device).max_entries?;
+                (count > max).then(|| (device.to_string(), count - max))
+            })
+            .collect();
+
+        history.retain(|location| {
+            if let Some(remaining) = excess.get_mut(&location.device_name) {
+                if *remaining > 0 {
+                    *remaining -= 1;
+                    return false;
+                }
+            }
+            !self.is_expired(location, now)
+        });
+
+        self.enforce_on_insert(history);
+        before - history.locations.len()
+    }
+
+    fn is_expired(&self, location: &Location, now: u64) -> bool {
+        let device_age = self.device_limits(&location.device_name).max_age;
+        let max_age = match (self.global.max_age, device_age) {
+            (Some(a), Some(b)) => a.min(b),
+            (a, b) => match a.or(b) {
+                Some(age) => age,
+                None => return false,
+            },
+        };
+
+        let cutoff = now.saturating_sub(max_age.as_secs());
+        location.epoch_secs().is_none_or(|ts| ts < cutoff)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let overrides: HashMap<String, JsonValue> = self
+            .overrides
+            .iter()
+            .map(|(device, limits)| (device.clone(), limits.to_json()))
+            .collect();
+
+        let mut map = HashMap::new();
+        map.insert("global".to_string(), self.global.to_json());
+        map.insert("per_device".to_string(), self.per_device.to_json());
+        map.insert("overrides".to_string(), JsonValue::Object(overrides));
+        JsonValue::Object(map)
+    }
+}
+
+/// Outcome of the pruning task, exposed so operators can audit retention
+#[derive(Debug, Clone, Default)]
+pub struct RetentionStatus {
+    pub last_run: Option<u64>,
+    pub last_removed: usize,
+    pub total_removed: u64,
+}
+
+impl RetentionStatus {
+    pub fn to_json(&self, history: &LocationHistory) -> JsonValue {
+        let oldest = history.locations.iter().filter_map(Location::epoch_secs).min();
+
+        let mut map = HashMap::new();
+        map.insert(
+            "last_run".to_string(),
+            self.last_run.map_or(JsonValue::Null, |t| JsonValue::String(t.to_string())),
+        );
+        map.insert("last_removed".to_string(), JsonValue::Number(self.last_removed as f64));
+        map.insert("total_removed".to_string(), JsonValue::Number(self.total_removed as f64));
+        map.insert(
+            "oldest_timestamp".to_string(),
+            oldest.map_or(JsonValue::Null, |t| JsonValue::String(t.to_string())),
+        );
+        map.insert("retained".to_string(), JsonValue::Number(history.locations.len() as f64));
+        JsonValue::Object(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(device: &str, timestamp: u64) -> Location {
+        Location {
+            id: 0,
+            latitude: 0.0,
+            longitude: 0.0,
+            accuracy: None,
+            timestamp: timestamp.to_string(),
+            device_name: device.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn history(fixes: &[(&str, u64)]) -> LocationHistory {
+        let mut history = LocationHistory::new();
+        for &(device, timestamp) in fixes {
+            history.push(fix(device, timestamp));
+        }
+        history
+    }
+
+    #[test]
+    fn test_parse_limits() {
+        let limits = Limits::parse("30d,500").unwrap();
+        assert_eq!(limits.max_age, Some(Duration::from_secs(30 * 86_400)));
+        assert_eq!(limits.max_entries, Some(500));
+        assert!(Limits::parse("abc").is_none());
+
+        let overrides = RetentionPolicy::parse_overrides("truck=90d; phone=10").unwrap();
+        assert_eq!(overrides["phone"].max_entries, Some(10));
+    }
+
+    #[test]
+    fn test_prune_by_age() {
+        let policy = RetentionPolicy {
+            global: Limits { max_entries: None, max_age: Some(Duration::from_secs(100)) },
+            ..Default::default()
+        };
+        let mut h = history(&[("a", 10), ("a", 950), ("b", 899), ("b", 1000)]);
+
+        assert_eq!(policy.prune(&mut h, 1000), 2);
+        assert!(h.locations.iter().all(|l| l.epoch_secs().unwrap() >= 900));
+    }
+
+    #[test]
+    fn test_prune_per_device_keeps_newest() {
+        let mut policy = RetentionPolicy {
+            per_device: Limits { max_entries: Some(2), max_age: None },
+            ..Default::default()
+        };
+        policy.overrides.insert("b".to_string(), Limits { max_entries: Some(1), max_age: None });
+        let mut h = history(&[("a", 1), ("b", 2), ("a", 3), ("b", 4), ("a", 5), ("b", 6)]);
+
+        assert_eq!(policy.prune(&mut h, 10), 3);
+        let left: Vec<u64> = h.locations.iter().filter_map(Location::epoch_secs).collect();
+        assert_eq!(left, vec![3, 5, 6]);
+    }
+
+    #[test]
+    fn test_global_cap_on_insert() {
+        let policy = RetentionPolicy {
+            global: Limits { max_entries: Some(2), max_age: None },
+            ..Default::default()
+        };
+        let mut h = history(&[("a", 1), ("a", 2), ("a", 3)]);
+        policy.enforce_on_insert(&mut h);
+        assert_eq!(h.locations.front().unwrap().timestamp, "2");
+    }
+}
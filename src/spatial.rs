@@ -0,0 +1,274 @@
+// Índice espacial do histórico por geohash
+// Cada célula fina guarda os ids e a soma dos vetores unitários; prefixos agrupam células vizinhas
+
+use crate::{Location, LocationHistory};
+use avila_geo::{geohash, Coordinate, EARTH_RADIUS_KM};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Precision of the stored keys: 8 characters is roughly 38 m x 19 m
+pub const INDEX_PRECISION: usize = 8;
+
+#[derive(Debug, Clone, Default)]
+struct Cell {
+    /// Ascending, like the history itself
+    ids: Vec<u64>,
+    /// Sum of the members' unit vectors, so means work across the antimeridian
+    vector_sum: [f64; 3],
+}
+
+/// Locations grouped under a shorter geohash prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cluster {
+    pub geohash: String,
+    pub count: usize,
+    /// Mean position of the members
+    pub center: Coordinate,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GeohashIndex {
+    cells: BTreeMap<String, Cell>,
+}
+
+fn key(location: &Location) -> String {
+    geohash::encode(&Coordinate::new(location.latitude, location.longitude), INDEX_PRECISION)
+}
+
+fn unit_vector(location: &Location) -> [f64; 3] {
+    let (lat, lon) = (location.latitude.to_radians(), location.longitude.to_radians());
+    [lat.cos() * lon.cos(), lat.cos() * lon.sin(), lat.sin()]
+}
+
+fn add(sum: &mut [f64; 3], vector: [f64; 3], sign: f64) {
+    for (total, component) in sum.iter_mut().zip(vector) {
+        *total += sign * component;
+    }
+}
+
+impl GeohashIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, location: &Location) {
+        let cell = self.cells.entry(key(location)).or_default();
+        match cell.ids.last() {
+            Some(&last) if last > location.id => {
+                let at = cell.ids.partition_point(|&id| id < location.id);
+                cell.ids.insert(at, location.id);
+            }
+            _ => cell.ids.push(location.id),
+        }
+        add(&mut cell.vector_sum, unit_vector(location), 1.0);
+    }
+
+    pub fn remove(&mut self, location: &Location) {
+        let key = key(location);
+        let Some(cell) = self.cells.get_mut(&key) else { return };
+        if let Ok(at) = cell.ids.binary_search(&location.id) {
+            cell.ids.remove(at);
+            add(&mut cell.vector_sum, unit_vector(location), -1.0);
+        }
+        if cell.ids.is_empty() {
+            self.cells.remove(&key);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// Ids of every location whose geohash starts with `prefix`
+    pub fn ids_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = u64> + 'a {
+        self.cells
+            .range(prefix.to_string()..)
+            .take_while(move |(key, _)| key.starts_with(prefix))
+            .flat_map(|(_, cell)| cell.ids.iter().copied())
+    }
+
+    /// Ids that may lie within `radius_m` of `center`: the covering cell and its
+    /// neighbors. Callers still check the exact distance.
+    pub fn candidates_near(&self, center: &Coordinate, radius_m: f64) -> BTreeSet<u64> {
+        let precision = covering_precision(center, radius_m);
+        if precision == 0 {
+            return self.ids_with_prefix("").collect();
+        }
+
+        let hash = geohash::encode(center, precision);
+        let mut cells = geohash::neighbors(&hash).unwrap_or_default();
+        cells.push(hash);
+        cells.iter().flat_map(|cell| self.ids_with_prefix(cell)).collect()
+    }
+
+    /// Counts and mean positions per geohash prefix of `precision` characters
+    pub fn clusters(&self, precision: usize) -> Vec<Cluster> {
+        let precision = precision.clamp(1, INDEX_PRECISION);
+        let mut clusters: Vec<(String, usize, [f64; 3])> = Vec::new();
+
+        // As chaves vêm ordenadas: células com o mesmo prefixo são contíguas
+        for (key, cell) in &self.cells {
+            let prefix = &key[..precision];
+            match clusters.last_mut() {
+                Some(last) if last.0 == prefix => {
+                    last.1 += cell.ids.len();
+                    add(&mut last.2, cell.vector_sum, 1.0);
+                }
+                _ => clusters.push((prefix.to_string(), cell.ids.len(), cell.vector_sum)),
+            }
+        }
+
+        clusters
+            .into_iter()
+            .map(|(geohash, count, [x, y, z])| Cluster {
+                geohash,
+                count,
+                center: Coordinate::new(z.atan2(x.hypot(y)).to_degrees(), y.atan2(x).to_degrees()),
+            })
+            .collect()
+    }
+}
+
+/// Locations within `radius_m` of `center` with their distance in meters,
+/// ordered by time. Only the cells around `center` are scanned.
+pub fn nearby<'a>(history: &'a LocationHistory, center: &Coordinate, radius_m: f64) -> Vec<(&'a Location, f64)> {
+    let mut found: Vec<(&Location, f64)> = history
+        .index
+        .candidates_near(center, radius_m)
+        .into_iter()
+        .filter_map(|id| history.get(id))
+        .filter_map(|location| {
+            let distance = center.distance_to(&Coordinate::new(location.latitude, location.longitude)) * 1000.0;
+            (distance <= radius_m).then_some((location, distance))
+        })
+        .collect();
+    found.sort_by_key(|(location, _)| (location.epoch_secs().unwrap_or(0), location.id));
+    found
+}
+
+/// Finest precision whose cells are at least `radius_m` tall and wide around
+/// `center`, so the cell plus its 8 neighbors cover the circle. 0 means no
+/// cell is large enough.
+pub fn covering_precision(center: &Coordinate, radius_m: f64) -> usize {
+    // Margem para a diferença entre esfera e elipsoide
+    let radius_m = radius_m * 1.01;
+    let meters_per_degree = EARTH_RADIUS_KM * 1000.0 * std::f64::consts::PI / 180.0;
+    // Largura avaliada no lado mais próximo do polo, onde as células são mais estreitas
+    let far_latitude = (center.latitude.abs() + radius_m / meters_per_degree).min(90.0);
+    let cos_lat = far_latitude.to_radians().cos();
+
+    (1..=INDEX_PRECISION)
+        .rev()
+        .find(|&precision| {
+            let (height, width) = geohash::cell_size(precision);
+            height * meters_per_degree >= radius_m && width * meters_per_degree * cos_lat >= radius_m
+        })
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(id: u64, latitude: f64, longitude: f64) -> Location {
+        Location { id, latitude, longitude, ..Default::default() }
+    }
+
+    #[test]
+    fn test_insert_remove_and_prefix() {
+        let mut index = GeohashIndex::new();
+        let a = fix(1, 25.1972, 55.2744);
+        let b = fix(2, 25.1973, 55.2745);
+        let c = fix(3, -33.8568, 151.2153);
+        for location in [&a, &c, &b] {
+            index.insert(location);
+        }
+
+        let prefix = geohash::encode(&Coordinate::new(25.1972, 55.2744), 5);
+        assert_eq!(index.ids_with_prefix(&prefix).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(index.ids_with_prefix("").count(), 3);
+
+        index.remove(&a);
+        index.remove(&a);
+        assert_eq!(index.ids_with_prefix(&prefix).collect::<Vec<_>>(), vec![2]);
+        index.clear();
+        assert_eq!(index.ids_with_prefix("").count(), 0);
+    }
+
+    #[test]
+    fn test_candidates_cover_radius() {
+        let mut index = GeohashIndex::new();
+        let center = Coordinate::new(59.9, 10.75);
+        // Pontos a 90 m em todas as direções, e um a 5 km
+        for (i, bearing) in (0..360).step_by(15).enumerate() {
+            let point = center.destination(0.09, bearing as f64);
+            index.insert(&fix(i as u64 + 1, point.latitude, point.longitude));
+        }
+        let far = center.destination(5.0, 0.0);
+        index.insert(&fix(100, far.latitude, far.longitude));
+
+        let candidates = index.candidates_near(&center, 100.0);
+        assert_eq!(candidates.len(), 24);
+        assert!(!candidates.contains(&100));
+
+        assert_eq!(covering_precision(&center, 10_000_000.0), 0);
+        assert_eq!(index.candidates_near(&center, 10_000_000.0).len(), 25);
+    }
+
+    #[test]
+    fn test_clusters() {
+        let mut index = GeohashIndex::new();
+        index.insert(&fix(1, 25.1972, 55.2744));
+        index.insert(&fix(2, 25.1974, 55.2746));
+        index.insert(&fix(3, 25.0805, 55.1403));
+
+        let clusters = index.clusters(5);
+        assert_eq!(clusters.len(), 2);
+        let burj = clusters.iter().find(|c| c.count == 2).unwrap();
+        assert!((burj.center.latitude - 25.1973).abs() < 1e-6);
+        assert!((burj.center.longitude - 55.2745).abs() < 1e-6);
+        assert_eq!(index.clusters(1).len(), 1);
+
+        // Removido, o membro deixa de pesar na média
+        index.remove(&fix(2, 25.1974, 55.2746));
+        let burj = index.clusters(5).into_iter().find(|c| c.geohash == burj.geohash).unwrap();
+        assert!((burj.center.latitude - 25.1972).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cluster_center_across_antimeridian() {
+        // 180° é indexado como -180°: os dois fixes caem na mesma célula
+        let mut index = GeohashIndex::new();
+        index.insert(&fix(1, -16.5, 180.0));
+        index.insert(&fix(2, -16.5, -179.9998));
+
+        let clusters = index.clusters(5);
+        assert_eq!(clusters.len(), 1);
+        assert!((clusters[0].center.longitude.abs() - 179.9999).abs() < 1e-6, "{:?}", clusters[0]);
+    }
+
+    #[test]
+    fn test_nearby_tracks_history_changes() {
+        let mut history = LocationHistory::new();
+        let center = Coordinate::new(-23.5614, -46.6559);
+        for (seconds, km) in [(30, 0.05), (10, 0.2), (20, 0.01), (40, 3.0)] {
+            let point = center.destination(km, 45.0);
+            history.push(Location {
+                latitude: point.latitude,
+                longitude: point.longitude,
+                timestamp: seconds.to_string(),
+                ..Default::default()
+            });
+        }
+
+        let found = nearby(&history, &center, 250.0);
+        let stamps: Vec<&str> = found.iter().map(|(l, _)| l.timestamp.as_str()).collect();
+        assert_eq!(stamps, vec!["10", "20", "30"]);
+        assert!((found[1].1 - 10.0).abs() < 0.1);
+
+        history.pop_front();
+        history.retain(|location| location.timestamp != "20");
+        assert_eq!(nearby(&history, &center, 250.0).len(), 1);
+        history.clear();
+        assert!(nearby(&history, &center, 10_000.0).is_empty());
+    }
+}
@@ -0,0 +1,75 @@
+// Estatísticas de trajeto: distância percorrida por dispositivo
+// Modelo esférico (Haversine, R = 6371 km) ou elipsoidal (WGS84, Karney)
+
+use crate::Location;
+use avila_geo::{Coordinate, Ellipsoid};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistanceModel {
+    Spherical,
+    Ellipsoidal,
+}
+
+impl DistanceModel {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "spherical" | "haversine" => Some(DistanceModel::Spherical),
+            "ellipsoidal" | "wgs84" => Some(DistanceModel::Ellipsoidal),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DistanceModel::Spherical => "spherical",
+            DistanceModel::Ellipsoidal => "ellipsoidal",
+        }
+    }
+
+    /// Distance between two fixes in meters
+    pub fn distance(&self, from: &Location, to: &Location) -> f64 {
+        let a = Coordinate::new(from.latitude, from.longitude);
+        let b = Coordinate::new(to.latitude, to.longitude);
+        match self {
+            DistanceModel::Spherical => a.distance_to(&b) * 1000.0,
+            DistanceModel::Ellipsoidal => Ellipsoid::WGS84.distance(&a, &b),
+        }
+    }
+}
+
+/// Total length in meters of a track given in chronological order
+pub fn track_distance(locations: &[&Location], model: DistanceModel) -> f64 {
+    locations
+        .windows(2)
+        .map(|pair| model.distance(pair[0], pair[1]))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fix(latitude: f64, longitude: f64) -> Location {
+        Location { latitude, longitude, ..Default::default() }
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(DistanceModel::parse("WGS84"), Some(DistanceModel::Ellipsoidal));
+        assert_eq!(DistanceModel::parse(" spherical "), Some(DistanceModel::Spherical));
+        assert_eq!(DistanceModel::parse("flat"), None);
+    }
+
+    #[test]
+    fn test_models_differ_along_meridian() {
+        // Um grau de latitude no equador: 110574 m no elipsoide, 111195 m na esfera
+        let track = [fix(0.0, 10.0), fix(0.5, 10.0), fix(1.0, 10.0)];
+        let refs: Vec<&Location> = track.iter().collect();
+
+        let ellipsoidal = track_distance(&refs, DistanceModel::Ellipsoidal);
+        let spherical = track_distance(&refs, DistanceModel::Spherical);
+        assert!((ellipsoidal - 110574.4).abs() < 0.5);
+        assert!((spherical - 111194.9).abs() < 0.5);
+        assert_eq!(track_distance(&refs[..1], DistanceModel::Ellipsoidal), 0.0);
+    }
+}
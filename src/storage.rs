@@ -0,0 +1,469 @@
+// Armazenamento append-only do histórico de localizações
+// Snapshot JSON + write-ahead log em JSON Lines, com compactação periódica
+
+use crate::{Location, LocationHistory};
+use avila_json::JsonValue;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// When appended WAL records are flushed to stable storage
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FsyncPolicy {
+    /// fsync after every record (safest, slowest)
+    Always,
+    /// fsync from the maintenance thread at a fixed interval
+    Interval(Duration),
+    /// Leave it to the operating system
+    Never,
+}
+
+impl FsyncPolicy {
+    pub fn parse(value: &str, interval: Duration) -> Option<Self> {
+        match value {
+            "always" => Some(FsyncPolicy::Always),
+            "interval" => Some(FsyncPolicy::Interval(interval)),
+            "never" => Some(FsyncPolicy::Never),
+            _ => None,
+        }
+    }
+}
+
+struct Wal {
+    file: File,
+    next_seq: u64,
+    records: u64,
+    dirty: bool,
+    last_sync: Instant,
+    last_compaction: Instant,
+}
+
+pub struct Storage {
+    snapshot_path: PathBuf,
+    wal_path: PathBuf,
+    fsync: FsyncPolicy,
+    compact_every: u64,
+    compact_interval: Duration,
+    wal: Mutex<Wal>,
+    compacting: Mutex<()>,
+}
+
+impl Storage {
+    /// Open the store, recovering history from the snapshot plus any WAL segments.
+    /// A truncated last record (crash mid-write) is discarded.
+    pub fn open(
+        snapshot_path: &Path,
+        fsync: FsyncPolicy,
+        compact_every: u64,
+        compact_interval: Duration,
+    ) -> io::Result<(Self, LocationHistory)> {
+        let snapshot_path = snapshot_path.to_path_buf();
+        let wal_path = snapshot_path.with_extension("wal");
+
+        let (mut history, snapshot_seq) = read_snapshot(&snapshot_path)?;
+        let mut last_seq = snapshot_seq;
+        let mut records = 0;
+
+        let mut files = rotated_segments(&wal_path)?;
+        files.push((u64::MAX, wal_path.clone()));
+
+        for (_, path) in &files {
+            if !path.exists() {
+                continue;
+            }
+            let data = fs::read(path)?;
+            let (good_len, entries) = replay_segment(&data, path);
+
+            for (seq, record) in entries {
+                if seq <= snapshot_seq {
+                    continue;
+                }
+                apply_record(&mut history, &record);
+                last_seq = last_seq.max(seq);
+                records += 1;
+            }
+
+            if good_len < data.len() {
+                eprintln!("⚠️  Discarding truncated record at end of {}", path.display());
+                let file = OpenOptions::new().write(true).open(path)?;
+                file.set_len(good_len as u64)?;
+                file.sync_all()?;
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&wal_path)?;
+
+        let storage = Storage {
+            snapshot_path,
+            wal_path,
+            fsync,
+            compact_every,
+            compact_interval,
+            wal: Mutex::new(Wal {
+                file,
+                next_seq: last_seq + 1,
+                records,
+                dirty: false,
+                last_sync: Instant::now(),
+                last_compaction: Instant::now(),
+            }),
+            compacting: Mutex::new(()),
+        };
+
+        Ok((storage, history))
+    }
+
+    /// Append a new location to the log
+    pub fn append(&self, location: &Location) -> io::Result<()> {
+        let mut map = HashMap::new();
+        map.insert("op".to_string(), JsonValue::String("append".to_string()));
+        map.insert("location".to_string(), location.to_json());
+        self.write_record(map)
+    }
+
+    /// Record that the whole history was cleared
+    pub fn log_clear(&self) -> io::Result<()> {
+        let mut map = HashMap::new();
+        map.insert("op".to_string(), JsonValue::String("clear".to_string()));
+        self.write_record(map)
+    }
+
+    fn write_record(&self, mut map: HashMap<String, JsonValue>) -> io::Result<()> {
+        let mut wal = self.wal.lock().map_err(|_| poisoned())?;
+
+        let seq = wal.next_seq;
+        map.insert("seq".to_string(), JsonValue::Number(seq as f64));
+        let mut line = JsonValue::Object(map).to_string();
+        line.push('\n');
+
+        wal.file.write_all(line.as_bytes())?;
+        wal.next_seq += 1;
+        wal.records += 1;
+
+        if self.fsync == FsyncPolicy::Always {
+            wal.file.sync_data()?;
+        } else {
+            wal.dirty = true;
+        }
+
+        Ok(())
+    }
+
+    /// Periodic work: interval fsync and compaction when due
+    pub fn maintain(&self, history: &Mutex<LocationHistory>) -> io::Result<()> {
+        let compact_due = {
+            let mut wal = self.wal.lock().map_err(|_| poisoned())?;
+
+            if let FsyncPolicy::Interval(interval) = self.fsync {
+                if wal.dirty && wal.last_sync.elapsed() >= interval {
+                    wal.file.sync_data()?;
+                    wal.dirty = false;
+                    wal.last_sync = Instant::now();
+                }
+            }
+
+            wal.records >= self.compact_every
+                || (wal.records > 0 && wal.last_compaction.elapsed() >= self.compact_interval)
+        };
+
+        if compact_due {
+            self.compact(history)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write a fresh snapshot of `history` and drop the WAL records it covers.
+    ///
+    /// The active WAL is rotated aside while both locks are held, so writers
+    /// only wait for the rename, not for the snapshot to be serialized.
+    pub fn compact(&self, history: &Mutex<LocationHistory>) -> io::Result<()> {
+        let _guard = self.compacting.lock().map_err(|_| poisoned())?;
+
+        let (snapshot, snapshot_seq) = {
+            let history = history.lock().map_err(|_| poisoned())?;
+            let mut wal = self.wal.lock().map_err(|_| poisoned())?;
+
+            let snapshot_seq = wal.next_seq - 1;
+            wal.last_compaction = Instant::now();
+            if wal.records > 0 {
+                wal.file.sync_data()?;
+                fs::rename(&self.wal_path, segment_path(&self.wal_path, snapshot_seq))?;
+                wal.file = OpenOptions::new().create(true).append(true).open(&self.wal_path)?;
+                wal.records = 0;
+                wal.dirty = false;
+            }
+
+            (history.clone(), snapshot_seq)
+        };
+
+        let mut json = snapshot.to_json();
+        if let JsonValue::Object(map) = &mut json {
+            map.insert("last_seq".to_string(), JsonValue::Number(snapshot_seq as f64));
+        }
+
+        write_atomic(&self.snapshot_path, json.to_string().as_bytes())?;
+
+        for (seq, path) in rotated_segments(&self.wal_path)? {
+            if seq <= snapshot_seq {
+                fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Replace `path` with `data` without ever leaving it half written: a
+/// temporary file is synced and renamed over it, then the directory is synced
+/// so the rename itself survives a crash
+pub fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let tmp_path = suffixed(path, ".tmp");
+    {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all(data)?;
+        tmp.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)?;
+    sync_parent(path)
+}
+
+/// Rename an unreadable file to `<name>.corrupt` so the service can start
+/// empty without overwriting it
+pub fn move_aside(path: &Path) -> io::Result<PathBuf> {
+    let aside = suffixed(path, ".corrupt");
+    fs::rename(path, &aside)?;
+    Ok(aside)
+}
+
+fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+/// Diretórios não podem ser abertos para fsync no Windows; o rename já é durável lá
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn poisoned() -> io::Error {
+    io::Error::other("storage lock poisoned")
+}
+
+fn segment_path(wal_path: &Path, seq: u64) -> PathBuf {
+    suffixed(wal_path, &format!(".{}", seq))
+}
+
+/// WAL segments rotated aside by an unfinished or finished compaction, oldest first
+fn rotated_segments(wal_path: &Path) -> io::Result<Vec<(u64, PathBuf)>> {
+    let dir = match wal_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let prefix = match wal_path.file_name() {
+        Some(name) => format!("{}.", name.to_string_lossy()),
+        None => return Ok(Vec::new()),
+    };
+
+    let mut segments = Vec::new();
+    if let Ok(entries) = fs::read_dir(&dir) {
+        for entry in entries {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if let Some(seq) = name.strip_prefix(&prefix).and_then(|s| s.parse::<u64>().ok()) {
+                segments.push((seq, entry.path()));
+            }
+        }
+    }
+    segments.sort();
+    Ok(segments)
+}
+
+fn read_snapshot(path: &Path) -> io::Result<(LocationHistory, u64)> {
+    if !path.exists() {
+        return Ok((LocationHistory::new(), 0));
+    }
+
+    let data = fs::read_to_string(path)?;
+    match avila_json::parse(&data) {
+        Ok(json) => {
+            let seq = json
+                .as_object()
+                .and_then(|map| map.get("last_seq"))
+                .and_then(|v| v.as_f64())
+                .unwrap_or(0.0) as u64;
+            let history = LocationHistory::from_json(&json)
+                .unwrap_or(LocationHistory::new());
+            Ok((history, seq))
+        }
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("corrupt snapshot {}", path.display()),
+        )),
+    }
+}
+
+/// Parse one WAL segment. Returns the byte length of the valid prefix and its records.
+fn replay_segment(data: &[u8], path: &Path) -> (usize, Vec<(u64, JsonValue)>) {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+
+    while offset < data.len() {
+        let end = match data[offset..].iter().position(|&b| b == b'\n') {
+            Some(pos) => offset + pos,
+            // Sem newline final: registro truncado por crash
+            None => return (offset, entries),
+        };
+
+        let line = String::from_utf8_lossy(&data[offset..end]);
+        let record = avila_json::parse(&line).ok();
+        let seq = record
+            .as_ref()
+            .and_then(|r| r.as_object())
+            .and_then(|map| map.get("seq"))
+            .and_then(|v| v.as_f64());
+
+        match (record, seq) {
+            (Some(record), Some(seq)) => entries.push((seq as u64, record)),
+            _ => eprintln!("⚠️  Skipping corrupt record in {} at byte {}", path.display(), offset),
+        }
+
+        offset = end + 1;
+    }
+
+    (offset, entries)
+}
+
+fn apply_record(history: &mut LocationHistory, record: &JsonValue) {
+    let map = match record.as_object() {
+        Some(map) => map,
+        None => return,
+    };
+
+    match map.get("op").and_then(|v| v.as_str()) {
+        Some("append") => {
+            if let Some(location) = map.get("location").and_then(Location::from_json) {
+                history.restore(location);
+            }
+        }
+        Some("clear") => history.clear(),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_snapshot(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gps-storage-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("history.json")
+    }
+
+    fn fix(n: u64) -> Location {
+        Location {
+            id: n,
+            latitude: n as f64,
+            longitude: 55.0,
+            accuracy: None,
+            timestamp: n.to_string(),
+            device_name: "phone".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn open(path: &Path) -> (Storage, LocationHistory) {
+        Storage::open(path, FsyncPolicy::Never, 1000, Duration::from_secs(300)).unwrap()
+    }
+
+    #[test]
+    fn test_replays_wal_after_restart() {
+        let path = temp_snapshot("replay");
+        let (storage, _) = open(&path);
+        storage.append(&fix(1)).unwrap();
+        storage.append(&fix(2)).unwrap();
+        storage.log_clear().unwrap();
+        storage.append(&fix(3)).unwrap();
+        drop(storage);
+
+        let (_, history) = open(&path);
+        assert_eq!(history.locations.len(), 1);
+        assert_eq!(history.locations[0].timestamp, "3");
+    }
+
+    #[test]
+    fn test_tolerates_truncated_last_record() {
+        let path = temp_snapshot("truncated");
+        let (storage, _) = open(&path);
+        storage.append(&fix(1)).unwrap();
+        drop(storage);
+
+        let wal_path = path.with_extension("wal");
+        let mut file = OpenOptions::new().append(true).open(&wal_path).unwrap();
+        file.write_all(b"{\"seq\":2,\"op\":\"app").unwrap();
+        drop(file);
+
+        let (storage, history) = open(&path);
+        assert_eq!(history.locations.len(), 1);
+
+        // O próximo registro deve começar numa linha limpa
+        storage.append(&fix(2)).unwrap();
+        drop(storage);
+        let (_, history) = open(&path);
+        assert_eq!(history.locations.len(), 2);
+    }
+
+    #[test]
+    fn test_compaction_snapshots_and_truncates_wal() {
+        let path = temp_snapshot("compact");
+        let (storage, mut history) = open(&path);
+        for n in 1..=3 {
+            storage.append(&fix(n)).unwrap();
+            history.locations.push_back(fix(n));
+        }
+
+        let history = Mutex::new(history);
+        storage.compact(&history).unwrap();
+        assert_eq!(fs::metadata(path.with_extension("wal")).unwrap().len(), 0);
+        assert!(rotated_segments(&path.with_extension("wal")).unwrap().is_empty());
+
+        storage.append(&fix(4)).unwrap();
+        drop(storage);
+
+        let (_, recovered) = open(&path);
+        let stamps: Vec<&str> = recovered.locations.iter().map(|l| l.timestamp.as_str()).collect();
+        assert_eq!(stamps, vec!["1", "2", "3", "4"]);
+    }
+
+    #[test]
+    fn test_leftover_segment_is_not_replayed_twice() {
+        let path = temp_snapshot("segment");
+        let (storage, mut history) = open(&path);
+        storage.append(&fix(1)).unwrap();
+        history.locations.push_back(fix(1));
+        storage.compact(&Mutex::new(history)).unwrap();
+        drop(storage);
+
+        // Simula crash após gravar o snapshot mas antes de apagar o segmento
+        let wal_path = path.with_extension("wal");
+        fs::write(segment_path(&wal_path, 1), "{\"seq\":1,\"op\":\"append\",\"location\":{\"latitude\":1,\"longitude\":55,\"accuracy\":null,\"timestamp\":\"1\",\"device_name\":\"phone\"}}\n").unwrap();
+
+        let (_, recovered) = open(&path);
+        assert_eq!(recovered.locations.len(), 1);
+    }
+}
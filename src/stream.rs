@@ -0,0 +1,74 @@
+// Distribuição de novas localizações para conexões ao vivo (SSE)
+// Cada assinante recebe uma cópia via canal; canais fechados são descartados
+
+use crate::Location;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+#[derive(Default)]
+pub struct Broadcaster {
+    subscribers: Mutex<Vec<Sender<Location>>>,
+}
+
+impl Broadcaster {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self) -> Receiver<Location> {
+        let (sender, receiver) = channel();
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.push(sender);
+        }
+        receiver
+    }
+
+    /// Send to every live subscriber, dropping the ones that hung up
+    pub fn publish(&self, location: &Location) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.retain(|sender| sender.send(location.clone()).is_ok());
+        }
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscribers.lock().map(|s| s.len()).unwrap_or(0)
+    }
+}
+
+/// One `location` event; the history id doubles as the SSE event id
+pub fn sse_event(location: &Location) -> String {
+    format!(
+        "id: {}\nevent: location\ndata: {}\n\n",
+        location.id,
+        location.to_json().to_string()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_publish_drops_closed_subscribers() {
+        let broadcaster = Broadcaster::new();
+        let first = broadcaster.subscribe();
+        let second = broadcaster.subscribe();
+        drop(second);
+
+        let location = Location { id: 7, device_name: "car".to_string(), ..Default::default() };
+        broadcaster.publish(&location);
+
+        assert_eq!(first.try_recv().unwrap().id, 7);
+        assert_eq!(broadcaster.subscriber_count(), 1);
+    }
+
+    #[test]
+    fn test_sse_event_format() {
+        let location = Location { id: 12, device_name: "car".to_string(), ..Default::default() };
+        let event = sse_event(&location);
+
+        assert!(event.starts_with("id: 12\nevent: location\ndata: {"));
+        assert!(event.ends_with("}\n\n"));
+        assert!(!event.trim_end().contains("\n\n"));
+    }
+}
@@ -0,0 +1,648 @@
+// Webhooks: assinaturas, fila de entrega persistente, retry com backoff e dead letters
+// Cliente HTTP/1.1 mínimo sobre TcpStream (apenas http://)
+
+use crate::crypto::{hex, hmac_sha256};
+use crate::storage::{move_aside, write_atomic};
+use avila_json::JsonValue;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Event names a webhook can subscribe to
+pub const EVENTS: [&str; 5] = ["geofence.enter", "geofence.exit", "geofence.dwell", "battery.low", "device.new"];
+
+/// Oldest dead letters are dropped beyond this
+const MAX_DEAD_LETTERS: usize = 1000;
+
+/// Backoff never grows past this
+const MAX_BACKOFF_SECS: u64 = 3600;
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Delay after the first failure; doubles on each further failure
+    pub base_backoff_secs: u64,
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempts: u32) -> u64 {
+        let factor = 2u64.saturating_pow(attempts.saturating_sub(1));
+        self.base_backoff_secs.saturating_mul(factor).min(MAX_BACKOFF_SECS)
+    }
+}
+
+/// `http://host[:port][/path]`; IPv6 literals go in brackets (`http://[::1]:8080/`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Endpoint {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Endpoint {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let rest = url
+            .trim()
+            .strip_prefix("http://")
+            .ok_or("'url' must start with http:// (TLS is not supported)")?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], rest[i..].to_string()),
+            None => (rest, "/".to_string()),
+        };
+        // Literal IPv6 vem entre colchetes; os ':' dentro deles não separam a porta
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, rest) = bracketed.split_once(']').ok_or("unclosed '[' in 'url'")?;
+                match rest {
+                    "" => (host, 80),
+                    _ => {
+                        let port = rest.strip_prefix(':').ok_or("invalid port in 'url'")?;
+                        (host, port.parse().map_err(|_| "invalid port in 'url'")?)
+                    }
+                }
+            }
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, port.parse().map_err(|_| "invalid port in 'url'")?),
+                None => (authority, 80),
+            },
+        };
+        if host.is_empty() {
+            return Err("missing host in 'url'".to_string());
+        }
+        Ok(Endpoint { host: host.to_string(), port, path })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub id: u64,
+    pub url: String,
+    /// HMAC-SHA256 key; never returned by the API
+    pub secret: String,
+    /// Subscribed event names; empty means all
+    pub events: Vec<String>,
+}
+
+impl Webhook {
+    /// Parse a subscription; errors name the offending field
+    pub fn from_json(json: &JsonValue) -> Result<Self, String> {
+        let map = json.as_object().ok_or("expected a JSON object")?;
+        let url = map.get("url").and_then(|v| v.as_str()).ok_or("missing 'url'")?.to_string();
+        Endpoint::parse(&url)?;
+        let secret = map
+            .get("secret")
+            .and_then(|v| v.as_str())
+            .filter(|s| !s.is_empty())
+            .ok_or("missing 'secret'")?
+            .to_string();
+
+        let mut events = Vec::new();
+        for event in map.get("events").and_then(|v| v.as_array()).into_iter().flatten() {
+            match event.as_str() {
+                Some(name) if EVENTS.contains(&name) => events.push(name.to_string()),
+                _ => return Err(format!("'events' must only contain {}", EVENTS.join(", "))),
+            }
+        }
+
+        Ok(Webhook {
+            id: map.get("id").and_then(|v| v.as_f64()).unwrap_or(0.0) as u64,
+            url,
+            secret,
+            events,
+        })
+    }
+
+    /// Public view, without the secret
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::Number(self.id as f64));
+        map.insert("url".to_string(), JsonValue::String(self.url.clone()));
+        map.insert(
+            "events".to_string(),
+            JsonValue::Array(self.events.iter().cloned().map(JsonValue::String).collect()),
+        );
+        JsonValue::Object(map)
+    }
+
+    fn to_stored_json(&self) -> JsonValue {
+        let mut json = self.to_json();
+        if let JsonValue::Object(map) = &mut json {
+            map.insert("secret".to_string(), JsonValue::String(self.secret.clone()));
+        }
+        json
+    }
+
+    fn wants(&self, event: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == event)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Delivery {
+    pub id: u64,
+    pub webhook_id: u64,
+    pub event: String,
+    /// Serialized request body, fixed at enqueue time
+    pub payload: String,
+    pub attempts: u32,
+    pub next_attempt: u64,
+    pub last_error: Option<String>,
+}
+
+impl Delivery {
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("id".to_string(), JsonValue::Number(self.id as f64));
+        map.insert("webhook_id".to_string(), JsonValue::Number(self.webhook_id as f64));
+        map.insert("event".to_string(), JsonValue::String(self.event.clone()));
+        map.insert("payload".to_string(), JsonValue::String(self.payload.clone()));
+        map.insert("attempts".to_string(), JsonValue::Number(self.attempts as f64));
+        map.insert("next_attempt".to_string(), JsonValue::Number(self.next_attempt as f64));
+        map.insert(
+            "last_error".to_string(),
+            self.last_error.clone().map_or(JsonValue::Null, JsonValue::String),
+        );
+        JsonValue::Object(map)
+    }
+
+    fn from_json(json: &JsonValue) -> Option<Self> {
+        let map = json.as_object()?;
+        let number = |name: &str| map.get(name).and_then(|v| v.as_f64());
+        Some(Delivery {
+            id: number("id")? as u64,
+            webhook_id: number("webhook_id")? as u64,
+            event: map.get("event")?.as_str()?.to_string(),
+            payload: map.get("payload")?.as_str()?.to_string(),
+            attempts: number("attempts").unwrap_or(0.0) as u32,
+            next_attempt: number("next_attempt").unwrap_or(0.0) as u64,
+            last_error: map.get("last_error").and_then(|v| v.as_str()).map(str::to_string),
+        })
+    }
+}
+
+pub struct WebhookStore {
+    path: PathBuf,
+    policy: RetryPolicy,
+    hooks: BTreeMap<u64, Webhook>,
+    next_id: u64,
+    queue: Vec<Delivery>,
+    dead_letters: VecDeque<Delivery>,
+    next_delivery_id: u64,
+    /// Webhooks with a delivery worker running
+    sending: HashSet<u64>,
+}
+
+impl WebhookStore {
+    /// Load subscriptions and pending deliveries from `path`, or start empty.
+    /// An unreadable file is moved aside instead of failing startup.
+    pub fn open(path: &Path, policy: RetryPolicy) -> io::Result<Self> {
+        let mut store = WebhookStore {
+            path: path.to_path_buf(),
+            policy,
+            hooks: BTreeMap::new(),
+            next_id: 1,
+            queue: Vec::new(),
+            dead_letters: VecDeque::new(),
+            next_delivery_id: 1,
+            sending: HashSet::new(),
+        };
+
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(store),
+            Err(e) => return Err(e),
+        };
+        let json = avila_json::parse(&data).ok();
+        let Some(map) = json.as_ref().and_then(|json| json.as_object()) else {
+            let aside = move_aside(path)?;
+            eprintln!("⚠️  {} is not valid JSON; moved to {}, starting without webhooks", path.display(), aside.display());
+            return Ok(store);
+        };
+        let list = |name: &str| map.get(name).and_then(|v| v.as_array()).cloned().unwrap_or_default();
+
+        for hook in list("webhooks").iter().filter_map(|h| Webhook::from_json(h).ok()) {
+            store.next_id = store.next_id.max(hook.id + 1);
+            store.hooks.insert(hook.id, hook);
+        }
+        store.queue = list("queue").iter().filter_map(Delivery::from_json).collect();
+        store.dead_letters = list("dead_letters").iter().filter_map(Delivery::from_json).collect();
+        store.next_delivery_id = store
+            .queue
+            .iter()
+            .chain(&store.dead_letters)
+            .map(|d| d.id + 1)
+            .max()
+            .unwrap_or(1);
+        if let Some(next) = map.get("next_id").and_then(|v| v.as_f64()) {
+            store.next_id = store.next_id.max(next as u64);
+        }
+        Ok(store)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut map = HashMap::new();
+        map.insert("next_id".to_string(), JsonValue::Number(self.next_id as f64));
+        map.insert(
+            "webhooks".to_string(),
+            JsonValue::Array(self.hooks.values().map(Webhook::to_stored_json).collect()),
+        );
+        map.insert("queue".to_string(), JsonValue::Array(self.queue.iter().map(Delivery::to_json).collect()));
+        map.insert(
+            "dead_letters".to_string(),
+            JsonValue::Array(self.dead_letters.iter().map(Delivery::to_json).collect()),
+        );
+
+        write_atomic(&self.path, JsonValue::Object(map).to_string().as_bytes())
+    }
+
+    fn save_or_log(&self) {
+        if let Err(e) = self.save() {
+            eprintln!("❌ Failed to persist webhooks: {}", e);
+        }
+    }
+
+    pub fn list(&self) -> Vec<&Webhook> {
+        self.hooks.values().collect()
+    }
+
+    pub fn create(&mut self, mut hook: Webhook) -> io::Result<Webhook> {
+        hook.id = self.next_id;
+        self.next_id += 1;
+        self.hooks.insert(hook.id, hook.clone());
+        self.save()?;
+        Ok(hook)
+    }
+
+    /// Remove a subscription and its pending deliveries
+    pub fn delete(&mut self, id: u64) -> io::Result<bool> {
+        if self.hooks.remove(&id).is_none() {
+            return Ok(false);
+        }
+        self.queue.retain(|d| d.webhook_id != id);
+        self.save()?;
+        Ok(true)
+    }
+
+    pub fn pending(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn dead_letters(&self) -> impl Iterator<Item = &Delivery> {
+        self.dead_letters.iter()
+    }
+
+    /// Queue each event for every subscribed webhook, saving once for the
+    /// whole batch. Returns how many deliveries were queued.
+    pub fn enqueue(&mut self, events: Vec<(&str, JsonValue)>, now: u64) -> usize {
+        let mut queued = 0;
+        for (event, data) in events {
+            queued += self.push(event, &data, now);
+        }
+        if queued > 0 {
+            self.save_or_log();
+        }
+        queued
+    }
+
+    fn push(&mut self, event: &str, data: &JsonValue, now: u64) -> usize {
+        let targets: Vec<u64> = self.hooks.values().filter(|h| h.wants(event)).map(|h| h.id).collect();
+        for webhook_id in &targets {
+            let id = self.next_delivery_id;
+            self.next_delivery_id += 1;
+
+            let mut body = HashMap::new();
+            body.insert("id".to_string(), JsonValue::Number(id as f64));
+            body.insert("event".to_string(), JsonValue::String(event.to_string()));
+            body.insert("created".to_string(), JsonValue::Number(now as f64));
+            body.insert("data".to_string(), data.clone());
+
+            self.queue.push(Delivery {
+                id,
+                webhook_id: *webhook_id,
+                event: event.to_string(),
+                payload: JsonValue::Object(body).to_string(),
+                attempts: 0,
+                next_attempt: now,
+                last_error: None,
+            });
+        }
+        targets.len()
+    }
+
+    /// Deliveries whose retry time has come, grouped by webhook in queue order.
+    /// Webhooks already being sent to are skipped; the rest are marked as sending.
+    fn claim_due(&mut self, now: u64) -> Vec<(Webhook, Vec<Delivery>)> {
+        let mut batches: BTreeMap<u64, (Webhook, Vec<Delivery>)> = BTreeMap::new();
+        for delivery in self.queue.iter().filter(|d| d.next_attempt <= now) {
+            if self.sending.contains(&delivery.webhook_id) {
+                continue;
+            }
+            if let Some(hook) = self.hooks.get(&delivery.webhook_id) {
+                batches.entry(hook.id).or_insert_with(|| (hook.clone(), Vec::new())).1.push(delivery.clone());
+            }
+        }
+        self.sending.extend(batches.keys());
+        batches.into_values().collect()
+    }
+
+    /// Drop a delivered item, or schedule the retry / move it to the dead letters.
+    /// Does not save; each delivery worker saves once when it finishes.
+    fn record(&mut self, id: u64, result: Result<(), String>, now: u64) {
+        let index = match self.queue.iter().position(|d| d.id == id) {
+            Some(index) => index,
+            None => return,
+        };
+
+        match result {
+            Ok(()) => {
+                self.queue.remove(index);
+            }
+            Err(error) => {
+                let delivery = &mut self.queue[index];
+                delivery.attempts += 1;
+                delivery.last_error = Some(error);
+                if delivery.attempts >= self.policy.max_attempts {
+                    let dead = self.queue.remove(index);
+                    eprintln!("❌ Webhook delivery {} dead-lettered after {} attempts", dead.id, dead.attempts);
+                    self.dead_letters.push_back(dead);
+                    while self.dead_letters.len() > MAX_DEAD_LETTERS {
+                        self.dead_letters.pop_front();
+                    }
+                } else {
+                    delivery.next_attempt = now + self.policy.backoff(delivery.attempts);
+                }
+            }
+        }
+    }
+
+    /// Put a dead letter back in the queue with a fresh attempt budget
+    pub fn retry_dead_letter(&mut self, id: u64, now: u64) -> io::Result<bool> {
+        let index = match self.dead_letters.iter().position(|d| d.id == id) {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+        let mut delivery = self.dead_letters.remove(index).unwrap();
+        if !self.hooks.contains_key(&delivery.webhook_id) {
+            self.dead_letters.insert(index, delivery);
+            return Ok(false);
+        }
+        delivery.attempts = 0;
+        delivery.next_attempt = now;
+        self.queue.push(delivery);
+        self.save()?;
+        Ok(true)
+    }
+}
+
+/// Start one worker per webhook with due deliveries, so a slow or dead
+/// endpoint only delays itself. A worker sends in queue order and stops at the
+/// first failure; the store is unlocked while sending and saved once at the end.
+pub fn run_due(store: &Arc<Mutex<WebhookStore>>, now: u64) -> Vec<JoinHandle<()>> {
+    let batches = match store.lock() {
+        Ok(mut store) => store.claim_due(now),
+        Err(_) => return Vec::new(),
+    };
+    batches
+        .into_iter()
+        .map(|(hook, deliveries)| {
+            let store = Arc::clone(store);
+            thread::spawn(move || {
+                let mut results = Vec::new();
+                for delivery in &deliveries {
+                    let result = send(&hook, delivery, now);
+                    let failed = result.is_err();
+                    results.push((delivery.id, result));
+                    // Endpoint fora do ar: o resto espera a próxima rodada
+                    if failed {
+                        break;
+                    }
+                }
+                if let Ok(mut store) = store.lock() {
+                    for (id, result) in results {
+                        store.record(id, result, now);
+                    }
+                    store.sending.remove(&hook.id);
+                    store.save_or_log();
+                }
+            })
+        })
+        .collect()
+}
+
+/// `sha256=<hex>` of HMAC-SHA256(secret, "<timestamp>.<body>")
+pub fn signature(secret: &str, timestamp: u64, body: &str) -> String {
+    let message = format!("{}.{}", timestamp, body);
+    format!("sha256={}", hex(&hmac_sha256(secret.as_bytes(), message.as_bytes())))
+}
+
+/// POST the payload; any 2xx status is a success
+fn send(hook: &Webhook, delivery: &Delivery, now: u64) -> Result<(), String> {
+    let endpoint = Endpoint::parse(&hook.url)?;
+    let address = (endpoint.host.as_str(), endpoint.port)
+        .to_socket_addrs()
+        .map_err(|e| format!("resolve failed: {}", e))?
+        .next()
+        .ok_or("resolve failed: no address")?;
+
+    let mut stream = TcpStream::connect_timeout(&address, HTTP_TIMEOUT).map_err(|e| format!("connect failed: {}", e))?;
+    let _ = stream.set_read_timeout(Some(HTTP_TIMEOUT));
+    let _ = stream.set_write_timeout(Some(HTTP_TIMEOUT));
+
+    let host = if endpoint.host.contains(':') {
+        format!("[{}]", endpoint.host)
+    } else {
+        endpoint.host.clone()
+    };
+    let request = format!(
+        "POST {} HTTP/1.1\r\n\
+         Host: {}:{}\r\n\
+         User-Agent: device-location-tracker\r\n\
+         Content-Type: application/json\r\n\
+         Content-Length: {}\r\n\
+         X-Webhook-Id: {}\r\n\
+         X-Webhook-Event: {}\r\n\
+         X-Webhook-Timestamp: {}\r\n\
+         X-Webhook-Signature: {}\r\n\
+         Connection: close\r\n\r\n{}",
+        endpoint.path,
+        host,
+        endpoint.port,
+        delivery.payload.len(),
+        delivery.id,
+        delivery.event,
+        now,
+        signature(&hook.secret, now, &delivery.payload),
+        delivery.payload
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| format!("send failed: {}", e))?;
+
+    let mut status_line = String::new();
+    BufReader::new(&stream)
+        .read_line(&mut status_line)
+        .map_err(|e| format!("no response: {}", e))?;
+    let status: u16 = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|s| s.parse().ok())
+        .ok_or_else(|| format!("invalid response: {:?}", status_line.trim()))?;
+
+    if (200..300).contains(&status) {
+        Ok(())
+    } else {
+        Err(format!("HTTP {}", status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http;
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn temp_path(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("gps-webhook-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("webhooks.json")
+    }
+
+    /// Stand-in receiver: answers each connection with the next status and
+    /// hands back the parsed requests
+    fn receiver(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<http::Request>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().unwrap();
+                requests.push(http::read_request(&stream).unwrap());
+                write!(stream, "HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status).unwrap();
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn hook(url: &str) -> Webhook {
+        let json = avila_json::parse(&format!(
+            r#"{{"url":"{}","secret":"s3cret","events":["device.new"]}}"#,
+            url
+        ))
+        .unwrap();
+        Webhook::from_json(&json).unwrap()
+    }
+
+    /// Run one delivery pass and wait for its workers
+    fn deliver(store: &Arc<Mutex<WebhookStore>>, now: u64) {
+        for worker in run_due(store, now) {
+            worker.join().unwrap();
+        }
+    }
+
+    const POLICY: RetryPolicy = RetryPolicy { max_attempts: 2, base_backoff_secs: 5 };
+
+    #[test]
+    fn test_signed_delivery() {
+        let (url, handle) = receiver(vec![200]);
+        let store = Arc::new(Mutex::new(WebhookStore::open(&temp_path("signed"), POLICY).unwrap()));
+        store.lock().unwrap().create(hook(&url)).unwrap();
+        assert_eq!(store.lock().unwrap().enqueue(vec![("battery.low", JsonValue::Null)], 100), 0);
+        assert_eq!(store.lock().unwrap().enqueue(vec![("device.new", JsonValue::Null)], 100), 1);
+
+        deliver(&store, 100);
+        let request = &handle.join().unwrap()[0];
+        assert_eq!(request.path, "/hook");
+        assert_eq!(request.headers["x-webhook-event"], "device.new");
+        assert_eq!(request.headers["x-webhook-signature"], signature("s3cret", 100, &request.body));
+        assert_eq!(store.lock().unwrap().pending(), 0);
+    }
+
+    #[test]
+    fn test_backoff_then_dead_letter() {
+        let (url, handle) = receiver(vec![500, 503]);
+        let path = temp_path("retry");
+        let store = Arc::new(Mutex::new(WebhookStore::open(&path, POLICY).unwrap()));
+        store.lock().unwrap().create(hook(&url)).unwrap();
+        store.lock().unwrap().enqueue(vec![("device.new", JsonValue::Null)], 100);
+
+        deliver(&store, 100);
+        assert_eq!(store.lock().unwrap().queue[0].next_attempt, 105);
+        deliver(&store, 104); // ainda não venceu
+        assert_eq!(store.lock().unwrap().queue[0].attempts, 1);
+
+        deliver(&store, 105);
+        handle.join().unwrap();
+        let dead: Vec<Delivery> = store.lock().unwrap().dead_letters().cloned().collect();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].last_error.as_deref(), Some("HTTP 503"));
+
+        // Dead letters e assinaturas sobrevivem a um reinício
+        let mut reopened = WebhookStore::open(&path, POLICY).unwrap();
+        assert_eq!(reopened.dead_letters().count(), 1);
+        assert!(reopened.retry_dead_letter(dead[0].id, 200).unwrap());
+        assert_eq!(reopened.pending(), 1);
+    }
+
+    #[test]
+    fn test_dead_endpoint_does_not_block_others() {
+        // Aceita a conexão (backlog) mas nunca responde
+        let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+        let (url, handle) = receiver(vec![200, 200]);
+        let store = Arc::new(Mutex::new(WebhookStore::open(&temp_path("dead"), POLICY).unwrap()));
+        store.lock().unwrap().create(hook(&url)).unwrap();
+        store.lock().unwrap().create(hook(&format!("http://{}/hook", silent.local_addr().unwrap()))).unwrap();
+        store.lock().unwrap().enqueue(vec![("device.new", JsonValue::Null), ("device.new", JsonValue::Null)], 100);
+
+        let mut workers = run_due(&store, 100);
+        assert_eq!(workers.len(), 2);
+        workers.remove(0).join().unwrap();
+        assert_eq!(handle.join().unwrap().len(), 2);
+        assert_eq!(store.lock().unwrap().pending(), 2);
+        // O webhook mudo ainda tem um worker: não é reenviado em paralelo
+        assert!(run_due(&store, 101).is_empty());
+    }
+
+    #[test]
+    fn test_recovers_from_corrupt_file() {
+        let path = temp_path("corrupt");
+        fs::write(&path, "{not json").unwrap();
+
+        let mut store = WebhookStore::open(&path, POLICY).unwrap();
+        assert_eq!(store.list().len(), 0);
+        assert_eq!(fs::read_to_string(path.with_extension("json.corrupt")).unwrap(), "{not json");
+
+        store.create(hook("http://127.0.0.1:9/hook")).unwrap();
+        assert_eq!(store.enqueue(vec![("device.new", JsonValue::Null), ("battery.low", JsonValue::Null)], 100), 1);
+        assert_eq!(WebhookStore::open(&path, POLICY).unwrap().pending(), 1);
+    }
+
+    #[test]
+    fn test_backoff_and_endpoint() {
+        let policy = RetryPolicy { max_attempts: 20, base_backoff_secs: 5 };
+        assert_eq!((1..=4).map(|n| policy.backoff(n)).collect::<Vec<_>>(), [5, 10, 20, 40]);
+        assert_eq!(policy.backoff(15), MAX_BACKOFF_SECS);
+
+        assert_eq!(
+            Endpoint::parse("http://dispatch.local:8081/hooks/gps").unwrap(),
+            Endpoint { host: "dispatch.local".to_string(), port: 8081, path: "/hooks/gps".to_string() }
+        );
+        assert_eq!(
+            Endpoint::parse("http://[::1]:8080/").unwrap(),
+            Endpoint { host: "::1".to_string(), port: 8080, path: "/".to_string() }
+        );
+        assert_eq!(Endpoint::parse("http://[fe80::1]/hook").unwrap().port, 80);
+        assert!(Endpoint::parse("http://[::1:8080/").is_err());
+        assert!(Endpoint::parse("http://[::1]8080/").is_err());
+        assert!(Endpoint::parse("https://example.com").is_err());
+    }
+}
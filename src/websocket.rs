@@ -0,0 +1,389 @@
+// WebSocket (RFC 6455) - handshake, framing e mensagens, std puro
+// Handshake via SHA-1/Base64 nativos (crate::crypto)
+
+use crate::crypto::{base64_encode, sha1};
+use avila_json::JsonValue;
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, Read, Write};
+
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Upper bound on a single message (all fragments together)
+const MAX_MESSAGE: usize = 1024 * 1024;
+
+pub const CLOSE_NORMAL: u16 = 1000;
+pub const CLOSE_GOING_AWAY: u16 = 1001;
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+pub const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+pub const CLOSE_INVALID_PAYLOAD: u16 = 1007;
+pub const CLOSE_TOO_BIG: u16 = 1009;
+
+/// True for a GET carrying `Upgrade: websocket` and `Connection: Upgrade`
+pub fn is_upgrade(headers: &HashMap<String, String>) -> bool {
+    let has_token = |name: &str, token: &str| {
+        headers
+            .get(name)
+            .is_some_and(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+    };
+    has_token("upgrade", "websocket") && has_token("connection", "upgrade")
+}
+
+/// `Sec-WebSocket-Accept` value for a client key
+pub fn accept_key(key: &str) -> String {
+    let mut input = key.trim().as_bytes().to_vec();
+    input.extend_from_slice(GUID.as_bytes());
+    base64_encode(&sha1(&input))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0x0 => Some(Opcode::Continuation),
+            0x1 => Some(Opcode::Text),
+            0x2 => Some(Opcode::Binary),
+            0x8 => Some(Opcode::Close),
+            0x9 => Some(Opcode::Ping),
+            0xA => Some(Opcode::Pong),
+            _ => None,
+        }
+    }
+
+    fn bits(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+#[derive(Debug)]
+pub enum FrameError {
+    Io(io::Error),
+    Protocol(&'static str),
+    InvalidUtf8,
+    TooBig,
+}
+
+impl FrameError {
+    /// Close code to send before dropping the connection; `None` if the socket is gone
+    pub fn close_code(&self) -> Option<u16> {
+        match self {
+            // Timeout de leitura: cliente mudo, encerramos nós
+            FrameError::Io(e) if matches!(e.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => {
+                Some(CLOSE_GOING_AWAY)
+            }
+            FrameError::Io(_) => None,
+            FrameError::Protocol(_) => Some(CLOSE_PROTOCOL_ERROR),
+            FrameError::InvalidUtf8 => Some(CLOSE_INVALID_PAYLOAD),
+            FrameError::TooBig => Some(CLOSE_TOO_BIG),
+        }
+    }
+
+    pub fn reason(&self) -> &'static str {
+        match self {
+            FrameError::Io(_) => "idle timeout",
+            FrameError::Protocol(reason) => reason,
+            FrameError::InvalidUtf8 => "invalid UTF-8",
+            FrameError::TooBig => "message too big",
+        }
+    }
+}
+
+impl From<io::Error> for FrameError {
+    fn from(e: io::Error) -> Self {
+        FrameError::Io(e)
+    }
+}
+
+#[derive(Debug)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+/// Read one client frame. Client frames must be masked (RFC 6455 §5.1).
+pub fn read_frame<R: Read>(reader: &mut R) -> Result<Frame, FrameError> {
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header)?;
+
+    let fin = header[0] & 0x80 != 0;
+    if header[0] & 0x70 != 0 {
+        return Err(FrameError::Protocol("reserved bits set"));
+    }
+    let opcode = Opcode::from_bits(header[0] & 0x0F).ok_or(FrameError::Protocol("unknown opcode"))?;
+    if header[1] & 0x80 == 0 {
+        return Err(FrameError::Protocol("client frames must be masked"));
+    }
+
+    let len = match header[1] & 0x7F {
+        126 => {
+            let mut ext = [0u8; 2];
+            reader.read_exact(&mut ext)?;
+            u16::from_be_bytes(ext) as u64
+        }
+        127 => {
+            let mut ext = [0u8; 8];
+            reader.read_exact(&mut ext)?;
+            u64::from_be_bytes(ext)
+        }
+        n => n as u64,
+    };
+    if opcode.is_control() && (len > 125 || !fin) {
+        return Err(FrameError::Protocol("invalid control frame"));
+    }
+    if len > MAX_MESSAGE as u64 {
+        return Err(FrameError::TooBig);
+    }
+
+    let mut mask = [0u8; 4];
+    reader.read_exact(&mut mask)?;
+    let mut payload = vec![0u8; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok(Frame { fin, opcode, payload })
+}
+
+/// Write one unmasked, unfragmented server frame
+pub fn write_frame<W: Write>(writer: &mut W, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | opcode.bits());
+    match payload.len() {
+        n if n < 126 => frame.push(n as u8),
+        n if n <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(n as u16).to_be_bytes());
+        }
+        n => {
+            frame.push(127);
+            frame.extend_from_slice(&(n as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Code to echo back for a peer's Close frame (RFC 6455 §7.4). Codes that
+/// must never appear on the wire, such as 1005, 1006 and 1015, become 1002.
+pub fn echo_close_code(code: Option<u16>) -> u16 {
+    match code {
+        None => CLOSE_NORMAL,
+        Some(code @ (1000..=1003 | 1007..=1014 | 3000..=4999)) => code,
+        Some(_) => CLOSE_PROTOCOL_ERROR,
+    }
+}
+
+pub fn write_close<W: Write>(writer: &mut W, code: u16, reason: &str) -> io::Result<()> {
+    let mut payload = code.to_be_bytes().to_vec();
+    payload.extend_from_slice(reason.as_bytes());
+    write_frame(writer, Opcode::Close, &payload)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong,
+    /// Close code sent by the peer, if any
+    Close(Option<u16>),
+}
+
+/// Reassembles fragmented messages; control frames may arrive between fragments
+#[derive(Default)]
+pub struct MessageReader {
+    partial: Option<(Opcode, Vec<u8>)>,
+}
+
+impl MessageReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next<R: Read>(&mut self, reader: &mut R) -> Result<Message, FrameError> {
+        loop {
+            let frame = read_frame(reader)?;
+            let (opcode, payload) = match frame.opcode {
+                Opcode::Ping => return Ok(Message::Ping(frame.payload)),
+                Opcode::Pong => return Ok(Message::Pong),
+                Opcode::Close => {
+                    let code = (frame.payload.len() >= 2)
+                        .then(|| u16::from_be_bytes([frame.payload[0], frame.payload[1]]));
+                    return Ok(Message::Close(code));
+                }
+                Opcode::Continuation => {
+                    let (opcode, mut buffer) =
+                        self.partial.take().ok_or(FrameError::Protocol("unexpected continuation"))?;
+                    if buffer.len() + frame.payload.len() > MAX_MESSAGE {
+                        return Err(FrameError::TooBig);
+                    }
+                    buffer.extend_from_slice(&frame.payload);
+                    (opcode, buffer)
+                }
+                opcode => {
+                    if self.partial.is_some() {
+                        return Err(FrameError::Protocol("expected continuation"));
+                    }
+                    (opcode, frame.payload)
+                }
+            };
+
+            if !frame.fin {
+                self.partial = Some((opcode, payload));
+                continue;
+            }
+            return match opcode {
+                Opcode::Text => String::from_utf8(payload)
+                    .map(Message::Text)
+                    .map_err(|_| FrameError::InvalidUtf8),
+                _ => Ok(Message::Binary(payload)),
+            };
+        }
+    }
+}
+
+/// Devices a connection receives `location` events for
+#[derive(Debug, Default)]
+pub struct Subscription {
+    all: bool,
+    devices: BTreeSet<String>,
+}
+
+impl Subscription {
+    /// `None` subscribes to every device
+    pub fn subscribe(&mut self, devices: Option<Vec<String>>) {
+        match devices {
+            Some(devices) => self.devices.extend(devices),
+            None => self.all = true,
+        }
+    }
+
+    /// `None` drops every subscription
+    pub fn unsubscribe(&mut self, devices: Option<Vec<String>>) {
+        match devices {
+            Some(devices) => {
+                for device in devices {
+                    self.devices.remove(&device);
+                }
+            }
+            None => {
+                self.all = false;
+                self.devices.clear();
+            }
+        }
+    }
+
+    pub fn matches(&self, device: &str) -> bool {
+        self.all || self.devices.contains(device)
+    }
+
+    pub fn to_json(&self) -> JsonValue {
+        let mut map = HashMap::new();
+        map.insert("type".to_string(), JsonValue::String("subscription".to_string()));
+        map.insert("all".to_string(), JsonValue::Bool(self.all));
+        map.insert(
+            "devices".to_string(),
+            JsonValue::Array(self.devices.iter().cloned().map(JsonValue::String).collect()),
+        );
+        JsonValue::Object(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn masked(fin: bool, opcode: Opcode, payload: &[u8]) -> Vec<u8> {
+        let mask = [0x37, 0xfa, 0x21, 0x3d];
+        let mut frame = vec![(if fin { 0x80 } else { 0 }) | opcode.bits(), 0x80 | payload.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        frame
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // Exemplo da RFC 6455, seção 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+
+    #[test]
+    fn test_fragmented_message_with_ping() {
+        let mut input = masked(false, Opcode::Text, b"Hel");
+        input.extend(masked(true, Opcode::Ping, b"hi"));
+        input.extend(masked(true, Opcode::Continuation, b"lo"));
+        input.extend(masked(true, Opcode::Close, &CLOSE_GOING_AWAY.to_be_bytes()));
+
+        let mut reader = MessageReader::new();
+        let mut cursor = io::Cursor::new(input);
+        assert_eq!(reader.next(&mut cursor).unwrap(), Message::Ping(b"hi".to_vec()));
+        assert_eq!(reader.next(&mut cursor).unwrap(), Message::Text("Hello".to_string()));
+        assert_eq!(reader.next(&mut cursor).unwrap(), Message::Close(Some(CLOSE_GOING_AWAY)));
+    }
+
+    #[test]
+    fn test_protocol_errors() {
+        let unmasked = [0x81u8, 0x01, b'x'];
+        let err = read_frame(&mut io::Cursor::new(unmasked)).unwrap_err();
+        assert_eq!(err.close_code(), Some(CLOSE_PROTOCOL_ERROR));
+
+        let invalid = masked(true, Opcode::Text, &[0xff, 0xfe]);
+        let err = MessageReader::new().next(&mut io::Cursor::new(invalid)).unwrap_err();
+        assert_eq!(err.close_code(), Some(CLOSE_INVALID_PAYLOAD));
+    }
+
+    #[test]
+    fn test_echo_close_code() {
+        assert_eq!(echo_close_code(None), CLOSE_NORMAL);
+        assert_eq!(echo_close_code(Some(CLOSE_GOING_AWAY)), CLOSE_GOING_AWAY);
+        assert_eq!(echo_close_code(Some(4000)), 4000);
+        for reserved in [0, 999, 1004, 1005, 1006, 1015, 2000, 5000] {
+            assert_eq!(echo_close_code(Some(reserved)), CLOSE_PROTOCOL_ERROR);
+        }
+    }
+
+    #[test]
+    fn test_server_frame_lengths() {
+        let mut out = Vec::new();
+        write_frame(&mut out, Opcode::Text, &[b'a'; 300]).unwrap();
+        assert_eq!(&out[..4], &[0x81, 126, 0x01, 0x2c]);
+        assert_eq!(out.len(), 304);
+    }
+
+    #[test]
+    fn test_subscription() {
+        let mut subscription = Subscription::default();
+        subscription.subscribe(Some(vec!["a".to_string(), "b".to_string()]));
+        assert!(subscription.matches("a") && !subscription.matches("c"));
+
+        subscription.unsubscribe(Some(vec!["a".to_string()]));
+        assert!(!subscription.matches("a") && subscription.matches("b"));
+
+        subscription.subscribe(None);
+        assert!(subscription.matches("c"));
+        subscription.unsubscribe(None);
+        assert!(!subscription.matches("b"));
+    }
+}